@@ -0,0 +1,92 @@
+use criterion::{BenchmarkId, Criterion, Throughput, criterion_group, criterion_main};
+use memutil::scan;
+
+const BUFFER_LEN: usize = 16 * 1024 * 1024;
+const LARGE_BUFFER_LEN: usize = 256 * 1024 * 1024;
+
+/// pseudo-random bytes, so concrete pattern bytes only match occasionally like in real code
+fn buffer() -> Vec<u8> {
+    random_bytes(BUFFER_LEN)
+}
+
+fn random_bytes(len: usize) -> Vec<u8> {
+    let mut state: u32 = 0x1234_5678;
+    (0..len)
+        .map(|_| {
+            state ^= state << 13;
+            state ^= state >> 17;
+            state ^= state << 5;
+            state as u8
+        })
+        .collect()
+}
+
+fn signatures() -> [(&'static str, Vec<Option<u8>>); 3] {
+    [
+        ("all_wildcard", vec![None; 8]),
+        (
+            "leading_concrete",
+            vec![Some(0x48), Some(0x8B), Some(0x05), None, None, None, None],
+        ),
+        (
+            "trailing_concrete",
+            vec![None, None, None, None, Some(0x48), Some(0x85), Some(0xC0)],
+        ),
+    ]
+}
+
+fn bench_signature_matches(c: &mut Criterion) {
+    let data = buffer();
+
+    let mut group = c.benchmark_group("signature_matches");
+    group.throughput(Throughput::Bytes(data.len() as u64));
+    for (name, pattern) in signatures() {
+        group.bench_with_input(BenchmarkId::from_parameter(name), &pattern, |b, pattern| {
+            b.iter(|| scan::signature_matches(&data, pattern).count());
+        });
+    }
+    group.finish();
+}
+
+/// the byte-by-byte comparison at every offset that `signature_matches` used before
+fn naive_signature_matches(data: &[u8], pattern: &[Option<u8>]) -> impl Iterator<Item = usize> {
+    data.windows(pattern.len().max(1))
+        .enumerate()
+        .filter(move |(_, window)| {
+            window
+                .iter()
+                .zip(pattern)
+                .all(|(byte, pattern_byte)| pattern_byte.is_none_or(|b| b == *byte))
+        })
+        .map(|(offset, _)| offset)
+}
+
+/// a typical code signature planted a few kb before the end of 256 mb, like a function near the
+/// end of a large library
+fn bench_naive_vs_horspool(c: &mut Criterion) {
+    let bytes = [
+        0x48, 0x8B, 0x05, 0x00, 0x00, 0x00, 0x00, 0x48, 0x85, 0xC0, 0x74, 0x12,
+    ];
+    let pattern: Vec<Option<u8>> = bytes
+        .iter()
+        .enumerate()
+        .map(|(i, &byte)| (!(3..7).contains(&i)).then_some(byte))
+        .collect();
+    let mut data = random_bytes(LARGE_BUFFER_LEN);
+    let planted = LARGE_BUFFER_LEN - 4096;
+    data[planted..planted + bytes.len()].copy_from_slice(&bytes);
+
+    let mut group = c.benchmark_group("signature_near_end");
+    group.sample_size(10);
+    group.throughput(Throughput::Bytes(data.len() as u64));
+    group.bench_function("naive", |b| {
+        b.iter(|| naive_signature_matches(&data, &pattern).next());
+    });
+    group.bench_function("horspool", |b| {
+        b.iter(|| scan::signature_matches(&data, &pattern).next());
+    });
+    group.finish();
+}
+
+criterion_group!(benches, bench_signature_matches, bench_naive_vs_horspool);
+criterion_main!(benches);
@@ -0,0 +1,39 @@
+use criterion::{Criterion, criterion_group, criterion_main};
+use memutil::Memory;
+
+/// the process to look functions up in. point `MEMUTIL_BENCH_PID` at a target mapping many
+/// shared libraries, the benchmark process itself maps only a few
+fn target_pid() -> i32 {
+    std::env::var("MEMUTIL_BENCH_PID")
+        .ok()
+        .and_then(|pid| pid.parse().ok())
+        .unwrap_or_else(|| std::process::id().cast_signed())
+}
+
+/// a missing function makes every module's symbols be searched
+fn bench_find_function(c: &mut Criterion) {
+    let pid = target_pid();
+    let memory = Memory::new(pid).unwrap();
+
+    let mut group = c.benchmark_group("find_function");
+    group.sample_size(10);
+    group.bench_function("fresh", |b| {
+        b.iter(|| {
+            Memory::new(pid)
+                .unwrap()
+                .find_function("memutil_bench_missing", false)
+                .unwrap()
+        });
+    });
+    group.bench_function("reused", |b| {
+        b.iter(|| {
+            memory
+                .find_function("memutil_bench_missing", false)
+                .unwrap()
+        });
+    });
+    group.finish();
+}
+
+criterion_group!(benches, bench_find_function);
+criterion_main!(benches);
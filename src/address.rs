@@ -2,11 +2,34 @@ use std::fmt::Display;
 
 use crate::memory::{Memory, MemoryError};
 
+/// a signature match and the address resolved from it
+pub type ResolvedMatch = (usize, Result<usize, MemoryError>);
+
+/// remembers where a locator's signature matched, so repeated resolves only re-walk the
+/// pointer chain instead of rescanning
+#[derive(Debug, Default)]
+pub struct ResolveCache {
+    match_address: Option<usize>,
+}
+
+impl ResolveCache {
+    pub fn clear(&mut self) {
+        self.match_address = None;
+    }
+}
+
 #[derive(Debug, Clone)]
 pub enum AddressLocator {
     Absolute(usize),
     Pattern(IdaSignature),
     PointerChain(IdaSignature, Vec<usize>),
+    /// offset from a labelled region like `[heap]` or `[stack]`
+    Region(String, usize),
+    /// offset from an exported function, optionally followed by a pointer chain
+    Function(String, usize, Vec<usize>),
+    /// offset from the base of a mapped module like `libclient.so`, optionally followed by a
+    /// pointer chain
+    ModuleOffset(String, usize, Vec<usize>),
 }
 
 impl AddressLocator {
@@ -19,47 +42,243 @@ impl AddressLocator {
                     Err(MemoryError::InvalidPointer(*address))
                 }
             }
-            AddressLocator::Pattern(signature) => self.resolve_signature(memory, signature),
-            AddressLocator::PointerChain(signature, pointers) => {
-                let base_address = self.resolve_signature(memory, signature)?;
-
-                if pointers.is_empty() {
-                    return Ok(base_address);
+            AddressLocator::Pattern(signature) | AddressLocator::PointerChain(signature, _) => {
+                let Some(match_address) = memory.scan_signature(signature)? else {
+                    return Err(MemoryError::SignatureNotFound(signature.clone()));
+                };
+                self.resolve_match(memory, match_address)
+            }
+            AddressLocator::Region(name, offset) => {
+                let base = Self::region_base(memory, name)?;
+                let address = base
+                    .checked_add(*offset)
+                    .ok_or(MemoryError::InvalidPointer(base))?;
+                if memory.is_pointer_valid(address) {
+                    Ok(address)
+                } else {
+                    Err(MemoryError::InvalidPointer(address))
                 }
+            }
+            AddressLocator::Function(name, offset, pointers) => {
+                let function = memory.function_address(name)?;
+                let base_address = function
+                    .checked_add(*offset)
+                    .ok_or(MemoryError::InvalidPointer(function))?;
+                Self::follow_pointers(memory, base_address, pointers)
+            }
+            AddressLocator::ModuleOffset(name, offset, pointers) => {
+                let base = Self::module_base(memory, name)?;
+                let base_address = base
+                    .checked_add(*offset)
+                    .ok_or(MemoryError::InvalidPointer(base))?;
+                if !memory.is_pointer_valid(base_address) {
+                    return Err(MemoryError::InvalidPointer(base_address));
+                }
+                Self::follow_pointers(memory, base_address, pointers)
+            }
+        }
+    }
+
+    /// like `resolve`, but reuses the signature match stored in `cache`. the cache is cleared
+    /// when resolving fails, so the next call scans again
+    pub fn resolve_cached(
+        &self,
+        memory: &Memory,
+        cache: &mut ResolveCache,
+    ) -> Result<usize, MemoryError> {
+        let Some(signature) = self.signature() else {
+            return self.resolve(memory);
+        };
 
-                let mut address = base_address;
-                let (deref_pointers, final_offset) = pointers.split_at(pointers.len() - 1);
+        let match_address = match cache.match_address {
+            Some(match_address) => match_address,
+            None => memory
+                .scan_signature(signature)?
+                .ok_or_else(|| MemoryError::SignatureNotFound(signature.clone()))?,
+        };
 
-                for &offset in deref_pointers {
-                    let new_address: usize = memory.read(address + offset)?;
-                    if !memory.is_pointer_valid(new_address) {
-                        return Err(MemoryError::InvalidPointer(new_address));
-                    }
-                    address = new_address;
+        let result = self.resolve_match(memory, match_address);
+        cache.match_address = result.is_ok().then_some(match_address);
+        result
+    }
+
+    /// resolves from every signature match, pairing each match with the address it leads to
+    pub fn resolve_all(&self, memory: &Memory) -> Result<Vec<ResolvedMatch>, MemoryError> {
+        match self {
+            AddressLocator::Absolute(address) => Ok(vec![(*address, self.resolve(memory))]),
+            AddressLocator::Region(..)
+            | AddressLocator::Function(..)
+            | AddressLocator::ModuleOffset(..) => {
+                let address = self.resolve(memory)?;
+                Ok(vec![(address, Ok(address))])
+            }
+            AddressLocator::Pattern(signature) | AddressLocator::PointerChain(signature, _) => {
+                let matches = memory.scan_signature_all(signature)?;
+                if matches.is_empty() {
+                    return Err(MemoryError::SignatureNotFound(signature.clone()));
                 }
 
-                Ok(address + final_offset[0])
+                Ok(matches
+                    .into_iter()
+                    .map(|match_address| (match_address, self.resolve_match(memory, match_address)))
+                    .collect())
+            }
+        }
+    }
+
+    /// applies the signature offset and pointer chain to a single signature match
+    fn resolve_match(&self, memory: &Memory, match_address: usize) -> Result<usize, MemoryError> {
+        match self {
+            AddressLocator::Absolute(address) => Ok(*address),
+            AddressLocator::Region(..)
+            | AddressLocator::Function(..)
+            | AddressLocator::ModuleOffset(..) => self.resolve(memory),
+            AddressLocator::Pattern(signature) => {
+                self.resolve_signature(memory, signature, match_address)
+            }
+            AddressLocator::PointerChain(signature, pointers) => {
+                let base_address = self.resolve_signature(memory, signature, match_address)?;
+                Self::follow_pointers(memory, base_address, pointers)
+            }
+        }
+    }
+
+    /// dereferences every offset but the last, which is added to the final pointer
+    fn follow_pointers(
+        memory: &Memory,
+        base_address: usize,
+        pointers: &[usize],
+    ) -> Result<usize, MemoryError> {
+        let Some((final_offset, deref_pointers)) = pointers.split_last() else {
+            return Ok(base_address);
+        };
+
+        let mut address = base_address;
+        for &offset in deref_pointers {
+            let new_address: usize = memory.read(address + offset)?;
+            if !memory.is_pointer_valid(new_address) {
+                return Err(MemoryError::InvalidPointer(new_address));
+            }
+            address = new_address;
+        }
+
+        Ok(address + final_offset)
+    }
+
+    /// the start of the function symbol containing `address`, so reads stay at the same place
+    /// when code around the match shifts but the symbol is still there
+    pub fn rebase_to_symbol(memory: &Memory, address: usize) -> Result<usize, MemoryError> {
+        memory
+            .symbol_for_address(address)?
+            .map(|symbol| symbol.address)
+            .ok_or(MemoryError::NoSymbol(address))
+    }
+
+    pub fn signature(&self) -> Option<&IdaSignature> {
+        match self {
+            AddressLocator::Absolute(_)
+            | AddressLocator::Region(..)
+            | AddressLocator::Function(..)
+            | AddressLocator::ModuleOffset(..) => None,
+            AddressLocator::Pattern(signature) | AddressLocator::PointerChain(signature, _) => {
+                Some(signature)
             }
         }
     }
 
+    /// `address` relative to the module or labelled region containing it, like `libc.so.6+0x10`
+    /// or `[heap]+0x10`, which still finds it after a restart as long as the layout inside
+    /// stays the same. anonymous memory and ambiguous labels have no such locator
+    pub fn relative_to_region(memory: &Memory, address: usize) -> Option<AddressLocator> {
+        let pathname = &memory.find_containing_region(address)?.pathname;
+        if pathname.starts_with('[') {
+            let base = Self::region_base(memory, pathname).ok()?;
+            return Some(AddressLocator::Region(pathname.clone(), address - base));
+        }
+
+        let file_name = pathname
+            .rsplit('/')
+            .next()
+            .filter(|_| pathname.starts_with('/'))?;
+        // another file with the same name makes it ambiguous, the full path never is
+        let name = match Self::module_base(memory, file_name) {
+            Ok(_) => file_name,
+            Err(_) => pathname,
+        };
+        let base = Self::module_base(memory, name).ok()?;
+        Some(AddressLocator::ModuleOffset(
+            name.to_string(),
+            address - base,
+            Vec::new(),
+        ))
+    }
+
+    /// start of the single region with this pathname, thread stacks can make `[stack]` ambiguous
+    fn region_base(memory: &Memory, name: &str) -> Result<usize, MemoryError> {
+        let regions: Vec<_> = memory
+            .memory_regions()
+            .iter()
+            .filter(|region| region.pathname == name)
+            .collect();
+
+        match regions.as_slice() {
+            [] => Err(MemoryError::NamedRegionNotFound(name.to_string())),
+            [region] => Ok(region.start),
+            _ => Err(MemoryError::AmbiguousRegion(
+                name.to_string(),
+                regions.len(),
+            )),
+        }
+    }
+
+    /// lowest start of the regions whose pathname ends with `name`, as long as they all belong
+    /// to the same file
+    fn module_base(memory: &Memory, name: &str) -> Result<usize, MemoryError> {
+        let regions: Vec<_> = memory
+            .memory_regions()
+            .iter()
+            .filter(|region| region.pathname.ends_with(name))
+            .collect();
+
+        let Some(first) = regions.first() else {
+            return Err(MemoryError::ModuleNotFound(name.to_string()));
+        };
+        let mut pathnames: Vec<_> = regions.iter().map(|region| &region.pathname).collect();
+        pathnames.sort();
+        pathnames.dedup();
+        if pathnames.len() > 1 {
+            return Err(MemoryError::AmbiguousRegion(
+                name.to_string(),
+                pathnames.len(),
+            ));
+        }
+
+        // maps are listed by address, so the first region is the base
+        Ok(first.start)
+    }
+
     fn resolve_signature(
         &self,
         memory: &Memory,
         signature: &IdaSignature,
+        base_address: usize,
     ) -> Result<usize, MemoryError> {
-        let Some(base_address) = memory.scan_signature(signature)? else {
-            return Err(MemoryError::SignatureNotFound(signature.clone()));
+        let Some(offset) = &signature.offset else {
+            return Ok(base_address);
         };
 
-        if let Some(offset) = &signature.offset {
-            let rip_address: i32 = memory.read(base_address + offset.offset)?;
-            Ok(base_address
-                .wrapping_add_signed(rip_address as isize)
-                .wrapping_add(offset.instruction_size))
-        } else {
-            Ok(base_address)
+        let rip_address: i32 = memory.read(base_address + offset.offset)?;
+        let address = base_address
+            .wrapping_add_signed(rip_address as isize)
+            .wrapping_add(offset.instruction_size);
+
+        let is_code = memory
+            .find_containing_region(address)
+            .is_some_and(|region| region.permissions.execute);
+        if offset.branch && !is_code {
+            return Err(MemoryError::NotExecutable(address));
         }
+        Ok(address)
     }
 }
 
@@ -68,18 +287,36 @@ impl Display for AddressLocator {
         match self {
             Self::Absolute(address) => write!(f, "0x{address:X}"),
             Self::Pattern(signature) => write!(f, "{signature}"),
+            Self::Region(name, offset) => write!(f, "{name}+0x{offset:X}"),
             Self::PointerChain(signature, pointers) => {
-                let pointer_str = pointers
-                    .iter()
-                    .map(|pointer| format!("0x{pointer:X}"))
-                    .collect::<Vec<_>>()
-                    .join(" -> ");
-                write!(f, "{signature} {pointer_str}")
+                write!(f, "{signature} {}", format_pointers(pointers))
+            }
+            Self::Function(name, offset, pointers) => {
+                write!(f, "func:{name}+0x{offset:X}")?;
+                if !pointers.is_empty() {
+                    write!(f, " -> {}", format_pointers(pointers))?;
+                }
+                Ok(())
+            }
+            Self::ModuleOffset(name, offset, pointers) => {
+                write!(f, "{name}+0x{offset:X}")?;
+                if !pointers.is_empty() {
+                    write!(f, " -> {}", format_pointers(pointers))?;
+                }
+                Ok(())
             }
         }
     }
 }
 
+fn format_pointers(pointers: &[usize]) -> String {
+    pointers
+        .iter()
+        .map(|pointer| format!("0x{pointer:X}"))
+        .collect::<Vec<_>>()
+        .join(" -> ")
+}
+
 #[derive(Debug, Clone)]
 pub struct IdaSignature {
     signature: Vec<Option<u8>>,
@@ -120,10 +357,28 @@ impl Display for IdaSignature {
 pub struct Offset {
     pub offset: usize,
     pub instruction_size: usize,
+    /// the displacement belongs to a relative call or jmp, so it has to lead to code
+    pub branch: bool,
+}
+
+impl Offset {
+    /// the target of a call or jmp whose 4 byte displacement is at `offset`, which ends the
+    /// instruction
+    pub fn branch(offset: usize) -> Self {
+        Self {
+            offset,
+            instruction_size: offset + 4,
+            branch: true,
+        }
+    }
 }
 
 impl Display for Offset {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "@{}/{}", self.offset, self.instruction_size)
+        if self.branch {
+            write!(f, "call@{}", self.offset)
+        } else {
+            write!(f, "@{}/{}", self.offset, self.instruction_size)
+        }
     }
 }
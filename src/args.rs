@@ -1,25 +1,103 @@
 use std::{num::ParseIntError, path::PathBuf, time::Duration};
 
+use clap::{ArgAction, builder::RangedU64ValueParser};
+
 use crate::{
     address::{AddressLocator, IdaSignature, Offset},
-    data_type::DataType,
+    commands::{scan::DEFAULT_SCAN_NAME, watch::Clock},
+    data_type::{DataType, unquote},
+    memory::{DEFAULT_MAX_STRING_LEN, MatrixOrder},
+    output::OutputFormat,
+    process::{matching_pids, processes},
 };
 
+/// most instructions `disasm` decodes in one go
+pub const MAX_DISASM_COUNT: u64 = 4096;
+
 #[derive(Debug, clap::Parser)]
 pub struct Args {
     #[command(subcommand)]
     pub command: Commands,
+    /// maximum number of elements read by string-like data types, like the bytes of a cstring
+    /// or the pointers of a ptrarray. `cstring:N` and `--max-units` override it
+    #[arg(long, global = true, default_value_t = DEFAULT_MAX_STRING_LEN)]
+    pub max_string_len: usize,
+    /// whether matrices in the target are stored row-major or column-major
+    #[arg(long, global = true, value_enum, default_value_t = MatrixOrder::Col)]
+    pub matrix_order: MatrixOrder,
+    /// stop collecting signature matches after this many
+    #[arg(long, global = true, value_parser = RangedU64ValueParser::<usize>::new().range(1..))]
+    pub max_matches: Option<usize>,
+    /// scan every readable region for signatures, not only mapped elf images
+    #[arg(long, global = true)]
+    pub scan_data: bool,
+    /// retry reads this many times while the process briefly can't be found
+    #[arg(long, global = true, default_value_t = 0)]
+    pub retry_on_esrch: u32,
+    /// fail on the first short read, without retrying or falling back to `/proc/<pid>/mem`
+    #[arg(long, global = true)]
+    pub strict: bool,
+    /// scan for each signature only once per command, assuming its match doesn't move
+    #[arg(long, global = true)]
+    pub assume_static: bool,
+    /// accept pointers into gaps between regions up to this many bytes wide
+    #[arg(long, global = true, default_value_t = 0)]
+    pub gap_tolerance: usize,
+    /// don't print warnings, only errors
+    #[arg(short, long, global = true)]
+    pub quiet: bool,
+    /// print how many matches were found after commands that list every match
+    #[arg(long, global = true)]
+    pub count_matches: bool,
+    /// log what memutil does, `-vv` for every read. `MEMUTIL_LOG` overrides this
+    #[arg(short, long, global = true, action = ArgAction::Count, conflicts_with = "quiet")]
+    pub verbose: u8,
+    /// print results as text or as json, one object per line
+    #[arg(long, global = true, value_enum, default_value_t = OutputFormat::Text)]
+    pub format: OutputFormat,
+    /// never color output, like setting `NO_COLOR`. colors are only used on terminals anyway
+    #[arg(long, global = true)]
+    pub no_color: bool,
 }
 
 #[derive(Debug, clap::Subcommand)]
 pub enum Commands {
     Read {
-        #[clap(value_parser=parse_pid)]
-        pid: i32,
+        /// one pid, or several separated by commas to compare processes
+        #[clap(value_parser=parse_pid_list)]
+        pids: PidList,
         #[clap(value_parser=parse_address_locator)]
         address: AddressLocator,
-        #[clap(value_parser=parse_data_type)]
-        data_type: DataType,
+        #[clap(value_parser=parse_data_type, required_unless_present = "hex")]
+        data_type: Option<DataType>,
+        /// hexdump this many bytes instead of reading a value
+        #[arg(long, value_parser = parse_size, conflicts_with_all = ["data_type", "show_bytes"])]
+        hex: Option<usize>,
+        /// stop the main thread of the process while reading
+        #[arg(long)]
+        stop: bool,
+        /// stop every thread of the process while reading, not only the main thread
+        #[arg(long, conflicts_with = "stop")]
+        stop_all: bool,
+        /// read again every time this file is modified, until ctrl-c
+        #[arg(long)]
+        watch_file: Option<PathBuf>,
+        /// read at the start of the function symbol containing the resolved address, an
+        /// `--index` counts from there
+        #[arg(long)]
+        rebase_to_symbol: bool,
+        /// read the array element at this index, relative to the resolved address
+        #[arg(long, requires = "stride")]
+        index: Option<usize>,
+        /// distance in bytes between array elements
+        #[arg(long, requires = "index")]
+        stride: Option<usize>,
+        /// print the raw bytes the value was decoded from after it
+        #[arg(long)]
+        show_bytes: bool,
+        /// most utf-16 code units a wstring is read up to, only valid with `wstring`
+        #[arg(long, conflicts_with = "hex")]
+        max_units: Option<usize>,
     },
     Watch {
         #[clap(value_parser=parse_pid)]
@@ -31,78 +109,437 @@ pub enum Commands {
         #[clap(value_parser=parse_duration)]
         #[arg(short, long, default_value = "1s")]
         interval: Duration,
+        /// print min, max and mean of numeric values when the watch ends
+        #[arg(long)]
+        aggregate: bool,
+        /// write the last sampled value to this file as json when the watch ends
+        #[arg(long)]
+        snapshot_on_exit: Option<PathBuf>,
+        /// rescan the signature on every sample instead of reusing the first match
+        #[arg(long)]
+        refresh: bool,
+        /// move each sample randomly by up to this much, so values changing on a timer close to
+        /// the interval aren't always sampled at the same phase. samples are less regular
+        #[arg(long, value_parser = parse_duration)]
+        jitter: Option<Duration>,
+        /// print the raw bytes each value was decoded from after it
+        #[arg(long)]
+        show_bytes: bool,
+        /// only print samples that differ from the previous one, with the time they were read
+        #[arg(long)]
+        on_change: bool,
+        /// how far floats have to move before `--on-change` counts them as changed
+        #[arg(long, default_value_t = 1e-6, requires = "on_change")]
+        epsilon: f64,
+        /// append every sample to this csv file, samples are still printed unless `--quiet` is given
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+        /// alert when the value rises above this bound
+        #[arg(long, allow_hyphen_values = true)]
+        alert_above: Option<String>,
+        /// alert when the value drops below this bound
+        #[arg(long, allow_hyphen_values = true)]
+        alert_below: Option<String>,
+        /// ring the terminal bell with every alert
+        #[arg(long)]
+        bell: bool,
+        /// timestamp every sample, with the wall clock or in seconds since the watch started
+        #[arg(long, value_enum)]
+        clock: Option<Clock>,
     },
     Find {
+        #[clap(value_parser=parse_pid)]
+        pid: i32,
+        #[clap(value_parser=parse_address_locator, required_unless_present = "sig_file")]
+        address: Option<AddressLocator>,
+        /// read the pattern from a file instead, `#` comment lines are skipped
+        #[arg(long, value_parser = parse_signature_file, conflicts_with = "address")]
+        sig_file: Option<IdaSignature>,
+        /// print the matched bytes, with wildcard positions in brackets
+        #[arg(long)]
+        print_bytes: bool,
+        /// hexdump this many bytes before and after the match
+        #[arg(long)]
+        context: Option<usize>,
+        /// when the pattern doesn't match, show the longest prefix of it that does
+        #[arg(long)]
+        suggest: bool,
+        /// print every match of the pattern, overlapping ones included, not only the first
+        #[arg(long)]
+        all: bool,
+        /// keep the matches in the order they were found, instead of ordered by the address
+        /// they resolve to
+        #[arg(long, requires = "all")]
+        unsorted: bool,
+        /// drop matches that resolve to an address an earlier match already resolved to
+        #[arg(long, requires = "all")]
+        unique_only: bool,
+    },
+    Resolve {
         #[clap(value_parser=parse_pid)]
         pid: i32,
         #[clap(value_parser=parse_address_locator)]
         address: AddressLocator,
+        /// resolve from every signature match instead of only the first
+        #[arg(long)]
+        all: bool,
+        /// also print the address relative to the closest preceding function symbol
+        #[arg(long)]
+        symbol_relative: bool,
+        /// print the start of the function symbol containing the address instead
+        #[arg(long)]
+        rebase_to_symbol: bool,
+        /// keep the matches in the order they were found, instead of ordered by the address
+        /// they resolve to
+        #[arg(long, requires = "all")]
+        unsorted: bool,
+        /// drop matches that resolve to an address an earlier match already resolved to
+        #[arg(long, requires = "all")]
+        unique_only: bool,
+    },
+    ExportSig {
+        #[clap(value_parser=parse_pid)]
+        pid: i32,
+        #[clap(value_parser=parse_address_locator)]
+        address: AddressLocator,
+        /// initial signature length in bytes, extended until the signature is unique
+        #[arg(long, default_value_t = 16)]
+        len: usize,
+    },
+    MonitorSig {
+        #[clap(value_parser=parse_pid)]
+        pid: i32,
+        #[clap(value_parser=parse_ida_signature_with_offset)]
+        signature: IdaSignature,
+        #[clap(value_parser=parse_duration)]
+        #[arg(short, long, default_value = "500ms")]
+        interval: Duration,
+    },
+    Batch {
+        #[clap(value_parser=parse_pid)]
+        pid: i32,
+        /// file with one `name = locator : type` entry per line
+        #[arg(short, long)]
+        file: PathBuf,
+        /// file with one `name = value` line per entry, fails on any mismatch
+        #[arg(long)]
+        compare: Option<PathBuf>,
     },
     FindFunction {
         #[clap(value_parser=parse_pid)]
         pid: i32,
         function_name: String,
+        /// also search symbols in separate debug files referenced by `.gnu_debuglink`
+        #[arg(long)]
+        include_debuglink: bool,
+        /// print only the addresses, one per line
+        #[arg(long)]
+        address_only: bool,
+        /// print symbols as stored in the module instead of demangling c++ and rust names
+        #[arg(long)]
+        raw: bool,
     },
     List {
+        /// one pid, or several separated by commas
+        #[clap(value_parser=parse_pid_list)]
+        pids: PidList,
+        /// group regions under their backing file
+        #[arg(long)]
+        tree: bool,
+        /// show how much of each region is actually resident in memory
+        #[arg(long)]
+        resident: bool,
+        /// only list writable regions
+        #[arg(long)]
+        writable: bool,
+        /// only list executable regions
+        #[arg(long)]
+        executable: bool,
+        /// save the region layout to this file as json instead of listing it
+        #[arg(long, conflicts_with_all = ["tree", "resident", "writable", "executable", "diff"])]
+        snapshot: Option<PathBuf>,
+        /// list regions added, removed and resized since this snapshot was taken
+        #[arg(long, conflicts_with_all = ["tree", "resident", "writable", "executable"])]
+        diff: Option<PathBuf>,
+    },
+    Strings {
         #[clap(value_parser=parse_pid)]
         pid: i32,
+        /// address range like `7F00A000-7F00B000`, or a region name like `[heap]` or a library glob
+        target: String,
+        /// minimum number of characters in a run
+        #[arg(short = 'n', long, default_value_t = 4)]
+        min_len: usize,
+        /// look for utf-16 strings instead of ascii
+        #[arg(long)]
+        unicode: bool,
     },
     Snap {
         #[clap(value_parser=parse_pid)]
         pid: i32,
+        /// part of the library file name, or a glob like 'libQt*.so*'
         lib: String,
     },
+    /// write the bytes at an address to a file
+    Dump {
+        #[clap(value_parser=parse_pid)]
+        pid: i32,
+        /// an address pattern, a module name, or `#N` for the region at index N of `list`
+        #[clap(value_parser=parse_dump_target)]
+        target: DumpTarget,
+        /// number of bytes, everything up to the end of the region or module if not given
+        #[clap(value_parser=parse_size)]
+        length: Option<usize>,
+        #[arg(short, long)]
+        output: PathBuf,
+    },
+    /// disassemble the instructions at an address
+    Disasm {
+        #[clap(value_parser=parse_pid)]
+        pid: i32,
+        #[clap(value_parser=parse_address_locator)]
+        address: AddressLocator,
+        /// number of instructions
+        #[arg(default_value_t = 16, value_parser = RangedU64ValueParser::<usize>::new().range(1..=MAX_DISASM_COUNT))]
+        count: usize,
+    },
+    SelfTest,
+    #[command(visible_alias = "ps")]
+    Processes {
+        /// only list processes whose name or command line contains this
+        filter: Option<String>,
+    },
+    Scan {
+        #[clap(value_parser=parse_pid)]
+        pid: i32,
+        #[clap(value_parser=parse_data_type)]
+        data_type: DataType,
+        /// in the same format read prints it, floats match within a small tolerance
+        #[arg(allow_hyphen_values = true)]
+        value: String,
+        /// only scan regions with this pathname, or a library file name or glob
+        #[arg(long = "region")]
+        region_filter: Option<String>,
+        /// show how much of the memory was scanned, with the rate and time left
+        #[arg(long)]
+        progress_bar: bool,
+        /// wait for enter and only scan the pages written to until then
+        #[arg(long)]
+        dirty_only: bool,
+        /// print bare addresses, without the module or region each lies in
+        #[arg(long)]
+        no_context: bool,
+        /// distance in bytes between the elements of an array the value repeats in
+        #[arg(long, requires = "count", value_parser = clap::value_parser!(u64).range(1..))]
+        stride: Option<u64>,
+        /// only report array bases where this many elements in a row hold the value
+        #[arg(long, requires = "stride", value_parser = clap::value_parser!(u64).range(2..))]
+        count: Option<u64>,
+        /// save the matches under this name, so several scans of a process can be refined
+        /// side by side
+        #[arg(long, value_parser = parse_scan_name, default_value = DEFAULT_SCAN_NAME)]
+        name: String,
+    },
+    /// narrow down the matches of the last scan of the process
+    NextScan {
+        #[clap(value_parser=parse_pid)]
+        pid: i32,
+        /// `increased`, `decreased`, `unchanged`, `changed`, or `=<value>`
+        #[arg(allow_hyphen_values = true)]
+        predicate: String,
+        /// the scan to refine, as named with `scan --name`
+        #[arg(long, value_parser = parse_scan_name, default_value = DEFAULT_SCAN_NAME)]
+        name: String,
+    },
+    /// list or remove saved scans
+    Scans {
+        #[command(subcommand)]
+        command: ScansCommand,
+    },
+    Write {
+        #[clap(value_parser=parse_pid)]
+        pid: i32,
+        #[clap(value_parser=parse_address_locator)]
+        address: AddressLocator,
+        #[clap(value_parser=parse_data_type)]
+        data_type: DataType,
+        /// written in the same format read prints it, e.g. `42`, `-1.5` or `[1, 2, 3]`
+        #[arg(allow_hyphen_values = true)]
+        value: String,
+        /// write even when the address lies in executable memory
+        #[arg(long)]
+        allow_exec: bool,
+        /// print the bytes that would be replaced and what with, without writing
+        #[arg(long)]
+        dry_run: bool,
+    },
+    /// patch raw bytes at an address, code included
+    Replace {
+        #[clap(value_parser=parse_pid)]
+        pid: i32,
+        #[clap(value_parser=parse_address_locator)]
+        address: AddressLocator,
+        /// hex bytes to write, like `90 90` or `EB`
+        #[clap(value_parser=parse_bytes, required_unless_present = "nop")]
+        bytes: Option<PatchBytes>,
+        /// write this many x86 `nop`s instead, the address has to be in executable memory
+        #[arg(long, conflicts_with = "bytes", value_parser = clap::value_parser!(u64).range(1..=4096))]
+        nop: Option<u64>,
+        /// allow patching executable memory
+        #[arg(long)]
+        allow_exec: bool,
+        /// print the bytes that would be replaced and what with, without writing
+        #[arg(long)]
+        dry_run: bool,
+        /// save the bytes that get replaced to this file, in a format `replace` takes back
+        #[arg(long)]
+        backup: Option<PathBuf>,
+    },
 }
 
-fn parse_pid(s: &str) -> Result<i32, String> {
-    if s == "self" {
-        return Ok(std::process::id() as i32);
+#[derive(Debug, clap::Subcommand)]
+pub enum ScansCommand {
+    /// show the saved scans and how many matches each has left
+    List {
+        /// only the scans of this process
+        #[arg(long, value_parser = parse_pid)]
+        pid: Option<i32>,
+    },
+    /// remove the scans with this name
+    Clear {
+        #[clap(value_parser = parse_scan_name)]
+        name: String,
+        /// only the scan of this process
+        #[arg(long, value_parser = parse_pid)]
+        pid: Option<i32>,
+    },
+}
+
+#[derive(Debug, Clone)]
+pub struct PidList(pub Vec<i32>);
+
+#[derive(Debug, Clone)]
+pub struct PatchBytes(pub Vec<u8>);
+
+/// what `dump` starts at, an address or the region at an index of `list`
+#[derive(Debug, Clone)]
+pub enum DumpTarget {
+    Address(AddressLocator),
+    RegionIndex(usize),
+}
+
+pub fn parse_dump_target(s: &str) -> Result<DumpTarget, String> {
+    if let Some(index) = s.strip_prefix('#') {
+        let index = index
+            .parse()
+            .map_err(|e| format!("Invalid region index '{index}': {e}"))?;
+        return Ok(DumpTarget::RegionIndex(index));
     }
 
-    // pid is a number
-    if let Ok(pid) = s.parse::<i32>() {
-        return if pid <= 0 || pid > 2_i32.pow(22) {
-            Err(format!("Invalid PID '{s}'"))
-        } else {
-            Ok(pid)
-        };
+    // a bare module name, like libclient.so, starts at the module base
+    match parse_address_locator(s) {
+        Ok(locator) => Ok(DumpTarget::Address(locator)),
+        Err(_) if !s.contains(char::is_whitespace) => Ok(DumpTarget::Address(
+            AddressLocator::ModuleOffset(s.to_string(), 0, Vec::new()),
+        )),
+        Err(err) => Err(err),
     }
+}
 
-    // pid is an executable name
-    for process in std::fs::read_dir("/proc").map_err(|e| format!("Could not read /proc: {e}"))? {
-        let Ok(process) = process else {
-            continue;
-        };
+/// a byte count, hex with a `0x` prefix and decimal otherwise
+fn parse_size(s: &str) -> Result<usize, String> {
+    match s.strip_prefix("0x").or_else(|| s.strip_prefix("0X")) {
+        Some(hex) => usize::from_str_radix(hex, 16),
+        None => s.parse(),
+    }
+    .map_err(|e| format!("Invalid size '{s}': {e}"))
+}
 
-        if !process.file_type().unwrap().is_dir() {
-            continue;
-        }
+/// space separated hex bytes, `90 90`, `9090` or `0x90 0x90`
+pub fn parse_bytes(s: &str) -> Result<PatchBytes, String> {
+    let digits: String = s
+        .split_whitespace()
+        .map(|byte| byte.trim_start_matches("0x").trim_start_matches("0X"))
+        .collect();
+    if digits.is_empty() || !digits.len().is_multiple_of(2) {
+        return Err(format!("Expected hex bytes like '90 90', found '{s}'"));
+    }
 
-        let dir_name = process.file_name();
-        let mut exe_path = PathBuf::from("/proc");
-        exe_path.push(&dir_name);
-        exe_path.push("exe");
+    (0..digits.len())
+        .step_by(2)
+        .map(|i| {
+            digits
+                .get(i..i + 2)
+                .and_then(|byte| u8::from_str_radix(byte, 16).ok())
+                .ok_or_else(|| format!("Invalid hex bytes '{s}'"))
+        })
+        .collect::<Result<_, _>>()
+        .map(PatchBytes)
+}
 
-        let Ok(exe_path) = std::fs::read_link(exe_path) else {
-            continue;
-        };
+/// scan names become file names, so they are limited to letters, digits, `-` and `_`
+pub fn parse_scan_name(s: &str) -> Result<String, String> {
+    if s.is_empty()
+        || s.len() > 64
+        || !s
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+    {
+        return Err(format!(
+            "Invalid scan name '{s}', use up to 64 letters, digits, '-' and '_'"
+        ));
+    }
+    Ok(s.to_string())
+}
 
-        if let Some(exe) = exe_path.file_name()
-            && exe == s
-        {
-            let pid = dir_name
-                .to_str()
-                .ok_or(format!("Invalid PID in path '{exe:?}'"))?;
-            let pid = pid
-                .parse()
-                .map_err(|e| format!("Invalid PID '{pid}': {e}"))?;
-            return Ok(pid);
+fn parse_pid_list(s: &str) -> Result<PidList, String> {
+    s.split(',')
+        .map(|pid| parse_pid(pid.trim()))
+        .collect::<Result<_, _>>()
+        .map(PidList)
+}
+
+pub fn parse_pid(s: &str) -> Result<i32, String> {
+    if s == "self" {
+        return Ok(std::process::id() as i32);
+    }
+
+    // pid is read from a pidfile, like `pidfile:/run/sshd.pid`
+    if let Some(path) = s.strip_prefix("pidfile:") {
+        let contents = std::fs::read_to_string(path)
+            .map_err(|e| format!("Could not read pid file '{path}': {e}"))?;
+        // only a number, a pid file naming another pid file or a process would loop or guess
+        return parse_pid_number(contents.trim())
+            .ok_or(format!("Pid file '{path}' does not contain a valid PID"));
+    }
+
+    // pid is a number
+    if s.parse::<i32>().is_ok() {
+        return parse_pid_number(s).ok_or(format!("Invalid PID '{s}'"));
+    }
+
+    // pid is a process name, which has to name exactly one process
+    let pids = matching_pids(&processes(), s);
+    match pids.as_slice() {
+        [pid] => Ok(*pid),
+        [] => Err(format!("No process named '{s}'")),
+        _ => {
+            let pids: Vec<String> = pids.iter().map(i32::to_string).collect();
+            Err(format!(
+                "Multiple processes named '{s}' ({}), pass one of them by pid",
+                pids.join(", ")
+            ))
         }
     }
-    Ok(0)
 }
 
-fn parse_address_locator(s: &str) -> Result<AddressLocator, String> {
+/// a positive decimal pid within the kernel's `pid_max` limit
+fn parse_pid_number(s: &str) -> Option<i32> {
+    let pid = s.parse::<i32>().ok()?;
+    (s.bytes().all(|byte| byte.is_ascii_digit()) && pid > 0 && pid <= 2_i32.pow(22)).then_some(pid)
+}
+
+pub fn parse_address_locator(s: &str) -> Result<AddressLocator, String> {
     // basic address
     if let Some(stripped) = s.strip_prefix("0x").or_else(|| s.strip_prefix("0X")) {
         let addr =
@@ -110,48 +547,133 @@ fn parse_address_locator(s: &str) -> Result<AddressLocator, String> {
         return Ok(AddressLocator::Absolute(addr));
     }
 
+    // offset from a labelled region, like [heap]+0x1234
+    if s.starts_with('[') {
+        let Some((name, offset)) = s.split_once(']') else {
+            return Err(format!("Invalid region label '{s}'"));
+        };
+        let offset = match offset.trim() {
+            "" => 0,
+            offset => {
+                let Some(offset) = offset.strip_prefix('+') else {
+                    return Err(format!("Invalid region offset '{offset}'"));
+                };
+                parse_pointer(offset.trim()).map_err(|e| format!("Invalid region offset: {e}"))?
+            }
+        };
+        return Ok(AddressLocator::Region(format!("{name}]"), offset));
+    }
+
     // split into potential pattern and pointer chain parts
     let parts: Vec<&str> = s.split("->").map(|part| part.trim()).collect();
+    let pointers: Result<Vec<usize>, ParseIntError> =
+        parts[1..].iter().map(|&ptr| parse_pointer(ptr)).collect();
+    let pointers = pointers.map_err(|e| format!("Invalid pointer: {e}"))?;
 
-    let pattern = parse_ida_signature_with_offset(parts[0])?;
+    // a pattern kept in a file, like sigfile:player.sig -> 0x10
+    if let Some(path) = parts[0].strip_prefix("sigfile:") {
+        let pattern = parse_signature_file(path)?;
+        return Ok(if pointers.is_empty() {
+            AddressLocator::Pattern(pattern)
+        } else {
+            AddressLocator::PointerChain(pattern, pointers)
+        });
+    }
 
-    if parts.len() > 1 {
-        let pointers: Result<Vec<usize>, ParseIntError> =
-            parts[1..].iter().map(|&ptr| parse_pointer(ptr)).collect();
+    // offset from a function, like func:GetPlayer+0x10
+    if let Some(function) = parts[0].strip_prefix("func:") {
+        // names like operator+ contain a plus, only a number after the last one is an offset
+        let (name, offset) = function
+            .rsplit_once('+')
+            .and_then(|(name, offset)| Some((name.trim(), parse_pointer(offset.trim()).ok()?)))
+            .unwrap_or((function.trim(), 0));
+        if name.is_empty() {
+            return Err(format!("Missing function name in '{s}'"));
+        }
+        return Ok(AddressLocator::Function(name.to_string(), offset, pointers));
+    }
 
-        let pointers = pointers.map_err(|e| format!("Invalid pointer: {e}"))?;
-        Ok(AddressLocator::PointerChain(pattern, pointers))
-    } else {
+    // offset from a module base, like libclient.so+0x4A10. patterns contain spaces instead
+    if let Some((name, offset)) = parts[0].split_once('+')
+        && !name.is_empty()
+        && !name.contains(char::is_whitespace)
+    {
+        let offset =
+            parse_pointer(offset.trim()).map_err(|e| format!("Invalid module offset: {e}"))?;
+        return Ok(AddressLocator::ModuleOffset(
+            name.to_string(),
+            offset,
+            pointers,
+        ));
+    }
+
+    let pattern = parse_ida_signature_with_offset(parts[0])?;
+
+    if pointers.is_empty() {
         Ok(AddressLocator::Pattern(pattern))
+    } else {
+        Ok(AddressLocator::PointerChain(pattern, pointers))
+    }
+}
+
+/// a pattern exported to a text file, which may span several lines. lines starting with `#`
+/// are comments
+pub fn parse_signature_file(path: &str) -> Result<IdaSignature, String> {
+    let contents = std::fs::read_to_string(path)
+        .map_err(|e| format!("Unable to read signature file '{path}': {e}"))?;
+    let signature = contents
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .collect::<Vec<_>>()
+        .join(" ");
+    if signature.is_empty() {
+        return Err(format!("Signature file '{path}' holds no pattern"));
     }
+    parse_ida_signature_with_offset(&signature)
 }
 
 fn parse_ida_signature_with_offset(s: &str) -> Result<IdaSignature, String> {
-    if let Some((signature, offset)) = s.split_once('@') {
-        let Some((offset, instruction_size)) = offset.split_once('/') else {
-            return Err(format!("Invalid offset '{offset}'"));
-        };
+    let Some((signature, offset)) = s.split_once('@') else {
+        let signature = parse_ida_signature(s)?;
+        return Ok(IdaSignature::new(signature, None));
+    };
+
+    // call@N, the rel32 of a call or jmp at offset N
+    if let Some(signature) = signature.trim_end().strip_suffix("call") {
         let offset: usize = offset
+            .trim()
             .parse()
-            .map_err(|e| format!("Invalid offset '{offset}': {e}"))?;
-        let instruction_size: usize = instruction_size
-            .parse()
-            .map_err(|e| format!("Invalid instruction size '{instruction_size}': {e}"))?;
+            .map_err(|e| format!("Invalid call offset '{offset}': {e}"))?;
         let signature = parse_ida_signature(signature)?;
-        Ok(IdaSignature::new(
-            signature,
-            Some(Offset {
-                offset,
-                instruction_size,
-            }),
-        ))
-    } else {
-        let signature = parse_ida_signature(s)?;
-        Ok(IdaSignature::new(signature, None))
+        return Ok(IdaSignature::new(signature, Some(Offset::branch(offset))));
     }
+
+    let Some((offset, instruction_size)) = offset.split_once('/') else {
+        return Err(format!("Invalid offset '{offset}'"));
+    };
+    let offset: usize = offset
+        .parse()
+        .map_err(|e| format!("Invalid offset '{offset}': {e}"))?;
+    let instruction_size: usize = instruction_size
+        .parse()
+        .map_err(|e| format!("Invalid instruction size '{instruction_size}': {e}"))?;
+    let signature = parse_ida_signature(signature)?;
+    Ok(IdaSignature::new(
+        signature,
+        Some(Offset {
+            offset,
+            instruction_size,
+            branch: false,
+        }),
+    ))
 }
 
 fn parse_ida_signature(s: &str) -> Result<Vec<Option<u8>>, String> {
+    if s.starts_with('"') || s.starts_with("code:") {
+        return parse_mask_signature(s);
+    }
+
     s.split_whitespace()
         .map(|byte| {
             if byte == "?" || byte == "??" {
@@ -165,6 +687,48 @@ fn parse_ida_signature(s: &str) -> Result<Vec<Option<u8>>, String> {
         .collect()
 }
 
+/// the byte string and mask format x64dbg and ida plugins export, like `"\x48\x8B\x00" "xx?"`
+/// or `code:\x48\x8B\x00 xx?`. a `?` in the mask makes the byte at that position a wildcard
+fn parse_mask_signature(s: &str) -> Result<Vec<Option<u8>>, String> {
+    let code = s.strip_prefix("code:").unwrap_or(s).trim();
+    let Some((bytes, mask)) = code.rsplit_once(char::is_whitespace) else {
+        return Err(format!("Expected a byte string and a mask in '{s}'"));
+    };
+    let (bytes, mask) = (unquote(bytes.trim()), unquote(mask.trim()));
+
+    let Some(bytes) = bytes.strip_prefix("\\x") else {
+        return Err(format!(
+            "Expected a byte string like '\\x48\\x8B', found '{bytes}'"
+        ));
+    };
+    let bytes = bytes
+        .split("\\x")
+        .map(|byte| {
+            u8::from_str_radix(byte, 16).map_err(|e| format!("Invalid hex byte '{byte}': {e}"))
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    if mask.len() != bytes.len() {
+        return Err(format!(
+            "Mask '{mask}' has {} positions for {} bytes",
+            mask.len(),
+            bytes.len()
+        ));
+    }
+
+    bytes
+        .into_iter()
+        .zip(mask.chars())
+        .map(|(byte, mask)| match mask {
+            'x' | 'X' => Ok(Some(byte)),
+            '?' => Ok(None),
+            _ => Err(format!(
+                "Invalid mask character '{mask}', expected 'x' or '?'"
+            )),
+        })
+        .collect()
+}
+
 fn parse_pointer(s: &str) -> Result<usize, ParseIntError> {
     if let Some(stripped) = s.strip_prefix("0x").or_else(|| s.strip_prefix("0X")) {
         usize::from_str_radix(stripped, 16)
@@ -173,9 +737,70 @@ fn parse_pointer(s: &str) -> Result<usize, ParseIntError> {
     }
 }
 
-fn parse_data_type(s: &str) -> Result<DataType, String> {
+pub fn parse_data_type(s: &str) -> Result<DataType, String> {
     use DataType::*;
 
+    // an array of a fixed-size element type, like u32[16]
+    if let Some((element, len)) = s.strip_suffix(']').and_then(|s| s.rsplit_once('[')) {
+        let len = len
+            .parse()
+            .map_err(|e| format!("Invalid array length '{len}': {e}"))?;
+        if len == 0 {
+            return Err(format!("Array type '{s}' has no elements"));
+        }
+        let element = parse_data_type(element)?;
+        if element.fixed_size().is_none() {
+            return Err(format!(
+                "Arrays of {element} are not supported, their size varies"
+            ));
+        }
+        let array = Array(Box::new(element), len);
+        if array.fixed_size().is_none() {
+            return Err(format!("Array type '{s}' is too large"));
+        }
+        return Ok(array);
+    }
+
+    if let Some(flags) = s.strip_prefix("flags:") {
+        return parse_flags(flags);
+    }
+
+    if let Some(bits) = s.strip_prefix("fixed:") {
+        return parse_fixed_point(bits, true);
+    }
+    if let Some(bits) = s.strip_prefix("ufixed:") {
+        return parse_fixed_point(bits, false);
+    }
+
+    if let Some(max_len) = s.strip_prefix("cstring:") {
+        let max_len = max_len
+            .parse()
+            .map_err(|e| format!("Invalid string length '{max_len}': {e}"))?;
+        return Ok(CString {
+            max_len: Some(max_len),
+        });
+    }
+
+    if let Some(max_units) = s.strip_prefix("wstring:") {
+        let max_units = max_units
+            .parse()
+            .map_err(|e| format!("Invalid string length '{max_units}': {e}"))?;
+        return Ok(WString {
+            max_units: Some(max_units),
+        });
+    }
+
+    if let Some(len_type) = s.strip_prefix("pstring:") {
+        let len_width = match len_type {
+            "u8" => 1,
+            "u16" => 2,
+            "u32" => 4,
+            "u64" => 8,
+            _ => return Err(format!("Invalid string length type '{len_type}'")),
+        };
+        return Ok(PascalString { len_width });
+    }
+
     let data_type = match s {
         "u8" => U8,
         "u16" => U16,
@@ -187,28 +812,109 @@ fn parse_data_type(s: &str) -> Result<DataType, String> {
         "i32" => I32,
         "i64" => I64,
 
+        "f16" => F16,
         "f32" => F32,
         "f64" => F64,
 
         "pointer" => Pointer,
         "pointer32" => Pointer32,
         "pointer64" => Pointer64,
+        "ptrarray" => PointerArray,
 
         "vec2" => Vec2,
         "vec3" => Vec3,
         "vec4" => Vec4,
         "mat4" => Mat4,
 
+        "dvec2" => DVec2,
+        "dvec3" => DVec3,
+        "dvec4" => DVec4,
+
         "rgb" => Rgb,
         "rgba" => Rgba,
         "color32" => Color32,
 
+        "counter" => Counter,
+        "utf8char" => Utf8Char,
+        "cstring" => CString { max_len: None },
+        "wstring" => WString { max_units: None },
+
         _ => return Err(format!("Unknown data type '{s}'")),
     };
 
     Ok(data_type)
 }
 
+/// parses `16.16`, the integer and fraction bits after `fixed:` or `ufixed:`
+fn parse_fixed_point(s: &str, signed: bool) -> Result<DataType, String> {
+    let Some((int_bits, frac_bits)) = s.split_once('.') else {
+        return Err(format!(
+            "Expected integer and fraction bits like '16.16', found '{s}'"
+        ));
+    };
+    let int_bits: u32 = int_bits
+        .parse()
+        .map_err(|e| format!("Invalid integer bits '{int_bits}': {e}"))?;
+    let frac_bits: u32 = frac_bits
+        .parse()
+        .map_err(|e| format!("Invalid fraction bits '{frac_bits}': {e}"))?;
+
+    let width = int_bits.saturating_add(frac_bits);
+    if ![16, 32, 64].contains(&width) {
+        return Err(format!(
+            "Fixed point '{s}' is {width} bits wide, only 16, 32 and 64 are supported"
+        ));
+    }
+
+    Ok(DataType::FixedPoint {
+        int_bits,
+        frac_bits,
+        signed,
+    })
+}
+
+/// parses `u32{0=alive,1=invincible}`, the part after `flags:`
+fn parse_flags(s: &str) -> Result<DataType, String> {
+    let Some((base, names)) = s.split_once('{') else {
+        return Err(format!("Missing flag names in 'flags:{s}'"));
+    };
+    let Some(names) = names.strip_suffix('}') else {
+        return Err(format!("Missing closing brace in 'flags:{s}'"));
+    };
+
+    let base = parse_data_type(base)?;
+    let bits = match base {
+        DataType::U8 | DataType::I8 => 8,
+        DataType::U16 | DataType::I16 => 16,
+        DataType::U32 | DataType::I32 => 32,
+        DataType::U64 | DataType::I64 => 64,
+        _ => return Err(format!("Flags need an integer base type, not '{base:?}'")),
+    };
+
+    let names = names
+        .split(',')
+        .filter(|flag| !flag.trim().is_empty())
+        .map(|flag| {
+            let Some((bit, name)) = flag.split_once('=') else {
+                return Err(format!("Invalid flag '{flag}', expected 'bit=name'"));
+            };
+            let bit: u32 = bit
+                .trim()
+                .parse()
+                .map_err(|e| format!("Invalid flag bit '{bit}': {e}"))?;
+            if bit >= bits {
+                return Err(format!("Flag bit {bit} does not fit into {bits} bits"));
+            }
+            Ok((bit, name.trim().to_string()))
+        })
+        .collect::<Result<_, _>>()?;
+
+    Ok(DataType::Flags {
+        base: Box::new(base),
+        names,
+    })
+}
+
 fn parse_duration(s: &str) -> Result<Duration, String> {
     if let Some(us) = s.strip_suffix("us") {
         let us = us.parse::<u64>().map_err(|e| e.to_string())?;
@@ -0,0 +1,168 @@
+use std::{collections::HashMap, path::Path};
+
+use crate::{
+    address::AddressLocator,
+    args::{parse_address_locator, parse_data_type},
+    commands::CommandError,
+    data_type::DataType,
+    memory::{Memory, MemoryOptions},
+    value::Value,
+};
+
+struct Entry {
+    name: String,
+    address: AddressLocator,
+    data_type: DataType,
+}
+
+/// reads every entry of the file with one process handle, failed entries don't stop the rest.
+/// with a compare file, each value is checked against the expected value with the same name
+pub fn batch(
+    pid: i32,
+    file: &Path,
+    compare: Option<&Path>,
+    options: MemoryOptions,
+) -> Result<(), CommandError> {
+    let entries = parse_entries(&read_file(file)?);
+    let mut expected = match compare {
+        Some(compare) => Some(parse_expected(&read_file(compare)?)?),
+        None => None,
+    };
+
+    let memory = Memory::with_options(pid, options).map_err(CommandError::Open)?;
+
+    let name_width = entries
+        .iter()
+        .filter_map(|entry| entry.as_ref().ok())
+        .map(|entry| entry.name.len())
+        .max()
+        .unwrap_or_default();
+
+    let mut failed = 0;
+    let mut total = entries.len();
+    for entry in &entries {
+        let entry = match entry {
+            Ok(entry) => entry,
+            Err(err) => {
+                eprintln!("{err}");
+                failed += 1;
+                continue;
+            }
+        };
+
+        let (address, value) = match read_entry(&memory, entry) {
+            Ok(read) => read,
+            Err(err) => {
+                eprintln!("{}: {err}", entry.name);
+                failed += 1;
+                continue;
+            }
+        };
+
+        let name = &entry.name;
+        let Some(expected) = expected.as_mut() else {
+            println!("{name:<name_width$} 0x{address:X} = {value}");
+            continue;
+        };
+
+        match check_expected(entry, &value, expected.remove(name)) {
+            Ok(()) => println!("{name:<name_width$} pass 0x{address:X} = {value}"),
+            Err(err) => {
+                println!("{name:<name_width$} FAIL 0x{address:X} = {value} ({err})");
+                failed += 1;
+            }
+        }
+    }
+
+    // expected values that no entry was read for
+    for name in expected.into_iter().flat_map(HashMap::into_keys) {
+        eprintln!("{name}: No entry to compare against");
+        failed += 1;
+        total += 1;
+    }
+
+    if failed > 0 {
+        return Err(CommandError::EntryFailures(failed, total));
+    }
+    Ok(())
+}
+
+fn read_file(path: &Path) -> Result<String, CommandError> {
+    std::fs::read_to_string(path)
+        .map_err(|err| CommandError::ReadFile(path.display().to_string(), err))
+}
+
+fn read_entry(memory: &Memory, entry: &Entry) -> Result<(usize, Value), CommandError> {
+    let address = entry
+        .address
+        .resolve(memory)
+        .map_err(CommandError::Resolve)?;
+    let value = entry
+        .data_type
+        .read(memory, address)
+        .map_err(CommandError::Read)?;
+
+    Ok((address, value))
+}
+
+/// values are compared after parsing, so vectors and matrices compare element by element
+fn check_expected(entry: &Entry, value: &Value, expected: Option<String>) -> Result<(), String> {
+    let Some(expected) = expected else {
+        return Err("no expected value".to_string());
+    };
+    let expected = entry.data_type.parse_value(&expected)?;
+
+    if *value == expected {
+        Ok(())
+    } else {
+        Err(format!("expected {expected}"))
+    }
+}
+
+/// skips blank lines and `#` comments
+fn parse_lines(contents: &str) -> impl Iterator<Item = (usize, &str)> {
+    contents
+        .lines()
+        .enumerate()
+        .map(|(index, line)| (index + 1, line.trim()))
+        .filter(|(_, line)| !line.is_empty() && !line.starts_with('#'))
+}
+
+fn parse_entries(contents: &str) -> Vec<Result<Entry, CommandError>> {
+    parse_lines(contents)
+        .map(|(line_number, line)| {
+            parse_entry(line).map_err(|err| CommandError::InvalidEntry(line_number, err))
+        })
+        .collect()
+}
+
+/// `name = locator : type`, the type may contain colons itself, like `flags:u8{0=alive}`
+fn parse_entry(line: &str) -> Result<Entry, String> {
+    let Some((name, rest)) = line.split_once('=') else {
+        return Err(format!("Missing '=' in '{line}'"));
+    };
+    let Some((address, data_type)) = rest.split_once(':') else {
+        return Err(format!("Missing ':' in '{line}'"));
+    };
+
+    Ok(Entry {
+        name: name.trim().to_string(),
+        address: parse_address_locator(address.trim())?,
+        data_type: parse_data_type(data_type.trim())?,
+    })
+}
+
+/// `name = value` lines, the values are parsed once the data type of the entry is known
+fn parse_expected(contents: &str) -> Result<HashMap<String, String>, CommandError> {
+    parse_lines(contents)
+        .map(|(line_number, line)| {
+            let Some((name, value)) = line.split_once('=') else {
+                return Err(CommandError::InvalidEntry(
+                    line_number,
+                    format!("Missing '=' in '{line}'"),
+                ));
+            };
+            Ok((name.trim().to_string(), value.trim().to_string()))
+        })
+        .collect()
+}
@@ -0,0 +1,185 @@
+use std::{fs::File, io::Read as _};
+
+use iced_x86::{Decoder, DecoderError, DecoderOptions, Formatter as _, IntelFormatter};
+use serde::Serialize;
+
+use crate::{
+    address::AddressLocator,
+    commands::{
+        CommandError,
+        hexdump::{format_hex, serialize_hex},
+    },
+    diagnostics::warning,
+    memory::{Memory, MemoryError, MemoryOptions},
+    output::{self, serialize_address},
+};
+
+/// longest possible x86 instruction
+const MAX_INSTRUCTION_LEN: usize = 15;
+/// instructions shorter than this keep the text column aligned
+const BYTES_COLUMN_LEN: usize = 8;
+
+/// instruction sets that can be disassembled, told apart by `e_machine` in the elf header
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Architecture {
+    X86,
+    X86_64,
+}
+
+impl Architecture {
+    pub fn from_machine(machine: u16) -> Result<Self, CommandError> {
+        match machine {
+            elf::abi::EM_386 => Ok(Self::X86),
+            elf::abi::EM_X86_64 => Ok(Self::X86_64),
+            elf::abi::EM_AARCH64 => {
+                Err(CommandError::UnsupportedArchitecture("AArch64".to_string()))
+            }
+            machine => Err(CommandError::UnsupportedArchitecture(format!(
+                "e_machine {machine}"
+            ))),
+        }
+    }
+
+    /// the architecture of the process' main executable
+    pub fn of_process(pid: i32) -> Result<Self, CommandError> {
+        let path = format!("/proc/{pid}/exe");
+        let mut header = [0; 20];
+        File::open(&path)
+            .and_then(|mut file| file.read_exact(&mut header))
+            .map_err(|err| CommandError::ReadFile(path.clone(), err))?;
+        if !header.starts_with(&[0x7F, b'E', b'L', b'F']) {
+            return Err(CommandError::UnsupportedArchitecture(format!(
+                "non-elf '{path}'"
+            )));
+        }
+
+        let machine = [header[18], header[19]];
+        Self::from_machine(match header[5] {
+            elf::abi::ELFDATA2MSB => u16::from_be_bytes(machine),
+            _ => u16::from_le_bytes(machine),
+        })
+    }
+
+    fn bitness(self) -> u32 {
+        match self {
+            Self::X86 => 32,
+            Self::X86_64 => 64,
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct Instruction {
+    #[serde(serialize_with = "serialize_address")]
+    pub address: usize,
+    #[serde(serialize_with = "serialize_hex")]
+    pub bytes: Vec<u8>,
+    /// intel syntax, with rip-relative operands and branch targets as absolute addresses
+    pub text: String,
+    /// where a branch goes or a rip-relative operand points
+    #[serde(skip)]
+    pub target: Option<usize>,
+}
+
+/// decodes up to `count` instructions from `bytes` as if they were at `address`. stops early
+/// when the bytes end in the middle of an instruction
+pub fn disassemble(
+    architecture: Architecture,
+    bytes: &[u8],
+    address: usize,
+    count: usize,
+) -> Vec<Instruction> {
+    let mut decoder = Decoder::with_ip(
+        architecture.bitness(),
+        bytes,
+        address as u64,
+        DecoderOptions::NONE,
+    );
+    let mut formatter = IntelFormatter::new();
+    let format = formatter.options_mut();
+    format.set_hex_prefix("0x");
+    format.set_hex_suffix("");
+    format.set_space_after_operand_separator(true);
+    format.set_branch_leading_zeros(false);
+
+    let mut instructions = Vec::new();
+    while instructions.len() < count && decoder.can_decode() {
+        let offset = decoder.position();
+        let instruction = decoder.decode();
+        if decoder.last_error() == DecoderError::NoMoreBytes {
+            break;
+        }
+
+        let mut text = String::new();
+        formatter.format(&instruction, &mut text);
+        let target = if instruction.is_ip_rel_memory_operand() {
+            Some(instruction.ip_rel_memory_address() as usize)
+        } else {
+            Some(instruction.near_branch_target() as usize).filter(|&target| target != 0)
+        };
+        instructions.push(Instruction {
+            address: instruction.ip() as usize,
+            bytes: bytes[offset..offset + instruction.len()].to_vec(),
+            text,
+            target,
+        });
+    }
+    instructions
+}
+
+/// disassembles `count` instructions at the address, naming the function a branch or
+/// rip-relative operand points into when its module has symbols
+pub fn disasm(
+    pid: i32,
+    address: AddressLocator,
+    count: usize,
+    options: MemoryOptions,
+) -> Result<(), CommandError> {
+    let architecture = Architecture::of_process(pid)?;
+    let memory = Memory::with_options(pid, options).map_err(CommandError::Open)?;
+    let address = address.resolve(&memory).map_err(CommandError::Resolve)?;
+
+    let end = memory
+        .mapped_end(address)
+        .ok_or(CommandError::Read(MemoryError::InvalidPointer(address)))?;
+    let len = count.saturating_mul(MAX_INSTRUCTION_LEN).min(end - address);
+    let bytes = memory
+        .read_bytes_partial(address, len)
+        .map_err(CommandError::Read)?;
+    let instructions = disassemble(architecture, &bytes, address, count);
+    if instructions.len() < count {
+        warning!(
+            "Only {} of {count} instructions at 0x{address:X} were readable",
+            instructions.len()
+        );
+    }
+
+    for instruction in &instructions {
+        if output::json() {
+            output::print_json(instruction);
+            continue;
+        }
+
+        let symbol = instruction.target.and_then(|target| {
+            let symbol = memory.symbol_for_address(target).ok()??;
+            Some(if target == symbol.address {
+                symbol.display_name().to_string()
+            } else {
+                symbol.relative(target)
+            })
+        });
+        let bytes = format!(
+            "{:<width$}",
+            format_hex(&instruction.bytes),
+            width = BYTES_COLUMN_LEN * 3
+        );
+        match symbol {
+            Some(symbol) => println!(
+                "0x{:X}  {bytes}  {}  ; {symbol}",
+                instruction.address, instruction.text
+            ),
+            None => println!("0x{:X}  {bytes}  {}", instruction.address, instruction.text),
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,103 @@
+use std::{fs::File, io::Write as _, path::Path};
+
+use crate::{
+    address::AddressLocator,
+    args::DumpTarget,
+    commands::CommandError,
+    diagnostics::warning,
+    memory::{Memory, MemoryOptions},
+};
+
+/// bytes read and written at a time, so large dumps don't have to fit in memory
+const CHUNK_LEN: usize = 1 << 20;
+
+/// writes `length` bytes at the target to `output`, or the rest of its region without a length.
+/// a read running into an unreadable page writes the bytes before it
+pub fn dump(
+    pid: i32,
+    target: DumpTarget,
+    length: Option<usize>,
+    output: &Path,
+    options: MemoryOptions,
+) -> Result<(), CommandError> {
+    let memory = Memory::with_options(pid, options).map_err(CommandError::Open)?;
+
+    let (address, length) = match target {
+        DumpTarget::RegionIndex(index) => {
+            let Some(region) = memory.memory_regions().get(index) else {
+                return Err(CommandError::InvalidArguments(format!(
+                    "No region at index {index}, process {pid} has {}",
+                    memory.memory_regions().len()
+                )));
+            };
+            (region.start, length.unwrap_or(region.end - region.start))
+        }
+        DumpTarget::Address(locator) => {
+            let address = locator.resolve(&memory).map_err(CommandError::Resolve)?;
+            let length = match length {
+                Some(length) => length,
+                None => rest_of_region(&memory, &locator, address)?,
+            };
+            (address, length)
+        }
+    };
+
+    // never read past the mapping, a huge length would otherwise be allocated up front
+    let end = memory
+        .mapped_end(address)
+        .ok_or(CommandError::RegionNotFound(address))?;
+    let readable = length.min(end - address);
+
+    let path = output.display().to_string();
+    let mut file =
+        File::create(output).map_err(|err| CommandError::WriteFile(path.clone(), err))?;
+    let mut written = 0;
+    while written < readable {
+        let chunk_len = (readable - written).min(CHUNK_LEN);
+        let chunk = memory
+            .read_bytes_partial(address + written, chunk_len)
+            .or_else(|err| {
+                if written == 0 {
+                    Err(err)
+                } else {
+                    Ok(Vec::new())
+                }
+            })
+            .map_err(CommandError::Read)?;
+        file.write_all(&chunk)
+            .map_err(|err| CommandError::WriteFile(path.clone(), err))?;
+        written += chunk.len();
+        if chunk.len() < chunk_len {
+            break;
+        }
+    }
+
+    if written < length {
+        warning!("Only 0x{written:X} of 0x{length:X} bytes at 0x{address:X} were readable");
+    }
+    println!(
+        "Wrote {written} bytes from 0x{address:X} to {}",
+        output.display()
+    );
+    Ok(())
+}
+
+/// bytes from `address` to the end of its region, or of the whole image for module offsets
+fn rest_of_region(
+    memory: &Memory,
+    locator: &AddressLocator,
+    address: usize,
+) -> Result<usize, CommandError> {
+    let end = match locator {
+        AddressLocator::ModuleOffset(..) => memory
+            .mapped_images()
+            .iter()
+            .find(|image| image.start <= address && address < image.end)
+            .map(|image| image.end),
+        _ => memory
+            .find_containing_region(address)
+            .map(|region| region.end),
+    };
+    end.map(|end| end - address)
+        .ok_or(CommandError::RegionNotFound(address))
+}
@@ -0,0 +1,84 @@
+use crate::{
+    address::{AddressLocator, IdaSignature},
+    commands::CommandError,
+    memory::Memory,
+};
+
+/// signatures that are still ambiguous at this length are given up on
+const MAX_SIGNATURE_LEN: usize = 128;
+const SIGNATURE_LEN_STEP: usize = 4;
+
+pub fn export_sig(pid: i32, address: AddressLocator, len: usize) -> Result<(), CommandError> {
+    let memory = Memory::new(pid).map_err(CommandError::Open)?;
+    let address = address.resolve(&memory).map_err(CommandError::Resolve)?;
+
+    let mut len = len.max(1);
+    while len <= MAX_SIGNATURE_LEN {
+        let bytes = memory
+            .read_bytes(address, len)
+            .map_err(CommandError::Read)?;
+        let signature = IdaSignature::new(wildcard_displacements(&bytes), None);
+
+        let matches = memory
+            .scan_signature_all(&signature)
+            .map_err(CommandError::Resolve)?;
+        if !matches.contains(&address) {
+            return Err(CommandError::NotScannable(address));
+        }
+        if matches.len() == 1 {
+            println!("{signature}");
+            return Ok(());
+        }
+
+        len += SIGNATURE_LEN_STEP;
+    }
+
+    Err(CommandError::NoUniqueSignature(address))
+}
+
+/// turns bytes into a pattern, wildcarding x86-64 rel32 and rip-relative displacements,
+/// since those change whenever code or data moves
+pub fn wildcard_displacements(bytes: &[u8]) -> Vec<Option<u8>> {
+    let mut pattern: Vec<Option<u8>> = bytes.iter().copied().map(Some).collect();
+
+    let mut i = 0;
+    while i < bytes.len() {
+        let displacement_start = match bytes[i..] {
+            // call rel32, jmp rel32
+            [0xE8 | 0xE9, ..] => Some(i + 1),
+            // jcc rel32
+            [0x0F, 0x80..=0x8F, ..] => Some(i + 2),
+            // rex prefix, opcode, modrm with rip-relative addressing
+            [0x40..=0x4F, opcode, modrm, ..] if has_modrm(opcode) && is_rip_relative(modrm) => {
+                Some(i + 3)
+            }
+            [opcode, modrm, ..] if has_modrm(opcode) && is_rip_relative(modrm) => Some(i + 2),
+            _ => None,
+        };
+
+        match displacement_start {
+            Some(start) => {
+                let end = (start + 4).min(bytes.len());
+                pattern[start..end].fill(None);
+                i = end;
+            }
+            None => i += 1,
+        }
+    }
+
+    pattern
+}
+
+/// common opcodes that take a modrm byte and are often used with rip-relative operands
+const MODRM_OPCODES: &[u8] = &[
+    0x01, 0x03, 0x09, 0x0B, 0x29, 0x2B, 0x31, 0x33, 0x39, 0x3B, 0x63, 0x80, 0x81, 0x83, 0x85, 0x88,
+    0x89, 0x8A, 0x8B, 0x8D, 0xC6, 0xC7, 0xFF,
+];
+
+fn has_modrm(opcode: u8) -> bool {
+    MODRM_OPCODES.contains(&opcode)
+}
+
+fn is_rip_relative(modrm: u8) -> bool {
+    modrm & 0xC7 == 0x05
+}
@@ -1,60 +1,359 @@
-use crate::{address::AddressLocator, memory::Memory};
+use crate::{
+    address::{AddressLocator, IdaSignature, ResolvedMatch},
+    commands::{
+        CommandError, hexdump::print_hexdump, resolve::order_resolved, warn_if_match_limit_reached,
+    },
+    diagnostics::{report_match_count, warning},
+    memory::{Memory, MemoryError, MemoryOptions},
+    output,
+};
 
-pub fn find(pid: i32, address: AddressLocator) {
-    let memory = match Memory::new(pid) {
-        Ok(memory) => memory,
-        Err(err) => {
-            eprintln!("Unable to open process memory: {err}");
-            return;
+#[derive(Debug, Clone, Default)]
+pub struct FindOptions {
+    pub print_bytes: bool,
+    /// bytes to hexdump before and after the match
+    pub context: Option<usize>,
+    pub suggest: bool,
+    /// every match, not only the first
+    pub all: bool,
+    /// keep `all` matches in the order they were found instead of by resolved address
+    pub unsorted: bool,
+    pub unique_only: bool,
+}
+
+pub fn find(
+    pid: i32,
+    address: AddressLocator,
+    find_options: &FindOptions,
+    options: MemoryOptions,
+) -> Result<(), CommandError> {
+    let memory = Memory::with_options(pid, options).map_err(CommandError::Open)?;
+    let FindOptions {
+        mut print_bytes,
+        mut context,
+        suggest,
+        all,
+        unsorted,
+        unique_only,
+    } = *find_options;
+
+    // the hexdumps don't fit into a json record
+    if output::json() && (print_bytes || context.is_some()) {
+        warning!("--bytes and --context are ignored with --format json");
+        (print_bytes, context) = (false, None);
+    }
+
+    let result = if all {
+        let resolved = address.resolve_all(&memory).map_err(CommandError::Resolve);
+        resolved.and_then(|resolved| {
+            let match_count = resolved.len();
+            let resolved = order_resolved(resolved, !unsorted, unique_only);
+            find_all(&memory, &address, resolved, print_bytes, context)?;
+            warn_if_match_limit_reached(&memory, match_count);
+            report_match_count(match_count);
+            Ok(())
+        })
+    } else {
+        find_first(&memory, &address, print_bytes, context)
+    };
+    match result {
+        Err(CommandError::Resolve(MemoryError::SignatureNotFound(signature))) if suggest => {
+            // only a hint, the pattern not matching stays the error
+            if let Err(err) = print_longest_prefix(&memory, &signature) {
+                warning!("Unable to find a matching prefix: {err}");
+            }
+            Err(CommandError::Resolve(MemoryError::SignatureNotFound(
+                signature,
+            )))
         }
+        result => result,
+    }
+}
+
+fn find_first(
+    memory: &Memory,
+    locator: &AddressLocator,
+    print_bytes: bool,
+    context: Option<usize>,
+) -> Result<(), CommandError> {
+    let address = locator.resolve(memory).map_err(CommandError::Resolve)?;
+    print_found(memory, address, None)?;
+
+    if !print_bytes && context.is_none() {
+        return Ok(());
+    }
+
+    let Some(signature) = locator.signature() else {
+        warning!("Absolute addresses have no pattern to print");
+        return Ok(());
     };
+    let match_address = find_match(memory, signature)?;
+    print_match(memory, signature, match_address, print_bytes, context)
+}
+
+/// every signature match, overlapping ones included, each followed through the pointer chain.
+/// matches whose chain breaks are reported without ending the search
+fn find_all(
+    memory: &Memory,
+    locator: &AddressLocator,
+    resolved: Vec<ResolvedMatch>,
+    print_bytes: bool,
+    context: Option<usize>,
+) -> Result<(), CommandError> {
+    for (match_address, result) in resolved {
+        let address = match result {
+            Ok(address) => address,
+            Err(err) => {
+                eprintln!("0x{match_address:X} -> {err}");
+                continue;
+            }
+        };
+        let signature = locator.signature();
+        print_found(memory, address, signature.map(|_| match_address))?;
+
+        if let Some(signature) = signature {
+            print_match(memory, signature, match_address, print_bytes, context)?;
+        }
+    }
+    Ok(())
+}
+
+/// `match_address` is where the signature itself matched, given when every match is listed
+fn print_found(
+    memory: &Memory,
+    address: usize,
+    match_address: Option<usize>,
+) -> Result<(), CommandError> {
+    let memory_region = memory
+        .find_containing_region(address)
+        .ok_or(CommandError::RegionNotFound(address))?;
+    // the symbol is only a hint, a module that can't be parsed doesn't fail the search
+    let symbol = memory
+        .symbol_for_address(address)
+        .ok()
+        .flatten()
+        .map(|symbol| symbol.relative(address));
 
-    let address = match address.resolve(&memory) {
-        Ok(address) => address,
-        Err(err) => {
-            eprintln!("Unable to resolve address: {err}");
-            return;
+    if output::json() {
+        let mut record = serde_json::json!({
+            "address": format!("0x{address:X}"),
+            "pathname": memory_region.pathname,
+        });
+        if let Some(match_address) = match_address {
+            record["match"] = format!("0x{match_address:X}").into();
         }
+        if let Some(symbol) = symbol {
+            record["symbol"] = symbol.into();
+        }
+        output::print_json(&record);
+        return Ok(());
+    }
+
+    match symbol {
+        Some(symbol) => println!(
+            "Found signature at 0x{address:X} in {} ({symbol})",
+            memory_region.pathname
+        ),
+        None => println!(
+            "Found signature at 0x{address:X} in {}",
+            memory_region.pathname
+        ),
+    }
+    Ok(())
+}
+
+fn print_match(
+    memory: &Memory,
+    signature: &IdaSignature,
+    match_address: usize,
+    print_bytes: bool,
+    context: Option<usize>,
+) -> Result<(), CommandError> {
+    if print_bytes {
+        print_matched_bytes(memory, signature, match_address)?;
+    }
+    if let Some(context) = context {
+        print_match_context(memory, signature, match_address, context)?;
+    }
+    Ok(())
+}
+
+/// shorter prefixes match almost anywhere, so they say nothing about the divergence
+const MIN_PREFIX_LEN: usize = 4;
+
+/// the length of the longest prefix of a pattern that doesn't match anywhere that still does,
+/// `None` when not even the first `MIN_PREFIX_LEN` bytes do. every prefix of a matching prefix
+/// matches too, so a binary search needs only log2(len) scans
+pub fn longest_matching_prefix(
+    memory: &Memory,
+    signature: &IdaSignature,
+) -> Result<Option<usize>, MemoryError> {
+    let pattern = signature.pattern();
+    let prefix_matches = |len: usize| -> Result<bool, MemoryError> {
+        let prefix = IdaSignature::new(pattern[..len].to_vec(), None);
+        Ok(memory.scan_signature(&prefix)?.is_some())
     };
 
-    let memory_region = match memory.find_containing_region(address) {
-        Some(region) => region,
-        None => {
-            eprintln!("Unable to find containing memory region for address 0x{address:X}");
-            return;
+    if pattern.len() <= MIN_PREFIX_LEN || !prefix_matches(MIN_PREFIX_LEN)? {
+        return Ok(None);
+    }
+
+    // the longest matching prefix is in `matching..not_matching`
+    let (mut matching, mut not_matching) = (MIN_PREFIX_LEN, pattern.len());
+    while not_matching - matching > 1 {
+        let len = matching + (not_matching - matching) / 2;
+        if prefix_matches(len)? {
+            matching = len;
+        } else {
+            not_matching = len;
         }
+    }
+    Ok(Some(matching))
+}
+
+/// shows where the target diverged from a pattern that doesn't match
+fn print_longest_prefix(memory: &Memory, signature: &IdaSignature) -> Result<(), CommandError> {
+    const MAX_SHOWN_MATCHES: usize = 5;
+
+    let pattern = signature.pattern();
+    let Some(matching) =
+        longest_matching_prefix(memory, signature).map_err(CommandError::Resolve)?
+    else {
+        println!(
+            "Not even the first {} bytes of the pattern match",
+            MIN_PREFIX_LEN.min(pattern.len())
+        );
+        return Ok(());
     };
 
+    let longest = IdaSignature::new(pattern[..matching].to_vec(), None);
+    let matches = memory
+        .scan_signature_all(&longest)
+        .map_err(CommandError::Resolve)?;
     println!(
-        "Found signature at 0x{address:X} in {}",
-        memory_region.pathname
+        "Longest matching prefix is {matching} of {} bytes ({longest}), {} matches",
+        pattern.len(),
+        matches.len()
     );
-}
 
-pub fn find_function(pid: i32, function_name: String) {
-    let memory = match Memory::new(pid) {
-        Ok(memory) => memory,
-        Err(err) => {
-            eprintln!("Unable to open process memory: {err}");
-            return;
+    // the byte after the prefix is a concrete one, a wildcard there would have matched
+    let expected = pattern[matching].unwrap_or_default();
+    for match_address in matches.into_iter().take(MAX_SHOWN_MATCHES) {
+        // the prefix can end right at the end of the mapped memory
+        match memory.read::<u8>(match_address + matching) {
+            Ok(found) => println!(
+                "0x{match_address:X}: byte {matching} is {found:02X}, the pattern expects {expected:02X}"
+            ),
+            Err(_) => println!("0x{match_address:X}: byte {matching} is past the mapped memory"),
         }
-    };
+    }
 
-    let functions = match memory.find_function(&function_name) {
-        Ok(functions) => functions,
-        Err(err) => {
-            eprintln!("Unable to find function '{function_name}': {err}");
-            return;
-        }
-    };
+    Ok(())
+}
+
+/// the resolved address may be a rip-relative target, so locate the raw match again
+fn find_match(memory: &Memory, signature: &IdaSignature) -> Result<usize, CommandError> {
+    memory
+        .scan_signature(signature)
+        .map_err(CommandError::Resolve)?
+        .ok_or_else(|| CommandError::Resolve(MemoryError::SignatureNotFound(signature.clone())))
+}
+
+fn print_matched_bytes(
+    memory: &Memory,
+    signature: &IdaSignature,
+    match_address: usize,
+) -> Result<(), CommandError> {
+    let pattern = signature.pattern();
+    let bytes = memory
+        .read_bytes(match_address, pattern.len())
+        .map_err(CommandError::Read)?;
+
+    let bytes_str = bytes
+        .iter()
+        .zip(pattern)
+        .map(|(byte, pattern_byte)| match pattern_byte {
+            Some(_) => format!("{byte:02X}"),
+            None => format!("[{byte:02X}]"),
+        })
+        .collect::<Vec<_>>()
+        .join(" ");
+    println!("Matched bytes at 0x{match_address:X}: {bytes_str}");
+
+    Ok(())
+}
+
+fn print_match_context(
+    memory: &Memory,
+    signature: &IdaSignature,
+    match_address: usize,
+    context: usize,
+) -> Result<(), CommandError> {
+    let region = memory
+        .find_containing_region(match_address)
+        .ok_or(CommandError::RegionNotFound(match_address))?;
+    // the match can sit at the end of a region that the next one continues
+    let mapped_end = memory.mapped_end(match_address).unwrap_or(region.end);
+
+    let match_end = match_address + signature.pattern().len();
+    let start = match_address.saturating_sub(context).max(region.start);
+    let end = match_end.saturating_add(context).min(mapped_end);
+
+    // stops early at an unreadable page after the match, like a guard region of the module
+    let bytes = memory
+        .read_bytes_partial(start, end - start)
+        .map_err(CommandError::Read)?;
+    print_hexdump(
+        start,
+        &bytes,
+        Some(match_address - start..match_end - start),
+    );
+
+    Ok(())
+}
+
+pub fn find_function(
+    pid: i32,
+    function_name: String,
+    include_debuglink: bool,
+    address_only: bool,
+    raw: bool,
+) -> Result<(), CommandError> {
+    let memory = Memory::new(pid).map_err(CommandError::Open)?;
+
+    let mut functions = memory
+        .find_function(&function_name, include_debuglink)
+        .map_err(|err| CommandError::FindFunction(function_name.clone(), err))?;
 
     if functions.is_empty() {
-        eprintln!("Could not find function '{function_name}'");
-        return;
+        return Err(CommandError::FunctionNotFound(function_name));
+    }
+    if raw {
+        functions
+            .iter_mut()
+            .for_each(|function| function.demangled = None);
     }
 
-    println!("Found function '{function_name}' at these locations:");
-    for function in functions {
-        println!("0x{:X} at {}", function.address, function.pathname);
+    let match_count = functions.len();
+    if output::json() {
+        functions.iter().for_each(output::print_json);
+    } else if address_only {
+        for function in functions {
+            println!("0x{:X}", function.address);
+        }
+    } else {
+        println!("Found function '{function_name}' at these locations:");
+        for function in functions {
+            // a demangled query can match several overloads, so their names are shown
+            match function.display_name() {
+                name if name == function_name => {
+                    println!("0x{:X} at {}", function.address, function.pathname)
+                }
+                name => println!("0x{:X} at {} ({name})", function.address, function.pathname),
+            }
+        }
     }
+    report_match_count(match_count);
+
+    Ok(())
 }
@@ -0,0 +1,70 @@
+use std::ops::Range;
+
+const BYTES_PER_LINE: usize = 16;
+
+/// ` [c3 f5 48 40]`, appended to a value to show the bytes it was decoded from
+pub fn format_value_bytes(bytes: Option<&[u8]>) -> String {
+    let Some(bytes) = bytes else {
+        return String::new();
+    };
+
+    format!(" [{}]", format_hex(bytes))
+}
+
+/// bytes as space separated lowercase hex pairs
+pub fn format_hex(bytes: &[u8]) -> String {
+    bytes
+        .iter()
+        .map(|byte| format!("{byte:02x}"))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// bytes as a `format_hex` string in json output
+pub fn serialize_hex<S: serde::Serializer>(bytes: &[u8], serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.serialize_str(&format_hex(bytes))
+}
+
+/// prints `bytes` as a canonical hexdump starting at `base`,
+/// bracketing the bytes in `highlight` (given as offsets into `bytes`)
+pub fn print_hexdump(base: usize, bytes: &[u8], highlight: Option<Range<usize>>) {
+    let highlight = highlight.filter(|range| !range.is_empty());
+
+    for (line_index, line) in bytes.chunks(BYTES_PER_LINE).enumerate() {
+        let offset = line_index * BYTES_PER_LINE;
+
+        let mut hex = String::new();
+        for (i, byte) in line.iter().enumerate() {
+            let index = offset + i;
+            let separator = match &highlight {
+                Some(range) if index == range.start => '[',
+                Some(range) if index == range.end && i != 0 => ']',
+                _ => ' ',
+            };
+            hex.push(separator);
+            hex.push_str(&format!("{byte:02X}"));
+        }
+        let line_end = offset + line.len();
+        match &highlight {
+            Some(range) if line_end == range.end => hex.push(']'),
+            _ => hex.push(' '),
+        }
+
+        let ascii: String = line
+            .iter()
+            .map(|&byte| {
+                if byte.is_ascii_graphic() || byte == b' ' {
+                    byte as char
+                } else {
+                    '.'
+                }
+            })
+            .collect();
+
+        println!(
+            "{:016X} {hex:<width$} |{ascii}|",
+            base + offset,
+            width = BYTES_PER_LINE * 3 + 1
+        );
+    }
+}
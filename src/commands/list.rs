@@ -1,15 +1,317 @@
-use crate::memory::Memory;
+use std::path::Path;
 
-pub fn list(pid: i32) {
-    let memory = match Memory::new(pid) {
-        Ok(memory) => memory,
-        Err(err) => {
-            eprintln!("Unable to open process memory: {err}");
-            return;
+use crate::{
+    commands::{CommandError, for_each_pid},
+    diagnostics::warning,
+    memory::{Memory, MemoryRegion, Permissions},
+    output,
+};
+
+pub fn list(
+    pids: &[i32],
+    tree: bool,
+    resident: bool,
+    required: Permissions,
+    snapshot: Option<&Path>,
+    diff: Option<&Path>,
+) -> Result<(), CommandError> {
+    if let Some(path) = snapshot {
+        let [pid] = pids else {
+            return Err(CommandError::InvalidArguments(
+                "A snapshot can only be taken of a single process".to_string(),
+            ));
+        };
+        return write_snapshot(path, *pid);
+    }
+
+    let old_regions = diff.map(read_snapshot).transpose()?;
+
+    for_each_pid(pids, |pid| {
+        // json records carry their pid instead of a heading
+        let label = (pids.len() > 1).then_some(pid);
+        if let Some(pid) = label
+            && !output::json()
+        {
+            println!("{pid}:");
+        }
+        match &old_regions {
+            Some(old_regions) => print_diff(pid, label, old_regions),
+            None => list_regions(pid, label, tree, resident, required),
+        }
+    })
+}
+
+fn write_snapshot(path: &Path, pid: i32) -> Result<(), CommandError> {
+    let memory = Memory::new(pid).map_err(CommandError::Open)?;
+
+    let snapshot = serde_json::json!({ "pid": pid, "regions": memory.memory_regions() });
+
+    std::fs::write(path, format!("{snapshot:#}\n"))
+        .map_err(|err| CommandError::WriteFile(path.display().to_string(), err))
+}
+
+fn read_snapshot(path: &Path) -> Result<Vec<MemoryRegion>, CommandError> {
+    let path_str = path.display().to_string();
+    let invalid =
+        |reason: &str| CommandError::InvalidSnapshot(path_str.clone(), reason.to_string());
+
+    let contents = std::fs::read_to_string(path)
+        .map_err(|err| CommandError::ReadFile(path_str.clone(), err))?;
+    let snapshot: serde_json::Value =
+        serde_json::from_str(&contents).map_err(|err| invalid(&err.to_string()))?;
+    let regions = snapshot["regions"]
+        .as_array()
+        .ok_or_else(|| invalid("missing regions"))?;
+
+    let address = |value: &serde_json::Value| {
+        value
+            .as_str()
+            .and_then(|address| address.strip_prefix("0x"))
+            .and_then(|address| usize::from_str_radix(address, 16).ok())
+            .ok_or_else(|| invalid(&format!("invalid address {value}")))
+    };
+    regions
+        .iter()
+        .map(|region| {
+            Ok(MemoryRegion {
+                start: address(&region["start"])?,
+                end: address(&region["end"])?,
+                // older snapshots were taken without permissions
+                permissions: region["permissions"]
+                    .as_str()
+                    .map(Permissions::parse)
+                    .unwrap_or_default(),
+                pathname: region["pathname"]
+                    .as_str()
+                    .ok_or_else(|| invalid("region without pathname"))?
+                    .to_string(),
+            })
+        })
+        .collect()
+}
+
+/// regions are matched by pathname and start address, so a region that grew in place is
+/// changed rather than removed and added again
+#[derive(Debug, Default)]
+pub struct RegionDiff<'a> {
+    pub added: Vec<&'a MemoryRegion>,
+    pub removed: Vec<&'a MemoryRegion>,
+    /// old and new version of each resized region
+    pub changed: Vec<(&'a MemoryRegion, &'a MemoryRegion)>,
+}
+
+pub fn diff_regions<'a>(old: &'a [MemoryRegion], new: &'a [MemoryRegion]) -> RegionDiff<'a> {
+    let same_region =
+        |a: &MemoryRegion, b: &MemoryRegion| a.start == b.start && a.pathname == b.pathname;
+
+    let mut diff = RegionDiff::default();
+    for new_region in new {
+        match old
+            .iter()
+            .find(|old_region| same_region(old_region, new_region))
+        {
+            Some(old_region) if old_region.end != new_region.end => {
+                diff.changed.push((old_region, new_region));
+            }
+            Some(_) => {}
+            None => diff.added.push(new_region),
+        }
+    }
+    diff.removed = old
+        .iter()
+        .filter(|old_region| {
+            !new.iter()
+                .any(|new_region| same_region(old_region, new_region))
+        })
+        .collect();
+
+    diff
+}
+
+/// a region as json, with the pid it belongs to when several processes are listed
+fn region_record(region: &MemoryRegion, label: Option<i32>) -> serde_json::Value {
+    let mut record = serde_json::json!(region);
+    if let Some(pid) = label {
+        record["pid"] = pid.into();
+    }
+    record
+}
+
+fn print_diff(
+    pid: i32,
+    label: Option<i32>,
+    old_regions: &[MemoryRegion],
+) -> Result<(), CommandError> {
+    let memory = Memory::new(pid).map_err(CommandError::Open)?;
+    let diff = diff_regions(old_regions, memory.memory_regions());
+
+    if output::json() {
+        let print_change = |change: &str, region: &MemoryRegion| {
+            let mut record = region_record(region, label);
+            record["change"] = change.into();
+            output::print_json(&record);
+        };
+        diff.added
+            .into_iter()
+            .for_each(|region| print_change("added", region));
+        diff.removed
+            .into_iter()
+            .for_each(|region| print_change("removed", region));
+        for (old, new) in diff.changed {
+            let mut record = region_record(new, label);
+            record["change"] = "changed".into();
+            record["old_end"] = format!("0x{:X}", old.end).into();
+            output::print_json(&record);
         }
+        return Ok(());
+    }
+
+    let print_region = |region: &MemoryRegion| {
+        println!(
+            "    {:X}-{:X} {} (0x{:X} bytes)",
+            region.start,
+            region.end,
+            region.pathname,
+            region.end - region.start
+        );
     };
 
-    for region in memory.memory_regions() {
-        println!("{:X}-{:X} {}", region.start, region.end, region.pathname);
+    println!("added:");
+    diff.added.into_iter().for_each(print_region);
+    println!("removed:");
+    diff.removed.into_iter().for_each(print_region);
+    println!("changed:");
+    for (old, new) in diff.changed {
+        println!(
+            "    {:X} {}: 0x{:X} -> 0x{:X} bytes",
+            new.start,
+            new.pathname,
+            old.end - old.start,
+            new.end - new.start
+        );
+    }
+
+    Ok(())
+}
+
+fn list_regions(
+    pid: i32,
+    label: Option<i32>,
+    tree: bool,
+    resident: bool,
+    required: Permissions,
+) -> Result<(), CommandError> {
+    let memory = Memory::new(pid).map_err(CommandError::Open)?;
+
+    // pagemap needs ptrace access to the target, so regions are still listed without it
+    let resident_sizes = if resident {
+        match memory.resident_sizes() {
+            Ok(sizes) => Some(sizes),
+            Err(err) => {
+                warning!("Unable to read resident sizes: {err}");
+                None
+            }
+        }
+    } else {
+        None
+    };
+
+    if tree {
+        print_tree(&memory, label, resident_sizes.as_deref(), required);
+        return Ok(());
+    }
+
+    for (i, region) in memory.memory_regions().iter().enumerate() {
+        if !region.permissions.contains(required) {
+            continue;
+        }
+        if output::json() {
+            let mut record = region_record(region, label);
+            if let Some(sizes) = &resident_sizes {
+                record["resident"] = sizes[i].into();
+            }
+            output::print_json(&record);
+            continue;
+        }
+        match &resident_sizes {
+            Some(sizes) => println!(
+                "{:X}-{:X} {} {:>10} {}",
+                region.start,
+                region.end,
+                region.permissions,
+                format_kb(sizes[i]),
+                region.pathname
+            ),
+            None => println!(
+                "{:X}-{:X} {} {}",
+                region.start, region.end, region.permissions, region.pathname
+            ),
+        }
     }
+
+    Ok(())
+}
+
+fn print_tree(
+    memory: &Memory,
+    label: Option<i32>,
+    resident_sizes: Option<&[usize]>,
+    required: Permissions,
+) {
+    let shown = |region: &MemoryRegion| region.permissions.contains(required);
+
+    for (pathname, mut regions) in memory.regions_by_module() {
+        // the base stays the module's, even when its first region is filtered out
+        let base = regions[0].start;
+        regions.retain(|region| shown(region));
+        if regions.is_empty() {
+            continue;
+        }
+        let size: usize = regions.iter().map(|region| region.end - region.start).sum();
+
+        let resident = resident_sizes.map(|sizes| {
+            memory
+                .memory_regions()
+                .iter()
+                .zip(sizes)
+                .filter(|(region, _)| region.pathname == pathname && shown(region))
+                .map(|(_, size)| size)
+                .sum::<usize>()
+        });
+        if output::json() {
+            let mut record = serde_json::json!({
+                "pathname": pathname,
+                "base": format!("0x{base:X}"),
+                "size": size,
+                "regions": regions,
+            });
+            if let Some(pid) = label {
+                record["pid"] = pid.into();
+            }
+            if let Some(resident) = resident {
+                record["resident"] = resident.into();
+            }
+            output::print_json(&record);
+            continue;
+        }
+
+        match resident {
+            Some(resident) => println!(
+                "{pathname} (base 0x{base:X}, size 0x{size:X}, resident {})",
+                format_kb(resident)
+            ),
+            None => println!("{pathname} (base 0x{base:X}, size 0x{size:X})"),
+        }
+
+        for region in regions {
+            println!(
+                "    {:X}-{:X} {}",
+                region.start, region.end, region.permissions
+            );
+        }
+    }
+}
+
+fn format_kb(bytes: usize) -> String {
+    format!("{} kB", bytes / 1024)
 }
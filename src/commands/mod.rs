@@ -1,5 +1,220 @@
+use thiserror::Error;
+
+use crate::{
+    diagnostics::warning,
+    memory::{Memory, MemoryError},
+};
+
+pub mod batch;
+pub mod disasm;
+pub mod dump;
+pub mod export_sig;
 pub mod find;
+pub mod hexdump;
 pub mod list;
+pub mod monitor_sig;
+pub mod processes;
 pub mod read;
+pub mod replace;
+pub mod resolve;
+pub mod scan;
+pub mod self_test;
 pub mod snap;
+pub mod strings;
 pub mod watch;
+pub mod write;
+
+pub const EXIT_FAILURE: u8 = 1;
+pub const EXIT_NOT_FOUND: u8 = 2;
+pub const EXIT_PERMISSION_DENIED: u8 = 3;
+pub const EXIT_INVALID_MEMORY: u8 = 4;
+/// 128 + SIGINT, what shells report for a program ended by ctrl-c
+pub const EXIT_INTERRUPTED: u8 = 130;
+
+#[derive(Debug, Error)]
+pub enum CommandError {
+    #[error("Unable to open process memory: {0}")]
+    Open(MemoryError),
+    #[error("Unable to stop process: {0}")]
+    Stop(MemoryError),
+    #[error("Unable to resolve address: {0}")]
+    Resolve(MemoryError),
+    #[error("Unable to read memory: {0}")]
+    Read(MemoryError),
+    #[error("Unable to write memory: {0}")]
+    Write(MemoryError),
+    #[error("Unable to find containing memory region for address 0x{0:X}")]
+    RegionNotFound(usize),
+    #[error("Unable to find function '{0}': {1}")]
+    FindFunction(String, MemoryError),
+    #[error("Could not find function '{0}'")]
+    FunctionNotFound(String),
+    #[error("Failed to find library '{0}'")]
+    LibraryNotFound(String),
+    #[error("Address 0x{0:X} is not inside a module image that can be scanned")]
+    NotScannable(usize),
+    #[error("Unable to generate a unique signature for address 0x{0:X}")]
+    NoUniqueSignature(usize),
+    #[error("Unable to read file '{0}': {1}")]
+    ReadFile(String, std::io::Error),
+    #[error("Unable to write to file '{0}': {1}")]
+    WriteFile(String, std::io::Error),
+    #[error("Invalid snapshot '{0}': {1}")]
+    InvalidSnapshot(String, String),
+    #[error("{0}")]
+    InvalidArguments(String),
+    #[error("{}", format_no_scan_session(.0, *.1))]
+    NoScanSession(String, Option<i32>),
+    #[error("Invalid scan session '{0}': {1}")]
+    InvalidScanSession(String, String),
+    #[error("Invalid entry on line {0}: {1}")]
+    InvalidEntry(usize, String),
+    #[error("{0} of {1} entries failed")]
+    EntryFailures(usize, usize),
+    #[error("Disassembling {0} code is not supported")]
+    UnsupportedArchitecture(String),
+    #[error("{0} of {1} self-test checks failed")]
+    SelfTestFailures(usize, usize),
+    #[error(
+        "Refusing to write to executable memory at 0x{0:X} in '{1}', pass --allow-exec to patch code"
+    )]
+    ExecutableMemory(usize, String),
+    #[error("Interrupted")]
+    Interrupted,
+    #[error("{}", format_pid_failures(.0))]
+    PidFailures(Vec<(i32, CommandError)>),
+}
+
+impl CommandError {
+    pub fn exit_code(&self) -> u8 {
+        match self {
+            Self::Open(err)
+            | Self::Stop(err)
+            | Self::Resolve(err)
+            | Self::Read(err)
+            | Self::Write(err)
+            | Self::FindFunction(_, err) => memory_error_exit_code(err),
+            Self::RegionNotFound(_)
+            | Self::FunctionNotFound(_)
+            | Self::LibraryNotFound(_)
+            | Self::NotScannable(_)
+            | Self::NoUniqueSignature(_) => EXIT_NOT_FOUND,
+            Self::ReadFile(_, err) | Self::WriteFile(_, err) => io_error_exit_code(err),
+            Self::InvalidSnapshot(..)
+            | Self::NoScanSession(..)
+            | Self::InvalidScanSession(..)
+            | Self::InvalidArguments(_)
+            | Self::ExecutableMemory(..)
+            | Self::UnsupportedArchitecture(_)
+            | Self::InvalidEntry(..)
+            | Self::EntryFailures(..)
+            | Self::SelfTestFailures(..) => EXIT_FAILURE,
+            Self::Interrupted => EXIT_INTERRUPTED,
+            Self::PidFailures(failures) => failures
+                .first()
+                .map_or(EXIT_FAILURE, |(_, err)| err.exit_code()),
+        }
+    }
+}
+
+impl CommandError {
+    /// the `kind` of json error output. errors from reading memory report the memory error, so
+    /// a missing signature is `SignatureNotFound` whichever command looked for it
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Self::Open(err)
+            | Self::Stop(err)
+            | Self::Resolve(err)
+            | Self::Read(err)
+            | Self::Write(err)
+            | Self::FindFunction(_, err) => err.kind(),
+            Self::RegionNotFound(_) => "RegionNotFound",
+            Self::FunctionNotFound(_) => "FunctionNotFound",
+            Self::LibraryNotFound(_) => "LibraryNotFound",
+            Self::NotScannable(_) => "NotScannable",
+            Self::NoUniqueSignature(_) => "NoUniqueSignature",
+            Self::ReadFile(..) => "ReadFile",
+            Self::WriteFile(..) => "WriteFile",
+            Self::InvalidSnapshot(..) => "InvalidSnapshot",
+            Self::InvalidArguments(_) => "InvalidArguments",
+            Self::NoScanSession(..) => "NoScanSession",
+            Self::InvalidScanSession(..) => "InvalidScanSession",
+            Self::InvalidEntry(..) => "InvalidEntry",
+            Self::EntryFailures(..) => "EntryFailures",
+            Self::UnsupportedArchitecture(_) => "UnsupportedArchitecture",
+            Self::SelfTestFailures(..) => "SelfTestFailures",
+            Self::ExecutableMemory(..) => "ExecutableMemory",
+            Self::Interrupted => "Interrupted",
+            Self::PidFailures(_) => "PidFailures",
+        }
+    }
+}
+
+fn format_no_scan_session(name: &str, pid: Option<i32>) -> String {
+    match pid {
+        Some(pid) => format!("No scan named '{name}' for process {pid}, run scan first"),
+        None => format!("No scan named '{name}'"),
+    }
+}
+
+fn format_pid_failures(failures: &[(i32, CommandError)]) -> String {
+    failures
+        .iter()
+        .map(|(pid, err)| format!("{pid}: {err}"))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+pub fn warn_if_match_limit_reached(memory: &Memory, count: usize) {
+    if memory.match_limit_reached(count) {
+        warning!("Stopped at {count} matches (pattern too loose)");
+    }
+}
+
+/// runs a command body once per pid, collecting failures so one bad pid doesn't hide the rest
+pub fn for_each_pid(
+    pids: &[i32],
+    mut f: impl FnMut(i32) -> Result<(), CommandError>,
+) -> Result<(), CommandError> {
+    if let [pid] = pids {
+        return f(*pid);
+    }
+
+    let failures: Vec<(i32, CommandError)> = pids
+        .iter()
+        .filter_map(|&pid| f(pid).err().map(|err| (pid, err)))
+        .collect();
+
+    if failures.is_empty() {
+        Ok(())
+    } else {
+        Err(CommandError::PidFailures(failures))
+    }
+}
+
+fn memory_error_exit_code(err: &MemoryError) -> u8 {
+    match err {
+        MemoryError::SignatureNotFound(_)
+        | MemoryError::FunctionNotFound(_)
+        | MemoryError::NamedRegionNotFound(_)
+        | MemoryError::ModuleNotFound(_)
+        | MemoryError::NoSymbol(_)
+        | MemoryError::ProcessGone(..) => EXIT_NOT_FOUND,
+        MemoryError::InvalidPointer(_)
+        | MemoryError::NotExecutable(_)
+        | MemoryError::PartialRead(..)
+        | MemoryError::OutOfBounds(..)
+        | MemoryError::PartialWrite(..) => EXIT_INVALID_MEMORY,
+        MemoryError::Io(err) => io_error_exit_code(err),
+        _ => EXIT_FAILURE,
+    }
+}
+
+fn io_error_exit_code(err: &std::io::Error) -> u8 {
+    match err.raw_os_error() {
+        Some(libc::EPERM | libc::EACCES) => EXIT_PERMISSION_DENIED,
+        Some(libc::ESRCH | libc::ENOENT) => EXIT_NOT_FOUND,
+        Some(libc::EFAULT) => EXIT_INVALID_MEMORY,
+        _ => EXIT_FAILURE,
+    }
+}
@@ -0,0 +1,64 @@
+use std::time::Duration;
+
+use crate::{
+    address::IdaSignature,
+    commands::{
+        CommandError, warn_if_match_limit_reached,
+        watch::{install_interrupt_handler, interrupted, sleep_interruptible},
+    },
+    memory::{Memory, MemoryOptions},
+};
+
+/// rescans until interrupted, printing whenever the set of matches changes
+pub fn monitor_sig(
+    pid: i32,
+    signature: IdaSignature,
+    interval: Duration,
+    options: MemoryOptions,
+) -> Result<(), CommandError> {
+    install_interrupt_handler();
+
+    let mut previous = Vec::new();
+    while !interrupted() {
+        // reopened every time, so libraries mapped in later get scanned too
+        let memory = Memory::with_options(pid, options.clone()).map_err(CommandError::Open)?;
+        let matches = memory
+            .scan_signature_all(&signature)
+            .map_err(CommandError::Resolve)?;
+        if matches.len() != previous.len() {
+            warn_if_match_limit_reached(&memory, matches.len());
+        }
+
+        print_transitions(&previous, &matches);
+        previous = matches;
+
+        sleep_interruptible(interval);
+    }
+
+    Ok(())
+}
+
+fn print_transitions(previous: &[usize], current: &[usize]) {
+    let appeared: Vec<usize> = current
+        .iter()
+        .copied()
+        .filter(|address| !previous.contains(address))
+        .collect();
+    let disappeared: Vec<usize> = previous
+        .iter()
+        .copied()
+        .filter(|address| !current.contains(address))
+        .collect();
+
+    if let ([from], [to]) = (disappeared.as_slice(), appeared.as_slice()) {
+        println!("moved 0x{from:X} -> 0x{to:X}");
+        return;
+    }
+
+    for address in disappeared {
+        println!("disappeared 0x{address:X}");
+    }
+    for address in appeared {
+        println!("appeared 0x{address:X}");
+    }
+}
@@ -0,0 +1,39 @@
+use crate::{commands::CommandError, process::processes as all_processes};
+
+/// command lines are cut to this many characters, so every process stays on one line
+const MAX_CMDLINE_LEN: usize = 80;
+
+/// prints pid, name and command line of every process, or of those whose name or command line
+/// contains `filter`
+pub fn processes(filter: Option<String>) -> Result<(), CommandError> {
+    for process in all_processes() {
+        // kernel threads have no command line, `ps` shows their name in brackets instead
+        let cmdline = if process.cmdline.is_empty() {
+            format!("[{}]", process.name)
+        } else {
+            process.cmdline.join(" ")
+        };
+        if let Some(filter) = &filter
+            && !process.name.contains(filter.as_str())
+            && !cmdline.contains(filter.as_str())
+        {
+            continue;
+        }
+
+        println!(
+            "{:>7} {:<15} {}",
+            process.pid,
+            process.name,
+            truncate(&cmdline, MAX_CMDLINE_LEN)
+        );
+    }
+
+    Ok(())
+}
+
+fn truncate(s: &str, max_len: usize) -> String {
+    match s.char_indices().nth(max_len) {
+        Some((end, _)) => format!("{}…", &s[..end]),
+        None => s.to_string(),
+    }
+}
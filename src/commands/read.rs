@@ -1,28 +1,220 @@
-use crate::{data_type::DataType, memory::Memory, address::AddressLocator};
+use std::{path::Path, time::Duration};
 
-pub fn read_once(pid: i32, addresss: AddressLocator, data_type: DataType) {
-    let memory = match Memory::new(pid) {
-        Ok(memory) => memory,
-        Err(err) => {
-            eprintln!("Unable to open process memory: {err}");
-            return;
+use crate::{
+    address::AddressLocator,
+    commands::{
+        CommandError, for_each_pid,
+        hexdump::{format_hex, format_value_bytes, print_hexdump},
+        watch::{install_interrupt_handler, interrupted, sleep_interruptible},
+    },
+    data_type::DataType,
+    diagnostics::warning,
+    memory::{Memory, MemoryError, MemoryOptions},
+    output,
+    value::Value,
+};
+
+/// how a single read is done and printed
+#[derive(Debug, Clone)]
+pub struct ReadOptions {
+    /// index and stride of an array element to read instead of the resolved address
+    pub element: Option<(usize, usize)>,
+    pub stop: bool,
+    pub stop_all: bool,
+    pub show_bytes: bool,
+    /// continue from the start of the function symbol containing the resolved address
+    pub rebase_to_symbol: bool,
+}
+
+/// applies `--max-units` to a wstring, which is the only type it means anything for
+pub fn with_max_units(
+    data_type: Option<DataType>,
+    max_units: Option<usize>,
+) -> Result<Option<DataType>, CommandError> {
+    match (data_type, max_units) {
+        (Some(DataType::WString { .. }), Some(max_units)) => Ok(Some(DataType::WString {
+            max_units: Some(max_units),
+        })),
+        (data_type, None) => Ok(data_type),
+        (data_type, Some(_)) => Err(CommandError::InvalidArguments(format!(
+            "--max-units only applies to wstring, not {}",
+            data_type.map_or("--hex".to_string(), |data_type| data_type.to_string())
+        ))),
+    }
+}
+
+pub fn read_once(
+    pids: &[i32],
+    addresss: &AddressLocator,
+    data_type: &DataType,
+    read_options: &ReadOptions,
+    options: &MemoryOptions,
+) -> Result<(), CommandError> {
+    let pid_width = pids.iter().map(|pid| pid.to_string().len()).max();
+
+    for_each_pid(pids, |pid| {
+        let (address, value, bytes) =
+            read_value(pid, addresss, data_type, read_options, options.clone())?;
+        if output::json() {
+            let mut record = serde_json::json!({
+                "address": format!("0x{address:X}"),
+                "type": data_type.to_string(),
+                "value": value,
+            });
+            if pids.len() > 1 {
+                record["pid"] = pid.into();
+            }
+            if read_options.show_bytes {
+                record["bytes"] = bytes.as_deref().map(format_hex).into();
+            }
+            output::print_json(&record);
+            return Ok(());
         }
-    };
 
-    let address = match addresss.resolve(&memory) {
-        Ok(address) => address,
-        Err(err) => {
-            eprintln!("Unable to resolve address: {err}");
-            return;
+        let bytes = if read_options.show_bytes {
+            format_value_bytes(bytes.as_deref())
+        } else {
+            String::new()
+        };
+
+        match pid_width {
+            Some(width) if pids.len() > 1 => {
+                println!("{pid:>width$}: 0x{address:X} = {value}{bytes}")
+            }
+            _ => println!("0x{address:X} = {value}{bytes}"),
         }
+        Ok(())
+    })
+}
+
+/// runs `read` now and again every time the trigger file's mtime changes, until ctrl-c ends it
+/// with `Interrupted`. failed reads are reported without ending the loop
+pub fn read_on_trigger(
+    trigger: &Path,
+    mut read: impl FnMut() -> Result<(), CommandError>,
+) -> Result<(), CommandError> {
+    const POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+    install_interrupt_handler();
+
+    let modified = || {
+        std::fs::metadata(trigger)
+            .and_then(|meta| meta.modified())
+            .ok()
     };
+    let mut last_modified = modified();
+    if let Err(err) = read() {
+        output::print_error(&err.to_string(), err.kind());
+    }
+
+    while !interrupted() {
+        sleep_interruptible(POLL_INTERVAL);
 
-    let value = match data_type.read(&memory, address) {
-        Ok(value) => value,
-        Err(err) => {
-            eprintln!("Unable to read memory: {err}");
-            return;
+        let current = modified();
+        if current == last_modified {
+            continue;
         }
-    };
-    println!("0x{address:X} = {value}");
+        last_modified = current;
+
+        if let Err(err) = read() {
+            output::print_error(&err.to_string(), err.kind());
+        }
+    }
+
+    Err(CommandError::Interrupted)
+}
+
+/// prints a hexdump of `len` bytes at the address instead of decoding a value. reads stop at
+/// the first unreadable page
+pub fn read_hex(
+    pids: &[i32],
+    addresss: &AddressLocator,
+    len: usize,
+    read_options: &ReadOptions,
+    options: &MemoryOptions,
+) -> Result<(), CommandError> {
+    for_each_pid(pids, |pid| {
+        let (memory, address) = resolve(pid, addresss, read_options, options.clone())?;
+        // the mapping bounds the allocation, not the requested length
+        let end = memory
+            .mapped_end(address)
+            .ok_or(CommandError::Read(MemoryError::InvalidPointer(address)))?;
+        let bytes = memory
+            .read_bytes_partial(address, len.min(end - address))
+            .map_err(CommandError::Read)?;
+        if bytes.len() < len {
+            warning!(
+                "Only 0x{:X} of 0x{len:X} bytes at 0x{address:X} were readable",
+                bytes.len()
+            );
+        }
+
+        if output::json() {
+            let mut record = serde_json::json!({
+                "address": format!("0x{address:X}"),
+                "bytes": format_hex(&bytes),
+            });
+            if pids.len() > 1 {
+                record["pid"] = pid.into();
+            }
+            output::print_json(&record);
+            return Ok(());
+        }
+
+        if pids.len() > 1 {
+            println!("{pid}:");
+        }
+        print_hexdump(address, &bytes, None);
+        Ok(())
+    })
+}
+
+fn read_value(
+    pid: i32,
+    addresss: &AddressLocator,
+    data_type: &DataType,
+    read_options: &ReadOptions,
+    options: MemoryOptions,
+) -> Result<(usize, Value, Option<Vec<u8>>), CommandError> {
+    let (memory, address) = resolve(pid, addresss, read_options, options)?;
+    let (value, bytes) = data_type
+        .read_with_bytes(&memory, address)
+        .map_err(CommandError::Read)?;
+
+    Ok((address, value, bytes))
+}
+
+/// opens the process, stopping it if asked to, and resolves the address of the read
+fn resolve(
+    pid: i32,
+    addresss: &AddressLocator,
+    read_options: &ReadOptions,
+    options: MemoryOptions,
+) -> Result<(Memory, usize), CommandError> {
+    let mut memory = Memory::with_options(pid, options).map_err(CommandError::Open)?;
+
+    if read_options.stop_all {
+        memory.attach_all().map_err(CommandError::Stop)?;
+    } else if read_options.stop {
+        memory.attach().map_err(CommandError::Stop)?;
+    }
+
+    let mut address = addresss.resolve(&memory).map_err(CommandError::Resolve)?;
+    if read_options.rebase_to_symbol {
+        address =
+            AddressLocator::rebase_to_symbol(&memory, address).map_err(CommandError::Resolve)?;
+    }
+
+    if let Some((index, stride)) = read_options.element {
+        // an index that overflows the address space is as invalid as one past the mapping
+        address = index
+            .checked_mul(stride)
+            .and_then(|offset| address.checked_add(offset))
+            .ok_or(CommandError::Resolve(MemoryError::InvalidPointer(address)))?;
+        if !memory.is_pointer_valid(address) {
+            return Err(CommandError::Resolve(MemoryError::InvalidPointer(address)));
+        }
+    }
+
+    Ok((memory, address))
 }
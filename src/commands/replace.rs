@@ -0,0 +1,85 @@
+use std::path::Path;
+
+use crate::{
+    address::AddressLocator,
+    commands::{
+        CommandError,
+        hexdump::format_hex,
+        write::{WriteOptions, WritePlan},
+    },
+    memory::{Memory, MemoryOptions},
+};
+
+/// x86 `nop`
+const NOP: u8 = 0x90;
+
+/// what `replace` writes at the address
+#[derive(Debug, Clone, PartialEq)]
+pub enum Replacement {
+    Bytes(Vec<u8>),
+    /// this many x86 `nop`s, only allowed in executable memory
+    Nop(usize),
+}
+
+impl Replacement {
+    pub fn bytes(&self) -> Vec<u8> {
+        match self {
+            Self::Bytes(bytes) => bytes.clone(),
+            Self::Nop(count) => vec![NOP; *count],
+        }
+    }
+}
+
+/// patches raw bytes at the address, even in read-only code. the bytes that were there can be
+/// saved to `backup` first, in the format the bytes are given in, so they can be written back
+pub fn replace(
+    pid: i32,
+    address: AddressLocator,
+    replacement: &Replacement,
+    write_options: &WriteOptions,
+    backup: Option<&Path>,
+    options: MemoryOptions,
+) -> Result<(), CommandError> {
+    let memory = Memory::with_options(pid, options).map_err(CommandError::Open)?;
+    let address = address.resolve(&memory).map_err(CommandError::Resolve)?;
+    let plan = plan_replace(&memory, address, replacement, write_options)?;
+    if write_options.dry_run {
+        plan.print();
+        return Ok(());
+    }
+
+    if let Some(path) = backup {
+        std::fs::write(path, format!("{}\n", format_hex(&plan.old)))
+            .map_err(|err| CommandError::WriteFile(path.display().to_string(), err))?;
+    }
+    memory
+        .patch_bytes(address, &plan.new)
+        .map_err(CommandError::Write)?;
+
+    println!(
+        "0x{address:X}: {} -> {}",
+        format_hex(&plan.old),
+        format_hex(&plan.new)
+    );
+    Ok(())
+}
+
+/// the checks a replace goes through, `--nop` only makes sense in code
+pub fn plan_replace(
+    memory: &Memory,
+    address: usize,
+    replacement: &Replacement,
+    write_options: &WriteOptions,
+) -> Result<WritePlan, CommandError> {
+    let bytes = replacement.bytes();
+    if let Replacement::Nop(count) = replacement
+        && memory
+            .find_executable_region(address, *count)
+            .is_none_or(|region| region.start > address || region.end - address < *count)
+    {
+        return Err(CommandError::InvalidArguments(format!(
+            "0x{address:X} is not in executable memory, --nop only patches code"
+        )));
+    }
+    WritePlan::new(memory, address, bytes, write_options)
+}
@@ -0,0 +1,114 @@
+use std::collections::HashSet;
+
+use crate::{
+    address::{AddressLocator, ResolvedMatch},
+    commands::{CommandError, warn_if_match_limit_reached},
+    diagnostics::report_match_count,
+    memory::{Memory, MemoryOptions},
+};
+
+#[derive(Debug, Default)]
+pub struct ResolveOptions {
+    /// resolve from every signature match instead of only the first
+    pub all: bool,
+    pub symbol_relative: bool,
+    /// report the start of the function symbol containing each resolved address
+    pub rebase_to_symbol: bool,
+    /// keep `all` matches in the order they were found instead of by resolved address
+    pub unsorted: bool,
+    pub unique_only: bool,
+}
+
+pub fn resolve(
+    pid: i32,
+    address: AddressLocator,
+    resolve_options: &ResolveOptions,
+    options: MemoryOptions,
+) -> Result<(), CommandError> {
+    let memory = Memory::with_options(pid, options).map_err(CommandError::Open)?;
+    let rebase = |address: usize| {
+        if resolve_options.rebase_to_symbol {
+            AddressLocator::rebase_to_symbol(&memory, address)
+        } else {
+            Ok(address)
+        }
+    };
+
+    if !resolve_options.all {
+        let address = address
+            .resolve(&memory)
+            .and_then(rebase)
+            .map_err(CommandError::Resolve)?;
+        println!(
+            "{}",
+            format_address(&memory, address, resolve_options.symbol_relative)?
+        );
+        return Ok(());
+    }
+
+    let resolved: Vec<_> = address
+        .resolve_all(&memory)
+        .map_err(CommandError::Resolve)?
+        .into_iter()
+        .map(|(match_address, result)| (match_address, result.and_then(rebase)))
+        .collect();
+    let match_count = resolved.len();
+    let ordered = order_resolved(
+        resolved,
+        !resolve_options.unsorted,
+        resolve_options.unique_only,
+    );
+    for (match_address, result) in ordered {
+        match result {
+            Ok(address) => println!(
+                "0x{match_address:X} -> {}",
+                format_address(&memory, address, resolve_options.symbol_relative)?
+            ),
+            Err(err) => eprintln!("0x{match_address:X} -> {err}"),
+        }
+    }
+    warn_if_match_limit_reached(&memory, match_count);
+    report_match_count(match_count);
+
+    Ok(())
+}
+
+/// sorts matches by the address they resolve to and drops matches that converge on an address
+/// already seen, keeping the first. failed matches are kept and sort last
+pub fn order_resolved(
+    mut resolved: Vec<ResolvedMatch>,
+    sorted: bool,
+    unique_only: bool,
+) -> Vec<ResolvedMatch> {
+    if sorted {
+        resolved.sort_by_key(|(_, result)| *result.as_ref().unwrap_or(&usize::MAX));
+    }
+    if unique_only {
+        let mut seen = HashSet::new();
+        resolved.retain(|(_, result)| {
+            result
+                .as_ref()
+                .map_or(true, |address| seen.insert(*address))
+        });
+    }
+
+    resolved
+}
+
+fn format_address(
+    memory: &Memory,
+    address: usize,
+    symbol_relative: bool,
+) -> Result<String, CommandError> {
+    if !symbol_relative {
+        return Ok(format!("0x{address:X}"));
+    }
+
+    let symbol = memory
+        .symbol_for_address(address)
+        .map_err(CommandError::Resolve)?;
+    Ok(match symbol {
+        Some(symbol) => format!("0x{address:X} ({})", symbol.relative(address)),
+        None => format!("0x{address:X} (no symbol)"),
+    })
+}
@@ -0,0 +1,633 @@
+use std::{
+    fs::{DirBuilder, OpenOptions},
+    io::Write as _,
+    os::unix::fs::{DirBuilderExt as _, MetadataExt as _, OpenOptionsExt as _},
+    path::{Path, PathBuf},
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    address::AddressLocator,
+    commands::{CommandError, warn_if_match_limit_reached},
+    data_type::DataType,
+    diagnostics::{Progress, report_match_count, warning},
+    memory::{MatrixOrder, Memory, MemoryOptions, MemoryRegion},
+    output,
+    process::start_time,
+    value::Value,
+};
+
+const WINDOW_SIZE: usize = 1024 * 1024;
+/// the scan session used when no `--name` is given
+pub const DEFAULT_SCAN_NAME: &str = "default";
+/// floats match when they differ by at most this fraction of the target, or of 1 for small ones
+const FLOAT_TOLERANCE: f64 = 1e-4;
+
+#[derive(Debug, Clone, Default)]
+pub struct ScanOptions {
+    /// only scan regions with this pathname, or a library file name or glob
+    pub region_filter: Option<String>,
+    pub progress_bar: bool,
+    /// only scan the pages written to until the user presses enter
+    pub dirty_only: bool,
+    /// print each match relative to its module or region
+    pub context: bool,
+    /// stride and count of an array the value repeats in, only array bases are kept
+    pub array: Option<(usize, usize)>,
+    /// the session the matches are saved to, scans with different names don't replace each
+    /// other's matches
+    pub name: String,
+}
+
+/// prints every address holding `value`, together with the module or region it lies in, and
+/// saves the matches so `next_scan` can narrow them down
+pub fn scan(
+    pid: i32,
+    data_type: DataType,
+    value: &str,
+    scan_options: &ScanOptions,
+    options: MemoryOptions,
+) -> Result<(), CommandError> {
+    let region_filter = scan_options.region_filter.as_deref();
+    let target = data_type
+        .parse_value(value)
+        .map_err(CommandError::InvalidArguments)?;
+    let Some(size) = data_type.fixed_size() else {
+        return Err(CommandError::InvalidArguments(format!(
+            "Scanning for {data_type} values is not supported, their size varies"
+        )));
+    };
+
+    let memory = Memory::with_options(pid, options).map_err(CommandError::Open)?;
+    let ranges = if scan_options.dirty_only {
+        dirty_ranges(&memory, region_filter)
+    } else {
+        scan_ranges(&memory, region_filter)
+    };
+    let matches = if scan_options.progress_bar {
+        let mut progress = Progress::new(total_bytes(&ranges));
+        let matches = scan_value_in(&memory, &data_type, &target, &ranges, |bytes| {
+            progress.advance(bytes)
+        });
+        progress.finish();
+        matches
+    } else {
+        scan_value_in(&memory, &data_type, &target, &ranges, |_| {})
+    };
+    let matches = match scan_options.array {
+        Some((stride, count)) => array_bases(&memory, &data_type, &target, &matches, stride, count),
+        None => matches,
+    };
+
+    for &address in &matches {
+        print_scan_match(&memory, address, scan_options.context);
+    }
+    warn_if_match_limit_reached(&memory, matches.len());
+    report_match_count(matches.len());
+
+    let session = ScanSession {
+        pid,
+        start_time: start_time(pid)
+            .map_err(|err| CommandError::ReadFile(format!("/proc/{pid}/stat"), err))?,
+        matches: matches
+            .into_iter()
+            .filter_map(|address| Some((address, memory.read_bytes(address, size).ok()?)))
+            .collect(),
+        data_type,
+    };
+    session.save(&scan_options.name)
+}
+
+/// the address with the module or region it lies in, unless `context` is off
+fn print_scan_match(memory: &Memory, address: usize, context: bool) {
+    let locator = context
+        .then(|| AddressLocator::relative_to_region(memory, address))
+        .flatten();
+
+    if output::json() {
+        let mut record = serde_json::json!({ "address": format!("0x{address:X}") });
+        if let Some(locator) = &locator {
+            record["locator"] = locator.to_string().into();
+        }
+        output::print_json(&record);
+        return;
+    }
+
+    match locator {
+        Some(locator) => println!("0x{address:X} {locator}"),
+        None => println!("0x{address:X}"),
+    }
+}
+
+/// re-reads the matches of the last scan of `pid` with this name and keeps those the predicate
+/// holds for
+pub fn next_scan(
+    pid: i32,
+    predicate: &str,
+    name: &str,
+    options: MemoryOptions,
+) -> Result<(), CommandError> {
+    let mut session = ScanSession::load(pid, name)?;
+    let predicate = ScanPredicate::parse(predicate, &session.data_type)
+        .map_err(CommandError::InvalidArguments)?;
+
+    let memory = Memory::with_options(pid, options).map_err(CommandError::Open)?;
+    let matrix_order = memory.options().matrix_order;
+    session.refine(&predicate, matrix_order, |address, size| {
+        memory.read_bytes(address, size).ok()
+    });
+
+    for (address, bytes) in &session.matches {
+        let value = session.data_type.decode(bytes, matrix_order);
+        println!("0x{address:X} = {value}");
+    }
+    report_match_count(session.matches.len());
+
+    session.save(name)
+}
+
+/// prints the saved scans, of every process or only of `pid`, with how many matches are left.
+/// sessions of processes that are gone are listed as stale
+pub fn scans_list(pid: Option<i32>) -> Result<(), CommandError> {
+    for (pid, name) in saved_sessions(pid)? {
+        match ScanSession::load(pid, &name) {
+            Ok(session) => println!(
+                "{pid} {name}: {} {} matches",
+                session.matches.len(),
+                session.data_type
+            ),
+            Err(err) => println!("{pid} {name}: stale, {err}"),
+        }
+    }
+    Ok(())
+}
+
+/// removes the scans with this name, of every process or only of `pid`
+pub fn scans_clear(name: &str, pid: Option<i32>) -> Result<(), CommandError> {
+    let sessions: Vec<_> = saved_sessions(pid)?
+        .into_iter()
+        .filter(|(_, session_name)| session_name == name)
+        .collect();
+    if sessions.is_empty() {
+        return Err(CommandError::NoScanSession(name.to_string(), pid));
+    }
+
+    for (pid, name) in sessions {
+        ScanSession::remove(pid, &name)?;
+        println!("Cleared {pid} {name}");
+    }
+    Ok(())
+}
+
+/// pid and name of every saved scan, of every process or only of `pid`, ordered by both
+pub fn saved_sessions(pid: Option<i32>) -> Result<Vec<(i32, String)>, CommandError> {
+    let dir = session_dir()?;
+    let file_names = |dir: &Path| -> Result<Vec<String>, CommandError> {
+        let entries = match std::fs::read_dir(dir) {
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+            entries => entries
+                .and_then(|entries| entries.collect::<Result<Vec<_>, _>>())
+                .map_err(|err| CommandError::ReadFile(dir.display().to_string(), err))?,
+        };
+        Ok(entries
+            .into_iter()
+            .filter_map(|entry| entry.file_name().into_string().ok())
+            .collect())
+    };
+
+    let pids = match pid {
+        Some(pid) => vec![pid],
+        None => file_names(&dir)?
+            .iter()
+            .filter_map(|name| name.parse().ok())
+            .collect(),
+    };
+    let mut sessions = Vec::new();
+    for pid in pids {
+        for file_name in file_names(&dir.join(pid.to_string()))? {
+            if let Some(name) = file_name.strip_suffix(".scan") {
+                sessions.push((pid, name.to_string()));
+            }
+        }
+    }
+    sessions.sort();
+    Ok(sessions)
+}
+
+/// addresses in all scannable regions, or only those matching `region_filter`, where
+/// `data_type` reads as `target`. every byte offset is tried, not only aligned ones
+pub fn scan_value(
+    memory: &Memory,
+    data_type: &DataType,
+    target: &Value,
+    region_filter: Option<&str>,
+) -> Vec<usize> {
+    let ranges = scan_ranges(memory, region_filter);
+    scan_value_in(memory, data_type, target, &ranges, |_| {})
+}
+
+/// part of a region to scan, values starting in `start..end` are matched
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ScanRange {
+    pub start: usize,
+    pub end: usize,
+    /// reads may go on up to here, the end of the region, so a value starting just before
+    /// `end` is still found
+    pub limit: usize,
+}
+
+/// every scannable region, or only those matching `region_filter`, as a whole
+pub fn scan_ranges(memory: &Memory, region_filter: Option<&str>) -> Vec<ScanRange> {
+    memory
+        .memory_regions()
+        .iter()
+        .filter(|region| is_scannable(region, region_filter))
+        .map(|region| ScanRange {
+            start: region.start,
+            end: region.end,
+            limit: region.end,
+        })
+        .collect()
+}
+
+/// the pages of the scannable regions the process writes to between now and the user pressing
+/// enter, from the kernel's soft-dirty bits. everything is scanned when they aren't available
+fn dirty_ranges(memory: &Memory, region_filter: Option<&str>) -> Vec<ScanRange> {
+    let ranges = scan_ranges(memory, region_filter);
+    if let Err(err) = memory.clear_soft_dirty() {
+        warning!("Unable to track changed pages ({err}), scanning all memory");
+        return ranges;
+    }
+
+    eprintln!("Tracking changed pages, press enter once the value changed");
+    let _ = std::io::stdin().read_line(&mut String::new());
+
+    let mut dirty = Vec::new();
+    for range in ranges.iter() {
+        match memory.soft_dirty_ranges(range.start, range.end) {
+            Ok(pages) => dirty.extend(pages.into_iter().map(|(start, end)| ScanRange {
+                start,
+                end,
+                limit: range.limit,
+            })),
+            Err(err) => {
+                warning!("Unable to read changed pages ({err}), scanning all memory");
+                return ranges;
+            }
+        }
+    }
+    dirty
+}
+
+/// bytes a scan of the ranges goes through
+pub fn total_bytes(ranges: &[ScanRange]) -> u64 {
+    ranges
+        .iter()
+        .map(|range| (range.end - range.start) as u64)
+        .sum()
+}
+
+/// like `scan_value` over the given ranges, calling `progress` with the number of bytes each
+/// step got through
+pub fn scan_value_in(
+    memory: &Memory,
+    data_type: &DataType,
+    target: &Value,
+    ranges: &[ScanRange],
+    mut progress: impl FnMut(u64),
+) -> Vec<usize> {
+    let Some(size) = data_type.fixed_size() else {
+        return Vec::new();
+    };
+    let max_matches = memory.options().max_matches.unwrap_or(usize::MAX);
+    let matcher = Matcher::new(data_type, target, memory.options().matrix_order);
+    let page_size = unsafe { libc::sysconf(libc::_SC_PAGESIZE) } as usize;
+
+    let mut matches = Vec::new();
+    for range in ranges {
+        let mut window_start = range.start;
+        while window_start < range.end && matches.len() < max_matches {
+            let window_len = (WINDOW_SIZE + size - 1).min(range.limit - window_start);
+            let data = memory
+                .read_bytes_partial(window_start, window_len)
+                .unwrap_or_default();
+            matches.extend(
+                data.windows(size)
+                    .enumerate()
+                    .take(range.end - window_start)
+                    .filter(|(_, bytes)| matcher.matches(bytes))
+                    .map(|(offset, _)| window_start + offset)
+                    .take(max_matches - matches.len()),
+            );
+
+            // merged regions can contain unreadable pages, like guard pages, the read stops at
+            // the first one and the scan goes on after it
+            let step = if data.len() < window_len {
+                data.len() + page_size
+            } else {
+                WINDOW_SIZE
+            };
+            progress(step.min(range.end - window_start) as u64);
+            window_start += step;
+        }
+    }
+
+    matches
+}
+
+/// the matches that start a run of `count` elements holding the value, `stride` bytes apart,
+/// like a field of an array of structs. later elements of the same run are dropped, so each
+/// array is reported once, by its base
+pub fn array_bases(
+    memory: &Memory,
+    data_type: &DataType,
+    target: &Value,
+    matches: &[usize],
+    stride: usize,
+    count: usize,
+) -> Vec<usize> {
+    let Some(size) = data_type.fixed_size() else {
+        return Vec::new();
+    };
+    let matcher = Matcher::new(data_type, target, memory.options().matrix_order);
+    let element_matches = |address: usize, index: usize| {
+        index
+            .checked_mul(stride)
+            .and_then(|offset| address.checked_add(offset))
+            .and_then(|element| memory.read_bytes(element, size).ok())
+            .is_some_and(|bytes| matcher.matches(&bytes))
+    };
+
+    matches
+        .iter()
+        .copied()
+        .filter(|&address| {
+            let continues_run = address
+                .checked_sub(stride)
+                .is_some_and(|previous| element_matches(previous, 0));
+            !continues_run && (1..count).all(|index| element_matches(address, index))
+        })
+        .collect()
+}
+
+/// kernel-provided regions and device mappings are never scanned, reading them can have
+/// side effects. neither are guard pages and other unreadable reservations
+fn is_scannable(region: &MemoryRegion, region_filter: Option<&str>) -> bool {
+    if !region.permissions.read
+        || region.is_kernel_provided()
+        || region.pathname.starts_with("/dev/")
+    {
+        return false;
+    }
+
+    region_filter.is_none_or(|filter| region.pathname == filter || region.matches_module(filter))
+}
+
+fn is_float(data_type: &DataType) -> bool {
+    matches!(
+        data_type,
+        DataType::F16 | DataType::F32 | DataType::F64 | DataType::FixedPoint { .. }
+    )
+}
+
+fn approx_eq(value: f64, target: f64) -> bool {
+    (value - target).abs() <= FLOAT_TOLERANCE * target.abs().max(1.0)
+}
+
+/// floats are compared with a tolerance, everything else by its encoded bytes
+enum Matcher<'a> {
+    Bytes(Vec<u8>),
+    Float {
+        data_type: &'a DataType,
+        matrix_order: MatrixOrder,
+        target: f64,
+    },
+}
+
+impl<'a> Matcher<'a> {
+    fn new(data_type: &'a DataType, target: &Value, matrix_order: MatrixOrder) -> Self {
+        match target.as_f64() {
+            Some(target) if is_float(data_type) => Self::Float {
+                data_type,
+                matrix_order,
+                target,
+            },
+            _ => Self::Bytes(data_type.encode(target, matrix_order)),
+        }
+    }
+
+    fn matches(&self, bytes: &[u8]) -> bool {
+        match self {
+            Self::Bytes(target) => bytes == target.as_slice(),
+            Self::Float {
+                data_type,
+                matrix_order,
+                target,
+            } => data_type
+                .decode(bytes, *matrix_order)
+                .as_f64()
+                .is_some_and(|value| approx_eq(value, *target)),
+        }
+    }
+}
+
+/// how `next_scan` narrows down the matches, comparing each value to the one the last scan saw
+#[derive(Debug, PartialEq)]
+pub enum ScanPredicate {
+    Increased,
+    Decreased,
+    Unchanged,
+    Changed,
+    Equals(Value),
+}
+
+impl ScanPredicate {
+    /// `increased`, `decreased`, `unchanged`, `changed`, or `=<value>` in the format of `data_type`
+    pub fn parse(s: &str, data_type: &DataType) -> Result<Self, String> {
+        let predicate = match s {
+            "increased" => Self::Increased,
+            "decreased" => Self::Decreased,
+            "unchanged" => Self::Unchanged,
+            "changed" => Self::Changed,
+            _ => match s.strip_prefix('=') {
+                Some(value) => Self::Equals(data_type.parse_value(value)?),
+                None => {
+                    return Err(format!(
+                        "Unknown predicate '{s}', expected increased, decreased, unchanged, \
+                         changed or =<value>"
+                    ));
+                }
+            },
+        };
+
+        Ok(predicate)
+    }
+
+    /// increased and decreased only hold for numbers, floats are equal within a tolerance
+    pub fn holds(&self, data_type: &DataType, previous: &Value, current: &Value) -> bool {
+        match self {
+            Self::Increased => current > previous,
+            Self::Decreased => current < previous,
+            Self::Unchanged => current == previous,
+            Self::Changed => current != previous,
+            Self::Equals(target) => match (current.as_f64(), target.as_f64()) {
+                (Some(current), Some(target)) if is_float(data_type) => approx_eq(current, target),
+                _ => current == target,
+            },
+        }
+    }
+}
+
+/// the matches of a named scan of a process with the bytes they held, kept in a private
+/// directory between `scan` and `next_scan`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ScanSession {
+    pub pid: i32,
+    /// start time of the scanned process, a session never carries over to a reused pid
+    pub start_time: u64,
+    pub data_type: DataType,
+    pub matches: Vec<(usize, Vec<u8>)>,
+}
+
+impl ScanSession {
+    /// `<session dir>/<pid>/<name>.scan`
+    pub fn path(pid: i32, name: &str) -> Result<PathBuf, CommandError> {
+        Ok(session_dir()?
+            .join(pid.to_string())
+            .join(format!("{name}.scan")))
+    }
+
+    pub fn load(pid: i32, name: &str) -> Result<Self, CommandError> {
+        let path = Self::path(pid, name)?;
+        let contents = match OpenOptions::new()
+            .read(true)
+            .custom_flags(libc::O_NOFOLLOW)
+            .open(&path)
+            .and_then(std::io::read_to_string)
+        {
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => {
+                return Err(CommandError::NoScanSession(name.to_string(), Some(pid)));
+            }
+            result => {
+                result.map_err(|err| CommandError::ReadFile(path.display().to_string(), err))?
+            }
+        };
+
+        let session = Self::from_json(&contents).map_err(|reason| {
+            CommandError::InvalidScanSession(path.display().to_string(), reason)
+        })?;
+        if session.pid != pid || start_time(pid).ok() != Some(session.start_time) {
+            return Err(CommandError::InvalidScanSession(
+                path.display().to_string(),
+                format!("it was saved for an earlier process with pid {pid}"),
+            ));
+        }
+        Ok(session)
+    }
+
+    pub fn save(&self, name: &str) -> Result<(), CommandError> {
+        let path = Self::path(self.pid, name)?;
+        // the session dir is private, so the process directory in it needs no further checks
+        if let Some(dir) = path.parent() {
+            match DirBuilder::new().mode(0o700).create(dir) {
+                Err(err) if err.kind() != std::io::ErrorKind::AlreadyExists => {
+                    return Err(CommandError::WriteFile(dir.display().to_string(), err));
+                }
+                _ => {}
+            }
+        }
+        OpenOptions::new()
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .mode(0o600)
+            .custom_flags(libc::O_NOFOLLOW)
+            .open(&path)
+            .and_then(|mut file| writeln!(file, "{}", self.to_json()))
+            .map_err(|err| CommandError::WriteFile(path.display().to_string(), err))
+    }
+
+    /// deletes the session file, and the process directory once it holds no more sessions
+    pub fn remove(pid: i32, name: &str) -> Result<(), CommandError> {
+        let path = Self::path(pid, name)?;
+        std::fs::remove_file(&path)
+            .map_err(|err| CommandError::WriteFile(path.display().to_string(), err))?;
+        if let Some(dir) = path.parent() {
+            // fails while other sessions are left, which is fine
+            let _ = std::fs::remove_dir(dir);
+        }
+        Ok(())
+    }
+
+    /// re-reads every match, dropping those that can't be read anymore or fail the predicate.
+    /// survivors keep their new bytes, so the next refinement compares against this one
+    pub fn refine(
+        &mut self,
+        predicate: &ScanPredicate,
+        matrix_order: MatrixOrder,
+        read: impl Fn(usize, usize) -> Option<Vec<u8>>,
+    ) {
+        let data_type = &self.data_type;
+        self.matches.retain_mut(|(address, bytes)| {
+            let Some(current) = read(*address, bytes.len()) else {
+                return false;
+            };
+            let holds = predicate.holds(
+                data_type,
+                &data_type.decode(bytes, matrix_order),
+                &data_type.decode(&current, matrix_order),
+            );
+            *bytes = current;
+            holds
+        });
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(self).expect("scan sessions always serialize")
+    }
+
+    pub fn from_json(contents: &str) -> Result<Self, String> {
+        let session: Self = serde_json::from_str(contents).map_err(|err| err.to_string())?;
+        let size = session
+            .data_type
+            .fixed_size()
+            .ok_or_else(|| format!("{} has no fixed size", session.data_type))?;
+        if let Some((address, _)) = session
+            .matches
+            .iter()
+            .find(|(_, bytes)| bytes.len() != size)
+        {
+            return Err(format!("invalid bytes at 0x{address:X}"));
+        }
+        Ok(session)
+    }
+}
+
+/// where scan sessions are kept: `$XDG_RUNTIME_DIR/memutil`, or a directory in the temp dir
+/// named after the user. it has to be a real directory only the user can access, so other
+/// users can neither read the matches nor plant files or symlinks in it
+pub fn session_dir() -> Result<PathBuf, CommandError> {
+    let uid = unsafe { libc::geteuid() };
+    let dir = match std::env::var_os("XDG_RUNTIME_DIR") {
+        Some(runtime_dir) if !runtime_dir.is_empty() => PathBuf::from(runtime_dir).join("memutil"),
+        _ => std::env::temp_dir().join(format!("memutil-{uid}")),
+    };
+    let display = dir.display().to_string();
+
+    match DirBuilder::new().mode(0o700).create(&dir) {
+        Err(err) if err.kind() != std::io::ErrorKind::AlreadyExists => {
+            return Err(CommandError::WriteFile(display, err));
+        }
+        _ => {}
+    }
+    let metadata = std::fs::symlink_metadata(&dir)
+        .map_err(|err| CommandError::ReadFile(display.clone(), err))?;
+    if !metadata.is_dir() || metadata.uid() != uid || metadata.mode() & 0o077 != 0 {
+        return Err(CommandError::InvalidScanSession(
+            display,
+            "not a directory private to the current user".to_string(),
+        ));
+    }
+    Ok(dir)
+}
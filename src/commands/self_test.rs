@@ -0,0 +1,165 @@
+use thiserror::Error;
+
+use crate::{
+    address::IdaSignature,
+    commands::CommandError,
+    memory::{Memory, MemoryError},
+};
+
+/// embedded in the binary, so the signature scan has something known to find
+static SELF_TEST_MARKER: [u8; 16] = [
+    0x6D, 0x65, 0x6D, 0x75, 0x74, 0x69, 0x6C, 0x2D, 0x73, 0x65, 0x6C, 0x66, 0xC7, 0x1E, 0x5A, 0x93,
+];
+
+/// why a check failed: the capability errored, or it worked but gave the wrong result
+#[derive(Debug, Error)]
+pub enum SelfTestFailure {
+    #[error("{0}")]
+    Memory(#[from] MemoryError),
+    #[error("expected {expected}, found {found}")]
+    SelfTestMismatch { expected: String, found: String },
+}
+
+impl SelfTestFailure {
+    fn mismatch(expected: impl ToString, found: impl ToString) -> Self {
+        Self::SelfTestMismatch {
+            expected: expected.to_string(),
+            found: found.to_string(),
+        }
+    }
+}
+
+type Check = fn() -> Result<(), SelfTestFailure>;
+
+/// runs each capability memutil relies on against its own process and reports which work here,
+/// so permission or sandbox problems show up before a real target is involved
+pub fn self_test() -> Result<(), CommandError> {
+    let checks: [(&str, Check); 6] = [
+        ("process_vm_readv", check_read),
+        ("process_vm_writev", check_write),
+        ("signature scan", check_scan),
+        ("function symbols", check_symbols),
+        ("ptrace stop", check_ptrace),
+        ("pagemap", check_pagemap),
+    ];
+
+    let name_width = checks
+        .iter()
+        .map(|(name, _)| name.len())
+        .max()
+        .unwrap_or_default();
+    let mut failed = 0;
+    for (name, check) in checks {
+        match check() {
+            Ok(()) => println!("{name:<name_width$} pass"),
+            Err(err) => {
+                println!("{name:<name_width$} FAIL ({err})");
+                failed += 1;
+            }
+        }
+    }
+
+    match failed {
+        0 => {
+            println!("All {} capabilities are available", checks.len());
+            Ok(())
+        }
+        _ => Err(CommandError::SelfTestFailures(failed, checks.len())),
+    }
+}
+
+fn own_memory() -> Result<Memory, MemoryError> {
+    Memory::new(std::process::id().cast_signed())
+}
+
+fn check_read() -> Result<(), SelfTestFailure> {
+    let value: u64 = 0x6D65_6D75_7469_6C21;
+    let read: u64 = own_memory()?.read(&raw const value as usize)?;
+    if read != value {
+        return Err(SelfTestFailure::mismatch(
+            format!("0x{value:X}"),
+            format!("0x{read:X}"),
+        ));
+    }
+    Ok(())
+}
+
+fn check_write() -> Result<(), SelfTestFailure> {
+    const VALUE: u64 = 0x6D65_6D75_7469_6C21;
+    let mut value = 0u64;
+    own_memory()?.write(&raw mut value as usize, VALUE)?;
+    // written behind the compiler's back, so it has to be read without assuming it's still 0
+    let written = unsafe { std::ptr::read_volatile(&raw const value) };
+    if written != VALUE {
+        return Err(SelfTestFailure::mismatch(
+            format!("0x{VALUE:X}"),
+            format!("0x{written:X}"),
+        ));
+    }
+    Ok(())
+}
+
+fn check_scan() -> Result<(), SelfTestFailure> {
+    let marker = std::hint::black_box(&SELF_TEST_MARKER);
+    let signature = IdaSignature::new(marker.iter().copied().map(Some).collect(), None);
+    let matches = own_memory()?.scan_signature_all(&signature)?;
+    let marker = marker.as_ptr() as usize;
+    if matches.is_empty() {
+        return Err(MemoryError::SignatureNotFound(signature).into());
+    }
+    if !matches.contains(&marker) {
+        return Err(SelfTestFailure::mismatch(
+            format!("a match at 0x{marker:X}"),
+            format!("{} matches elsewhere", matches.len()),
+        ));
+    }
+    Ok(())
+}
+
+fn check_symbols() -> Result<(), SelfTestFailure> {
+    let getpid = libc::getpid as *const () as usize;
+    let address = own_memory()?.function_address("getpid")?;
+    if address != getpid {
+        return Err(SelfTestFailure::mismatch(
+            format!("getpid at 0x{getpid:X}"),
+            format!("0x{address:X}"),
+        ));
+    }
+    Ok(())
+}
+
+/// a process can't ptrace itself, so a forked child that only waits for signals is stopped
+fn check_ptrace() -> Result<(), SelfTestFailure> {
+    let child = unsafe { libc::fork() };
+    if child == -1 {
+        return Err(MemoryError::Io(std::io::Error::last_os_error()).into());
+    }
+    if child == 0 {
+        loop {
+            unsafe { libc::pause() };
+        }
+    }
+
+    let result = Memory::new(child).and_then(|mut memory| {
+        memory.attach()?;
+        memory.detach()
+    });
+
+    unsafe {
+        libc::kill(child, libc::SIGKILL);
+        libc::waitpid(child, std::ptr::null_mut(), 0);
+    }
+    Ok(result?)
+}
+
+/// without the capability the kernel hides the page frames, but a zeroed pagemap reads fine
+fn check_pagemap() -> Result<(), SelfTestFailure> {
+    let resident: usize = own_memory()?.resident_sizes()?.iter().sum();
+    if resident == 0 {
+        return Err(SelfTestFailure::mismatch(
+            "resident pages",
+            "none marked present",
+        ));
+    }
+    Ok(())
+}
@@ -1,33 +1,25 @@
-use crate::memory::Memory;
+use crate::{
+    commands::CommandError,
+    memory::{Memory, is_glob},
+};
 
-pub fn snap(pid: i32, lib: String) {
-    let memory = match Memory::new(pid) {
-        Ok(memory) => memory,
-        Err(err) => {
-            eprintln!("Unable to open process memory: {err}");
-            return;
-        }
-    };
+pub fn snap(pid: i32, lib: String) -> Result<(), CommandError> {
+    let memory = Memory::new(pid).map_err(CommandError::Open)?;
 
-    let Some(region) = memory.memory_regions().iter().find(|region| {
-        let Some((_, filename)) = region.pathname.rsplit_once('/') else {
-            return false;
-        };
-        filename.contains(&lib)
-    }) else {
-        eprintln!("Failed to find library '{lib}'");
-        return;
+    let images = memory.mapped_images();
+    let Some(region) = images.iter().find(|region| region.matches_module(&lib)) else {
+        return Err(CommandError::LibraryNotFound(lib));
     };
 
-    let bytes = match memory.read_bytes(region.start, region.end - region.start) {
-        Ok(bytes) => bytes,
-        Err(err) => {
-            eprintln!("Unable to read memory: {err}");
-            return;
-        }
+    let bytes = memory
+        .read_bytes(region.start, region.end - region.start)
+        .map_err(CommandError::Read)?;
+
+    // a glob makes a poor file name, so use the name of the matched library instead
+    let file_name = match region.file_name() {
+        Some(file_name) if is_glob(&lib) => file_name.to_string(),
+        _ => lib,
     };
 
-    if let Err(err) = std::fs::write(&lib, bytes) {
-        eprintln!("Unable to write to file '{lib}': {err}");
-    }
+    std::fs::write(&file_name, bytes).map_err(|err| CommandError::WriteFile(file_name, err))
 }
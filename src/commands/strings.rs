@@ -0,0 +1,121 @@
+use std::ops::Range;
+
+use crate::{
+    commands::CommandError,
+    memory::{Memory, MemoryError},
+};
+
+const CHUNK_SIZE: usize = 1024 * 1024;
+
+/// prints runs of at least `min_len` printable characters, like the `strings` utility.
+/// `target` is either a `start-end` address range or a region pathname, file name or glob
+pub fn strings(
+    pid: i32,
+    target: String,
+    min_len: usize,
+    unicode: bool,
+) -> Result<(), CommandError> {
+    let memory = Memory::new(pid).map_err(CommandError::Open)?;
+
+    let ranges = match parse_range(&target) {
+        Some(range) => vec![range],
+        None => memory
+            .memory_regions()
+            .iter()
+            .filter(|region| region.pathname == target || region.matches_module(&target))
+            .map(|region| region.start..region.end)
+            .collect(),
+    };
+    if ranges.is_empty() {
+        return Err(CommandError::Resolve(MemoryError::NamedRegionNotFound(
+            target,
+        )));
+    }
+
+    for range in ranges {
+        find_strings(&memory, range, min_len, unicode, |address, text| {
+            println!("0x{address:X} {text}");
+        });
+    }
+
+    Ok(())
+}
+
+/// calls `found` with the address of every run of at least `min_len` printable characters in
+/// `range`, utf-16 ones when `unicode` is set
+pub fn find_strings(
+    memory: &Memory,
+    range: Range<usize>,
+    min_len: usize,
+    unicode: bool,
+    mut found: impl FnMut(usize, &str),
+) {
+    let char_width = if unicode { 2 } else { 1 };
+    let mut run = Run::new(min_len);
+    for chunk_start in range.clone().step_by(CHUNK_SIZE) {
+        let chunk_len = CHUNK_SIZE.min(range.end - chunk_start);
+        // unreadable chunks, like guard pages, end the current run
+        let Ok(chunk) = memory.read_bytes(chunk_start, chunk_len) else {
+            run.finish(&mut found);
+            continue;
+        };
+
+        for (i, bytes) in chunk.chunks_exact(char_width).enumerate() {
+            match printable(bytes) {
+                Some(c) => run.push(chunk_start + i * char_width, c),
+                None => run.finish(&mut found),
+            }
+        }
+    }
+    run.finish(&mut found);
+}
+
+/// `7F0000000000-7F0000001000`, with or without `0x` prefixes
+fn parse_range(s: &str) -> Option<Range<usize>> {
+    let (start, end) = s.split_once('-')?;
+    let parse = |s: &str| {
+        let s = s.trim_start_matches("0x").trim_start_matches("0X");
+        usize::from_str_radix(s, 16).ok()
+    };
+    let (start, end) = (parse(start)?, parse(end)?);
+    (start < end).then_some(start..end)
+}
+
+/// a single ascii byte, or a little-endian utf-16 code unit in the ascii range
+fn printable(bytes: &[u8]) -> Option<char> {
+    let c = match bytes {
+        [c] | [c, 0] => *c as char,
+        _ => return None,
+    };
+    (c.is_ascii_graphic() || c == ' ' || c == '\t').then_some(c)
+}
+
+struct Run {
+    start: usize,
+    text: String,
+    min_len: usize,
+}
+
+impl Run {
+    fn new(min_len: usize) -> Self {
+        Self {
+            start: 0,
+            text: String::new(),
+            min_len,
+        }
+    }
+
+    fn push(&mut self, address: usize, c: char) {
+        if self.text.is_empty() {
+            self.start = address;
+        }
+        self.text.push(c);
+    }
+
+    fn finish(&mut self, found: &mut impl FnMut(usize, &str)) {
+        if self.text.len() >= self.min_len {
+            found(self.start, &self.text);
+        }
+        self.text.clear();
+    }
+}
@@ -1,33 +1,553 @@
-use std::{thread::sleep, time::Duration};
+use std::{
+    fs::{File, OpenOptions},
+    io::{BufWriter, Write as _},
+    path::{Path, PathBuf},
+    sync::atomic::{AtomicBool, Ordering},
+    thread::sleep,
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
 
-use crate::{address::AddressLocator, data_type::DataType, memory::Memory};
+use crate::{
+    address::{AddressLocator, ResolveCache},
+    commands::{
+        CommandError,
+        hexdump::{format_hex, format_value_bytes},
+    },
+    data_type::DataType,
+    diagnostics::{self, warning},
+    memory::{Memory, MemoryOptions},
+    output::{self, Stream},
+    value::Value,
+};
 
-pub fn watch(pid: i32, address: AddressLocator, data_type: DataType, interval: Duration) {
-    let memory = match Memory::new(pid) {
-        Ok(memory) => memory,
-        Err(err) => {
-            eprintln!("Unable to open process memory: {err}");
-            return;
+static INTERRUPTED: AtomicBool = AtomicBool::new(false);
+
+/// how a watch samples and what it reports when it ends
+#[derive(Debug, Default)]
+pub struct WatchOptions {
+    pub interval: Duration,
+    pub aggregate: bool,
+    pub snapshot_on_exit: Option<PathBuf>,
+    pub refresh: bool,
+    pub jitter: Option<Duration>,
+    pub show_bytes: bool,
+    /// only print samples that changed by more than this epsilon since the previous one
+    pub on_change: Option<f64>,
+    /// csv file every sample is appended to
+    pub output: Option<PathBuf>,
+    /// bounds that raise an alert once a sample crosses them, parsed as the watched type
+    pub alert_above: Option<String>,
+    pub alert_below: Option<String>,
+    /// ring the terminal bell with every alert
+    pub bell: bool,
+    /// timestamps every sample, not only `--on-change` ones, and picks the csv time column
+    pub clock: Option<Clock>,
+}
+
+/// where sample timestamps come from
+#[derive(Debug, Clone, Copy, Default, PartialEq, clap::ValueEnum)]
+pub enum Clock {
+    /// milliseconds since the unix epoch, or the local time of day in text output
+    #[default]
+    Wall,
+    /// seconds since the watch started with microsecond resolution, immune to clock changes
+    Monotonic,
+}
+
+/// when a sample was read, by the clock the watch was asked for
+#[derive(Debug, Clone, Copy)]
+enum Timestamp {
+    Wall(SystemTime),
+    Monotonic(Duration),
+}
+
+impl Timestamp {
+    fn now(clock: Clock, start: Instant) -> Self {
+        match clock {
+            Clock::Wall => Self::Wall(SystemTime::now()),
+            Clock::Monotonic => Self::Monotonic(start.elapsed()),
         }
-    };
+    }
+
+    /// the name of the json field and csv column
+    fn field(clock: Clock) -> &'static str {
+        match clock {
+            Clock::Wall => "timestamp_ms",
+            Clock::Monotonic => "time_s",
+        }
+    }
 
-    loop {
-        let address = match address.resolve(&memory) {
-            Ok(address) => address,
-            Err(err) => {
-                eprintln!("Unable to resolve address: {err}");
-                return;
+    fn to_json(self) -> serde_json::Value {
+        match self {
+            Self::Wall(time) => unix_millis(time).into(),
+            Self::Monotonic(elapsed) => elapsed.as_secs_f64().into(),
+        }
+    }
+
+    fn to_csv(self) -> String {
+        match self {
+            Self::Wall(time) => unix_millis(time).to_string(),
+            Self::Monotonic(elapsed) => format!("{:.6}", elapsed.as_secs_f64()),
+        }
+    }
+
+    fn to_text(self) -> String {
+        match self {
+            Self::Wall(time) => format_local_time(time),
+            Self::Monotonic(elapsed) => format!("{:.6}s", elapsed.as_secs_f64()),
+        }
+    }
+}
+
+pub fn watch(
+    pid: i32,
+    address: AddressLocator,
+    data_type: DataType,
+    watch_options: WatchOptions,
+    options: MemoryOptions,
+) -> Result<(), CommandError> {
+    let memory = Memory::with_options(pid, options).map_err(CommandError::Open)?;
+
+    let mut csv_log = watch_options
+        .output
+        .as_deref()
+        .map(|path| CsvLog::open(path, watch_options.clock.unwrap_or_default()))
+        .transpose()?;
+
+    install_interrupt_handler();
+
+    let mut aggregate = watch_options.aggregate.then(Aggregate::default);
+    let mut last_sample = None;
+    let result = watch_loop(
+        &memory,
+        &address,
+        &data_type,
+        &watch_options,
+        aggregate.as_mut(),
+        csv_log.as_mut(),
+        &mut last_sample,
+    );
+
+    if let Some(aggregate) = aggregate {
+        aggregate.print_summary();
+    }
+
+    let snapshot = watch_options
+        .snapshot_on_exit
+        .map(|path| write_snapshot(&path, pid, &data_type, last_sample))
+        .transpose();
+
+    // summaries are printed either way, the exit status still tells ctrl-c apart. when the
+    // loop failed that error is the one to report, a failed snapshot only gets a warning
+    if let Err(err) = &snapshot
+        && result.is_err()
+    {
+        warning!("{err}");
+    }
+    result?;
+    snapshot?;
+    if interrupted() {
+        return Err(CommandError::Interrupted);
+    }
+    Ok(())
+}
+
+fn watch_loop(
+    memory: &Memory,
+    address: &AddressLocator,
+    data_type: &DataType,
+    watch_options: &WatchOptions,
+    mut aggregate: Option<&mut Aggregate>,
+    mut csv_log: Option<&mut CsvLog>,
+    last_sample: &mut Option<(usize, Value)>,
+) -> Result<(), CommandError> {
+    let mut alerts = Alerts::new(data_type, watch_options)?;
+    let mut cache = ResolveCache::default();
+    let mut last_read_at = None;
+    let mut rng = XorShift::from_time();
+    let clock = watch_options.clock.unwrap_or_default();
+    let start = Instant::now();
+    while !INTERRUPTED.load(Ordering::Relaxed) {
+        if watch_options.refresh {
+            cache.clear();
+            memory.clear_signature_cache();
+        }
+        let address = address
+            .resolve_cached(memory, &mut cache)
+            .map_err(CommandError::Resolve)?;
+
+        let (value, bytes) = data_type
+            .read_with_bytes(memory, address)
+            .map_err(CommandError::Read)?;
+        let read_at = Instant::now();
+
+        let previous = last_sample.as_ref().map(|(_, previous)| previous);
+        // the first sample is always printed, as the baseline later ones are compared against
+        let print = match (watch_options.on_change, previous) {
+            (Some(epsilon), Some(previous)) => value.changed_from(previous, epsilon),
+            _ => true,
+        };
+        // with a csv log, `--quiet` leaves the samples to the file
+        let print = print && !(csv_log.is_some() && diagnostics::quiet());
+        let sampled_at = Timestamp::now(clock, start);
+        let timestamp = (watch_options.on_change.is_some() || watch_options.clock.is_some())
+            .then_some(sampled_at);
+        let rate = previous
+            .and_then(|previous| value.counter_delta(previous))
+            .zip(last_read_at)
+            .map(|(delta, last_read_at)| {
+                let rate = delta as f64 / read_at.duration_since(last_read_at).as_secs_f64();
+                (delta, rate)
+            });
+        if print && output::json() {
+            let mut record = serde_json::json!({
+                "address": format!("0x{address:X}"),
+                "type": data_type.to_string(),
+                "value": value,
+            });
+            if let Some(timestamp) = timestamp {
+                record[Timestamp::field(clock)] = timestamp.to_json();
+            }
+            if watch_options.show_bytes {
+                record["bytes"] = bytes.as_deref().map(format_hex).into();
+            }
+            if let Some((delta, rate)) = rate {
+                record["delta"] = delta.into();
+                record["rate"] = rate.into();
+            }
+            output::print_json(&record);
+        } else if print {
+            let timestamp = timestamp
+                .map(|timestamp| format!("[{}] ", timestamp.to_text()))
+                .unwrap_or_default();
+            let bytes = if watch_options.show_bytes {
+                format_value_bytes(bytes.as_deref())
+            } else {
+                String::new()
+            };
+            match rate {
+                Some((delta, rate)) => {
+                    println!("{timestamp}0x{address:X} = {value}{bytes} (+{delta}, {rate:.1}/s)")
+                }
+                None => println!("{timestamp}0x{address:X} = {value}{bytes}"),
             }
+        }
+        last_read_at = Some(read_at);
+
+        for alert in alerts.check(address, &value) {
+            raise_alert(watch_options.bell, &alert);
+        }
+
+        if let Some(csv_log) = csv_log.as_deref_mut() {
+            csv_log.append(sampled_at, address, &value)?;
+        }
+
+        if let Some(aggregate) = aggregate.as_deref_mut()
+            && let Some(value) = value.as_f64()
+        {
+            aggregate.add(value);
+        }
+        *last_sample = Some((address, value));
+
+        let interval = match watch_options.jitter {
+            Some(jitter) => rng.jitter(watch_options.interval, jitter),
+            None => watch_options.interval,
         };
+        sleep_interruptible(interval);
+    }
 
-        let value = match data_type.read(&memory, address) {
-            Ok(value) => value,
-            Err(err) => {
-                eprintln!("Unable to read memory: {err}");
-                return;
-            }
+    Ok(())
+}
+
+fn unix_millis(time: SystemTime) -> u64 {
+    time.duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_millis() as u64
+}
+
+/// `HH:MM:SS.mmm` in local time
+fn format_local_time(time: SystemTime) -> String {
+    let millis = unix_millis(time);
+    let seconds = (millis / 1000) as libc::time_t;
+    let mut tm: libc::tm = unsafe { std::mem::zeroed() };
+    unsafe { libc::localtime_r(&seconds, &mut tm) };
+    format!(
+        "{:02}:{:02}:{:02}.{:03}",
+        tm.tm_hour,
+        tm.tm_min,
+        tm.tm_sec,
+        millis % 1000
+    )
+}
+
+/// a bound of `--alert-above` or `--alert-below`, `active` while samples stay past it
+struct Alert {
+    bound: Value,
+    active: bool,
+}
+
+impl Alert {
+    /// records whether samples are past the bound now, true when they just crossed it
+    fn crossed(&mut self, past_bound: bool) -> bool {
+        let crossed = past_bound && !self.active;
+        self.active = past_bound;
+        crossed
+    }
+}
+
+/// alerts fire when a sample crosses a bound, and not again until samples came back and
+/// crossed it anew
+pub(crate) struct Alerts {
+    above: Option<Alert>,
+    below: Option<Alert>,
+}
+
+impl Alerts {
+    pub(crate) fn new(
+        data_type: &DataType,
+        watch_options: &WatchOptions,
+    ) -> Result<Self, CommandError> {
+        let parse_bound = |bound: &Option<String>| {
+            bound
+                .as_deref()
+                .map(|bound| {
+                    let bound = data_type
+                        .parse_value(bound)
+                        .map_err(CommandError::InvalidArguments)?;
+                    if !bound.is_ordered() {
+                        return Err(CommandError::InvalidArguments(format!(
+                            "{data_type} values can't be compared against a bound"
+                        )));
+                    }
+                    Ok(Alert {
+                        bound,
+                        active: false,
+                    })
+                })
+                .transpose()
+        };
+
+        Ok(Self {
+            above: parse_bound(&watch_options.alert_above)?,
+            below: parse_bound(&watch_options.alert_below)?,
+        })
+    }
+
+    /// the alerts this sample raises
+    pub(crate) fn check(&mut self, address: usize, value: &Value) -> Vec<String> {
+        let mut alerts = Vec::new();
+        if let Some(alert) = &mut self.above
+            && alert.crossed(value > &alert.bound)
+        {
+            alerts.push(format!("0x{address:X} = {value} is above {}", alert.bound));
+        }
+        if let Some(alert) = &mut self.below
+            && alert.crossed(value < &alert.bound)
+        {
+            alerts.push(format!("0x{address:X} = {value} is below {}", alert.bound));
+        }
+        alerts
+    }
+}
+
+/// on stderr, so alerts stand out from the samples and stay out of piped output
+fn raise_alert(bell: bool, message: &str) {
+    let bell = if bell { "\x07" } else { "" };
+    let alert = output::paint("ALERT", output::BOLD_RED, Stream::Stderr);
+    eprintln!("{bell}{alert} {message}");
+}
+
+/// samples appended to a csv file as `timestamp_ms,address,value`, or `time_s,...` with the
+/// monotonic clock, flushed after every line so an interrupted watch keeps everything sampled
+/// so far
+struct CsvLog {
+    path: PathBuf,
+    writer: BufWriter<File>,
+}
+
+impl CsvLog {
+    fn open(path: &Path, clock: Clock) -> Result<Self, CommandError> {
+        let write_error = |err| CommandError::WriteFile(path.display().to_string(), err);
+
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .map_err(write_error)?;
+        let is_new = file.metadata().map_err(write_error)?.len() == 0;
+
+        let mut log = Self {
+            path: path.to_path_buf(),
+            writer: BufWriter::new(file),
         };
-        println!("0x{address:X} = {value}");
-        sleep(interval);
+        if is_new {
+            log.write_line(&format!("{},address,value", Timestamp::field(clock)))?;
+        }
+        Ok(log)
+    }
+
+    fn append(
+        &mut self,
+        timestamp: Timestamp,
+        address: usize,
+        value: &Value,
+    ) -> Result<(), CommandError> {
+        let line = format!("{},0x{address:X},{}", timestamp.to_csv(), csv_value(value));
+        self.write_line(&line)
+    }
+
+    fn write_line(&mut self, line: &str) -> Result<(), CommandError> {
+        writeln!(self.writer, "{line}")
+            .and_then(|_| self.writer.flush())
+            .map_err(|err| CommandError::WriteFile(self.path.display().to_string(), err))
+    }
+}
+
+/// numbers are written as they are, everything else as its text form, quoted when it
+/// contains a comma or quote
+fn csv_value(value: &Value) -> String {
+    let text = match serde_json::to_value(value) {
+        Ok(serde_json::Value::Number(number)) => return number.to_string(),
+        Ok(serde_json::Value::String(text)) => text,
+        _ => value.to_string(),
+    };
+
+    if text.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", text.replace('"', "\"\""))
+    } else {
+        text
+    }
+}
+
+/// xorshift64, random enough to spread out samples without pulling in a dependency
+pub(crate) struct XorShift(u64);
+
+impl XorShift {
+    pub(crate) fn new(seed: u64) -> Self {
+        // the state must never be zero
+        Self(seed | 1)
+    }
+
+    fn from_time() -> Self {
+        let nanos = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_nanos() as u64;
+        Self::new(nanos)
+    }
+
+    fn next(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+
+    /// `interval` moved by a random amount within `jitter` either way
+    pub(crate) fn jitter(&mut self, interval: Duration, jitter: Duration) -> Duration {
+        let jitter = jitter.as_nanos().min(u64::MAX as u128 / 2) as u64;
+        let offset = self.next() % (jitter * 2 + 1);
+        if offset >= jitter {
+            interval + Duration::from_nanos(offset - jitter)
+        } else {
+            interval.saturating_sub(Duration::from_nanos(jitter - offset))
+        }
+    }
+}
+
+/// writes the last sampled value as json, or null if nothing was sampled. the value is
+/// serialized like `--json` prints it, so numbers stay numbers
+pub(crate) fn write_snapshot(
+    path: &Path,
+    pid: i32,
+    data_type: &DataType,
+    last_sample: Option<(usize, Value)>,
+) -> Result<(), CommandError> {
+    let snapshot = match last_sample {
+        Some((address, value)) => serde_json::json!({
+            "pid": pid,
+            "address": format!("0x{address:X}"),
+            "type": data_type,
+            "value": value,
+        }),
+        None => serde_json::Value::Null,
+    };
+
+    std::fs::write(path, format!("{snapshot:#}\n"))
+        .map_err(|err| CommandError::WriteFile(path.display().to_string(), err))
+}
+
+extern "C" fn handle_interrupt(_signal: libc::c_int) {
+    INTERRUPTED.store(true, Ordering::Relaxed);
+}
+
+/// lets ctrl-c end the watch loop normally, so summaries still get printed
+pub fn install_interrupt_handler() {
+    let handler: extern "C" fn(libc::c_int) = handle_interrupt;
+    unsafe { libc::signal(libc::SIGINT, handler as libc::sighandler_t) };
+}
+
+pub fn interrupted() -> bool {
+    INTERRUPTED.load(Ordering::Relaxed)
+}
+
+pub fn sleep_interruptible(duration: Duration) {
+    const STEP: Duration = Duration::from_millis(50);
+
+    let start = Instant::now();
+    while !INTERRUPTED.load(Ordering::Relaxed) {
+        let remaining = duration.saturating_sub(start.elapsed());
+        if remaining.is_zero() {
+            break;
+        }
+        sleep(remaining.min(STEP));
+    }
+}
+
+#[derive(Debug, Default)]
+pub(crate) struct Aggregate {
+    pub(crate) min: f64,
+    pub(crate) max: f64,
+    sum: f64,
+    pub(crate) count: usize,
+}
+
+impl Aggregate {
+    pub(crate) fn add(&mut self, value: f64) {
+        if self.count == 0 {
+            self.min = value;
+            self.max = value;
+        } else {
+            self.min = self.min.min(value);
+            self.max = self.max.max(value);
+        }
+        self.sum += value;
+        self.count += 1;
+    }
+
+    pub(crate) fn mean(&self) -> f64 {
+        self.sum / self.count as f64
+    }
+
+    fn print_summary(&self) {
+        if self.count == 0 {
+            warning!("No numeric samples to aggregate");
+            return;
+        }
+
+        let mean = self.mean();
+        if output::json() {
+            output::print_json(&serde_json::json!({
+                "samples": self.count,
+                "min": self.min,
+                "max": self.max,
+                "mean": mean,
+            }));
+            return;
+        }
+        println!(
+            "samples = {}, min = {}, max = {}, mean = {mean}",
+            self.count, self.min, self.max
+        );
     }
 }
@@ -0,0 +1,124 @@
+use serde::Serialize;
+
+use crate::{
+    address::AddressLocator,
+    commands::{
+        CommandError,
+        hexdump::{format_hex, serialize_hex},
+    },
+    data_type::DataType,
+    memory::{Memory, MemoryOptions},
+    output::{self, serialize_address},
+};
+
+/// how a write is checked before it happens
+#[derive(Debug, Clone, Default)]
+pub struct WriteOptions {
+    /// allow writes into executable regions, which are refused so data writes can't clobber code
+    pub allow_exec: bool,
+    /// print what would be written instead of writing it
+    pub dry_run: bool,
+}
+
+/// the bytes a write would replace, for `--dry-run`
+#[derive(Debug, Serialize)]
+pub struct WritePlan {
+    #[serde(serialize_with = "serialize_address")]
+    pub address: usize,
+    #[serde(serialize_with = "serialize_hex")]
+    pub old: Vec<u8>,
+    #[serde(serialize_with = "serialize_hex")]
+    pub new: Vec<u8>,
+}
+
+impl WritePlan {
+    /// checks the write like a real one would be and reads the bytes currently there
+    pub fn new(
+        memory: &Memory,
+        address: usize,
+        bytes: Vec<u8>,
+        write_options: &WriteOptions,
+    ) -> Result<Self, CommandError> {
+        check_write(memory, address, bytes.len(), write_options)?;
+        let old = memory
+            .read_bytes(address, bytes.len())
+            .map_err(CommandError::Read)?;
+        Ok(Self {
+            address,
+            old,
+            new: bytes,
+        })
+    }
+
+    /// how many of the bytes the write would actually change
+    pub fn changed(&self) -> usize {
+        self.old
+            .iter()
+            .zip(&self.new)
+            .filter(|(old, new)| old != new)
+            .count()
+    }
+
+    pub fn print(&self) {
+        if output::json() {
+            output::print_json(self);
+            return;
+        }
+        println!(
+            "0x{:X}: {} of {} bytes would change",
+            self.address,
+            self.changed(),
+            self.new.len()
+        );
+        println!("  old: {}", format_hex(&self.old));
+        println!("  new: {}", format_hex(&self.new));
+    }
+}
+
+/// the value is parsed before the process is opened, so a typo never leaves a half-done write
+pub fn write(
+    pid: i32,
+    address: AddressLocator,
+    data_type: DataType,
+    value: &str,
+    write_options: &WriteOptions,
+    options: MemoryOptions,
+) -> Result<(), CommandError> {
+    let value = data_type
+        .parse_value(value)
+        .map_err(CommandError::InvalidArguments)?;
+
+    let memory = Memory::with_options(pid, options).map_err(CommandError::Open)?;
+    let address = address.resolve(&memory).map_err(CommandError::Resolve)?;
+    let bytes = data_type.encode(&value, memory.options().matrix_order);
+    if write_options.dry_run {
+        WritePlan::new(&memory, address, bytes, write_options)?.print();
+        return Ok(());
+    }
+    check_write(&memory, address, bytes.len(), write_options)?;
+    memory
+        .write_bytes(address, &bytes)
+        .map_err(CommandError::Write)?;
+
+    println!("0x{address:X} = {value}");
+    Ok(())
+}
+
+/// refuses writes into executable regions unless `--allow-exec` was given
+pub fn check_write(
+    memory: &Memory,
+    address: usize,
+    len: usize,
+    write_options: &WriteOptions,
+) -> Result<(), CommandError> {
+    if write_options.allow_exec {
+        return Ok(());
+    }
+    match memory.find_executable_region(address, len) {
+        Some(region) => Err(CommandError::ExecutableMemory(
+            address,
+            region.pathname.clone(),
+        )),
+        None => Ok(()),
+    }
+}
@@ -1,8 +1,17 @@
+use std::{fmt::Display, str::FromStr};
+
+use glam::{DVec2, DVec3, DVec4, Mat4, Vec2, Vec3, Vec4};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
 use crate::{
-    memory::{Memory, MemoryError},
+    args::parse_data_type,
+    diagnostics::warning,
+    memory::{MatrixOrder, Memory, MemoryError},
     value::Value,
 };
 
+/// upper bound for null-terminated pointer arrays, in case the terminator is missing
+
 #[derive(Debug, Clone, PartialEq)]
 pub enum DataType {
     U8,
@@ -15,53 +24,781 @@ pub enum DataType {
     I32,
     I64,
 
+    F16,
     F32,
     F64,
 
     Pointer,
     Pointer32,
     Pointer64,
+    PointerArray,
 
     Vec2,
     Vec3,
     Vec4,
     Mat4,
 
+    DVec2,
+    DVec3,
+    DVec4,
+
     Rgb,
     Rgba,
     Color32,
+
+    Flags {
+        base: Box<DataType>,
+        names: Vec<(u32, String)>,
+    },
+
+    /// integer with `frac_bits` of fraction, `int_bits + frac_bits` is 16, 32 or 64
+    FixedPoint {
+        int_bits: u32,
+        frac_bits: u32,
+        signed: bool,
+    },
+
+    /// an always increasing `u64`, `watch` also shows how much it grew per sample
+    Counter,
+
+    /// a single utf-8 encoded scalar, 1 to 4 bytes wide
+    Utf8Char,
+
+    /// nul-terminated string, read up to `max_len` bytes or `max_string_len` without one
+    CString {
+        max_len: Option<usize>,
+    },
+
+    /// utf-16le string ending in a nul code unit, read up to `max_units` code units or
+    /// `max_string_len` without one
+    WString {
+        max_units: Option<usize>,
+    },
+
+    /// string preceded by its length in bytes, stored as an unsigned integer `len_width` bytes wide
+    PascalString {
+        len_width: usize,
+    },
+
+    /// `len` elements of a fixed-size type one after another, like `u32[16]`
+    Array(Box<DataType>, usize),
 }
 
 impl DataType {
     pub fn read(&self, memory: &Memory, address: usize) -> Result<Value, MemoryError> {
+        Ok(self.read_with_bytes(memory, address)?.0)
+    }
+
+    /// reads the value together with the bytes it was decoded from. fixed-size types are read
+    /// once and decoded from those bytes, variable-length ones like strings come without bytes
+    pub fn read_with_bytes(
+        &self,
+        memory: &Memory,
+        address: usize,
+    ) -> Result<(Value, Option<Vec<u8>>), MemoryError> {
+        // addresses outside the known regions are left to the read itself to reject
+        let size = self.size();
+        if let Some(end) = memory.mapped_end(address)
+            && address.saturating_add(size) > end
+        {
+            return Err(MemoryError::OutOfBounds(address, size));
+        }
+
+        if let Some(size) = self.fixed_size() {
+            let bytes = memory.read_bytes(address, size)?;
+            let value = self.decode(&bytes, memory.options().matrix_order);
+            return Ok((value, Some(bytes)));
+        }
+
+        let value = match self {
+            DataType::PointerArray => {
+                Value::PointerArray(Self::read_pointer_array(memory, address)?)
+            }
+
+            DataType::Utf8Char => {
+                let (char, len) = Self::read_utf8_char(memory, address)?;
+                Value::Char(char, len)
+            }
+
+            DataType::CString { max_len } => {
+                Value::CString(memory.read_cstring(address, *max_len)?)
+            }
+
+            DataType::WString { max_units } => {
+                Value::WString(memory.read_wstring(address, *max_units)?)
+            }
+
+            DataType::PascalString { len_width } => {
+                Value::String(Self::read_pascal_string(memory, address, *len_width)?)
+            }
+
+            data_type => unreachable!("{data_type:?} has a fixed size"),
+        };
+
+        Ok((value, None))
+    }
+
+    /// bytes a value of this type takes up. for types that depend on the data read this is the
+    /// least a read needs, like the terminator of an empty string
+    pub fn size(&self) -> usize {
+        if let Some(size) = self.fixed_size() {
+            return size;
+        }
+
+        match self {
+            DataType::PointerArray => size_of::<usize>(),
+            DataType::Utf8Char | DataType::CString { .. } => 1,
+            DataType::WString { .. } => size_of::<u16>(),
+            DataType::PascalString { len_width } => *len_width,
+            data_type => unreachable!("{data_type:?} has a fixed size"),
+        }
+    }
+
+    /// bytes a value of this type takes up, `None` when it depends on the data read
+    pub fn fixed_size(&self) -> Option<usize> {
+        let size = match self {
+            DataType::U8 | DataType::I8 => 1,
+            DataType::U16 | DataType::I16 | DataType::F16 => 2,
+            DataType::U32 | DataType::I32 | DataType::F32 | DataType::Pointer32 => 4,
+            DataType::U64
+            | DataType::I64
+            | DataType::F64
+            | DataType::Pointer64
+            | DataType::Counter => 8,
+            DataType::Pointer => size_of::<usize>(),
+
+            DataType::Vec2 => size_of::<Vec2>(),
+            DataType::Vec3 => size_of::<Vec3>(),
+            DataType::Vec4 => size_of::<Vec4>(),
+            DataType::Mat4 => size_of::<Mat4>(),
+
+            DataType::DVec2 => size_of::<DVec2>(),
+            DataType::DVec3 => size_of::<DVec3>(),
+            DataType::DVec4 => size_of::<DVec4>(),
+
+            DataType::Rgb => 3,
+            DataType::Rgba => 4,
+            DataType::Color32 => size_of::<[f32; 4]>(),
+
+            DataType::Flags { base, .. } => return base.fixed_size(),
+            DataType::Array(element, len) => return element.fixed_size()?.checked_mul(*len),
+            DataType::FixedPoint {
+                int_bits,
+                frac_bits,
+                ..
+            } => (int_bits + frac_bits) as usize / 8,
+
+            DataType::PointerArray
+            | DataType::Utf8Char
+            | DataType::CString { .. }
+            | DataType::WString { .. }
+            | DataType::PascalString { .. } => {
+                return None;
+            }
+        };
+
+        Some(size)
+    }
+
+    /// decodes a fixed-size type from exactly `fixed_size` bytes, which need not be aligned
+    pub fn decode(&self, bytes: &[u8], matrix_order: MatrixOrder) -> Value {
+        fn pod<T: bytemuck::Pod>(bytes: &[u8]) -> T {
+            bytemuck::pod_read_unaligned(&bytes[..size_of::<T>()])
+        }
+
+        match self {
+            DataType::U8 => Value::U8(pod(bytes)),
+            DataType::U16 => Value::U16(pod(bytes)),
+            DataType::U32 => Value::U32(pod(bytes)),
+            DataType::U64 => Value::U64(pod(bytes)),
+
+            DataType::I8 => Value::I8(pod(bytes)),
+            DataType::I16 => Value::I16(pod(bytes)),
+            DataType::I32 => Value::I32(pod(bytes)),
+            DataType::I64 => Value::I64(pod(bytes)),
+
+            DataType::F16 => Value::F16(half::f16::from_bits(pod(bytes)).to_f32()),
+            DataType::F32 => Value::F32(pod(bytes)),
+            DataType::F64 => Value::F64(pod(bytes)),
+
+            DataType::Pointer => Value::Pointer(pod(bytes)),
+            DataType::Pointer32 => Value::Pointer32(pod(bytes)),
+            DataType::Pointer64 => Value::Pointer64(pod(bytes)),
+
+            DataType::Vec2 => Value::Vec2(pod(bytes)),
+            DataType::Vec3 => Value::Vec3(pod(bytes)),
+            DataType::Vec4 => Value::Vec4(pod(bytes)),
+            DataType::Mat4 => {
+                let matrix: Mat4 = pod(bytes);
+                match matrix_order {
+                    MatrixOrder::Row => Value::Mat4(matrix.transpose()),
+                    MatrixOrder::Col => Value::Mat4(matrix),
+                }
+            }
+
+            DataType::DVec2 => Value::DVec2(pod(bytes)),
+            DataType::DVec3 => Value::DVec3(pod(bytes)),
+            DataType::DVec4 => Value::DVec4(pod(bytes)),
+
+            DataType::Rgb => Value::Rgb(pod(bytes)),
+            DataType::Rgba => Value::Rgba(pod(bytes)),
+            DataType::Color32 => Value::Color32(pod(bytes)),
+
+            DataType::Flags { base, names } => {
+                let raw = match base.decode(bytes, matrix_order) {
+                    Value::U8(v) => v as u64,
+                    Value::U16(v) => v as u64,
+                    Value::U32(v) => v as u64,
+                    Value::U64(v) => v,
+                    Value::I8(v) => v as u8 as u64,
+                    Value::I16(v) => v as u16 as u64,
+                    Value::I32(v) => v as u32 as u64,
+                    Value::I64(v) => v as u64,
+                    value => unreachable!("flags base type read as non-integer {value:?}"),
+                };
+                Value::Flags(raw, Self::flag_names(raw, names))
+            }
+
+            DataType::FixedPoint {
+                int_bits,
+                frac_bits,
+                signed,
+            } => {
+                let raw = match (int_bits + frac_bits, signed) {
+                    (16, true) => pod::<i16>(bytes) as f64,
+                    (16, false) => pod::<u16>(bytes) as f64,
+                    (32, true) => pod::<i32>(bytes) as f64,
+                    (32, false) => pod::<u32>(bytes) as f64,
+                    (_, true) => pod::<i64>(bytes) as f64,
+                    (_, false) => pod::<u64>(bytes) as f64,
+                };
+                Value::Fixed(raw / 2f64.powi(*frac_bits as i32))
+            }
+
+            DataType::Counter => Value::Counter(pod(bytes)),
+
+            DataType::Array(element, _) => {
+                let size = element.fixed_size().unwrap_or_default();
+                Value::Array(
+                    bytes
+                        .chunks_exact(size)
+                        .map(|bytes| element.decode(bytes, matrix_order))
+                        .collect(),
+                )
+            }
+
+            DataType::PointerArray
+            | DataType::Utf8Char
+            | DataType::CString { .. }
+            | DataType::WString { .. }
+            | DataType::PascalString { .. } => {
+                unreachable!("{self:?} has no fixed size")
+            }
+        }
+    }
+
+    /// the bytes `read` decodes back into `value`, which has to be a value of this type like
+    /// `parse_value` returns. strings get their length prefix, pointer arrays their null end
+    pub fn encode(&self, value: &Value, matrix_order: MatrixOrder) -> Vec<u8> {
+        fn pod<T: bytemuck::Pod>(value: T) -> Vec<u8> {
+            bytemuck::bytes_of(&value).to_vec()
+        }
+
+        match (self, value) {
+            (DataType::Flags { base, .. }, Value::Flags(raw, _)) => match base.fixed_size() {
+                Some(1) => pod(*raw as u8),
+                Some(2) => pod(*raw as u16),
+                Some(4) => pod(*raw as u32),
+                _ => pod(*raw),
+            },
+
+            (
+                DataType::FixedPoint {
+                    int_bits,
+                    frac_bits,
+                    signed,
+                },
+                Value::Fixed(v),
+            ) => {
+                let raw = (v * 2f64.powi(*frac_bits as i32)).round();
+                match (int_bits + frac_bits, signed) {
+                    (16, true) => pod(raw as i16),
+                    (16, false) => pod(raw as u16),
+                    (32, true) => pod(raw as i32),
+                    (32, false) => pod(raw as u32),
+                    (_, true) => pod(raw as i64),
+                    (_, false) => pod(raw as u64),
+                }
+            }
+
+            (DataType::Array(element, _), Value::Array(values)) => values
+                .iter()
+                .flat_map(|value| element.encode(value, matrix_order))
+                .collect(),
+
+            (DataType::CString { .. }, Value::CString(s)) => {
+                let mut bytes = s.as_bytes().to_vec();
+                bytes.push(0);
+                bytes
+            }
+
+            (DataType::WString { .. }, Value::WString(s)) => s
+                .encode_utf16()
+                .chain([0])
+                .flat_map(u16::to_le_bytes)
+                .collect(),
+
+            (DataType::PascalString { len_width }, Value::String(s)) => {
+                let len = s.len();
+                let mut bytes = match len_width {
+                    1 => pod(len as u8),
+                    2 => pod(len as u16),
+                    4 => pod(len as u32),
+                    _ => pod(len as u64),
+                };
+                bytes.extend_from_slice(s.as_bytes());
+                bytes
+            }
+
+            (_, Value::U8(v)) => pod(*v),
+            (_, Value::U16(v)) => pod(*v),
+            (_, Value::U32(v)) => pod(*v),
+            (_, Value::U64(v)) => pod(*v),
+
+            (_, Value::I8(v)) => pod(*v),
+            (_, Value::I16(v)) => pod(*v),
+            (_, Value::I32(v)) => pod(*v),
+            (_, Value::I64(v)) => pod(*v),
+
+            (_, Value::F16(v)) => pod(half::f16::from_f32(*v).to_bits()),
+            (_, Value::F32(v)) => pod(*v),
+            (_, Value::F64(v)) => pod(*v),
+
+            (_, Value::Pointer(v)) => pod(*v),
+            (_, Value::Pointer32(v)) => pod(*v),
+            (_, Value::Pointer64(v)) => pod(*v),
+            (_, Value::PointerArray(pointers)) => pointers
+                .iter()
+                .chain([&0])
+                .flat_map(|pointer| pointer.to_ne_bytes())
+                .collect(),
+
+            (_, Value::Vec2(v)) => pod(*v),
+            (_, Value::Vec3(v)) => pod(*v),
+            (_, Value::Vec4(v)) => pod(*v),
+            (_, Value::Mat4(matrix)) => match matrix_order {
+                MatrixOrder::Row => pod(matrix.transpose()),
+                MatrixOrder::Col => pod(*matrix),
+            },
+
+            (_, Value::DVec2(v)) => pod(*v),
+            (_, Value::DVec3(v)) => pod(*v),
+            (_, Value::DVec4(v)) => pod(*v),
+
+            (_, Value::Rgb(v)) => pod(*v),
+            (_, Value::Rgba(v)) => pod(*v),
+            (_, Value::Color32(v)) => pod(*v),
+
+            (_, Value::Counter(v)) => pod(*v),
+            (_, Value::Char(char, _)) => char.encode_utf8(&mut [0; 4]).as_bytes().to_vec(),
+
+            (data_type, value) => unreachable!("{value:?} is not a {data_type:?} value"),
+        }
+    }
+
+    /// parses a value of this type, accepting the same format it is displayed in
+    pub fn parse_value(&self, s: &str) -> Result<Value, String> {
+        let s = s.trim();
         let value = match self {
-            DataType::U8 => Value::U8(memory.read(address)?),
-            DataType::U16 => Value::U16(memory.read(address)?),
-            DataType::U32 => Value::U32(memory.read(address)?),
-            DataType::U64 => Value::U64(memory.read(address)?),
-
-            DataType::I8 => Value::I8(memory.read(address)?),
-            DataType::I16 => Value::I16(memory.read(address)?),
-            DataType::I32 => Value::I32(memory.read(address)?),
-            DataType::I64 => Value::I64(memory.read(address)?),
-
-            DataType::F32 => Value::F32(memory.read(address)?),
-            DataType::F64 => Value::F64(memory.read(address)?),
-
-            DataType::Pointer => Value::Pointer(memory.read(address)?),
-            DataType::Pointer32 => Value::Pointer32(memory.read(address)?),
-            DataType::Pointer64 => Value::Pointer64(memory.read(address)?),
-
-            DataType::Vec2 => Value::Vec2(memory.read(address)?),
-            DataType::Vec3 => Value::Vec3(memory.read(address)?),
-            DataType::Vec4 => Value::Vec4(memory.read(address)?),
-            DataType::Mat4 => Value::Mat4(memory.read(address)?),
-
-            DataType::Rgb => Value::Rgb(memory.read(address)?),
-            DataType::Rgba => Value::Rgba(memory.read(address)?),
-            DataType::Color32 => Value::Color32(memory.read(address)?),
+            DataType::U8 => Value::U8(parse_integer(s, "u8")?),
+            DataType::U16 => Value::U16(parse_integer(s, "u16")?),
+            DataType::U32 => Value::U32(parse_integer(s, "u32")?),
+            DataType::U64 => Value::U64(parse_integer(s, "u64")?),
+
+            DataType::I8 => Value::I8(parse_integer(s, "i8")?),
+            DataType::I16 => Value::I16(parse_integer(s, "i16")?),
+            DataType::I32 => Value::I32(parse_integer(s, "i32")?),
+            DataType::I64 => Value::I64(parse_integer(s, "i64")?),
+
+            DataType::F16 => Value::F16(parse_float(s, "f16")?),
+            DataType::F32 => Value::F32(parse_float(s, "f32")?),
+            DataType::F64 => Value::F64(parse_float(s, "f64")?),
+
+            DataType::Pointer => Value::Pointer(parse_integer(s, "")?),
+            DataType::Pointer32 => Value::Pointer32(parse_integer(s, "")?),
+            DataType::Pointer64 => Value::Pointer64(parse_integer(s, "")?),
+            DataType::PointerArray => Value::PointerArray(
+                s.trim_start_matches('[')
+                    .trim_end_matches(']')
+                    .split(',')
+                    .filter(|pointer| !pointer.trim().is_empty())
+                    .map(|pointer| parse_integer(pointer.trim(), ""))
+                    .collect::<Result<_, _>>()?,
+            ),
+
+            DataType::Vec2 => Value::Vec2(Vec2::from_array(parse_floats(s)?)),
+            DataType::Vec3 => Value::Vec3(Vec3::from_array(parse_floats(s)?)),
+            DataType::Vec4 => Value::Vec4(Vec4::from_array(parse_floats(s)?)),
+            DataType::Mat4 => Value::Mat4(Mat4::from_cols_array(&parse_floats(s)?)),
+
+            DataType::DVec2 => Value::DVec2(DVec2::from_array(parse_floats(s)?)),
+            DataType::DVec3 => Value::DVec3(DVec3::from_array(parse_floats(s)?)),
+            DataType::DVec4 => Value::DVec4(DVec4::from_array(parse_floats(s)?)),
+
+            DataType::Rgb => Value::Rgb(parse_color(s)?),
+            DataType::Rgba => Value::Rgba(parse_color(s)?),
+            DataType::Color32 => Value::Color32(parse_floats(s)?),
+
+            DataType::Flags { names, .. } => {
+                let raw = s.split_whitespace().next().unwrap_or_default();
+                let raw = parse_integer(raw, "")?;
+                Value::Flags(raw, Self::flag_names(raw, names))
+            }
+
+            DataType::FixedPoint { .. } => Value::Fixed(parse_float(s, "fixed")?),
+
+            DataType::Counter => Value::Counter(parse_integer(s, "")?),
+
+            DataType::Utf8Char => {
+                let char = parse_char(s)?;
+                Value::Char(char, char.len_utf8())
+            }
+
+            DataType::CString { .. } => Value::CString(parse_string(s)?),
+            DataType::WString { .. } => Value::WString(parse_string(s)?),
+            DataType::PascalString { .. } => Value::String(parse_string(s)?),
+
+            DataType::Array(element, len) => {
+                let elements = split_elements(s)?;
+                if elements.len() != *len {
+                    return Err(format!(
+                        "Expected {len} elements in '{s}', found {}",
+                        elements.len()
+                    ));
+                }
+                Value::Array(
+                    elements
+                        .into_iter()
+                        .map(|element_str| element.parse_value(element_str))
+                        .collect::<Result<_, _>>()?,
+                )
+            }
         };
 
         Ok(value)
     }
+
+    /// names of all set bits, falling back to `bitN` for bits without a name
+    fn flag_names(raw: u64, names: &[(u32, String)]) -> Vec<String> {
+        (0..u64::BITS)
+            .filter(|bit| raw & (1 << bit) != 0)
+            .map(
+                |bit| match names.iter().find(|(named_bit, _)| *named_bit == bit) {
+                    Some((_, name)) => name.clone(),
+                    None => format!("bit{bit}"),
+                },
+            )
+            .collect()
+    }
+
+    /// only reads as many bytes as the leading byte announces, so a char at the end of a region
+    /// can still be read. invalid sequences yield the replacement character and consume one byte
+    fn read_utf8_char(memory: &Memory, address: usize) -> Result<(char, usize), MemoryError> {
+        let first: u8 = memory.read(address)?;
+        let len = match first {
+            0x00..=0x7F => 1,
+            0xC2..=0xDF => 2,
+            0xE0..=0xEF => 3,
+            0xF0..=0xF4 => 4,
+            _ => 0,
+        };
+
+        let bytes = match len {
+            0 => Vec::new(),
+            1 => vec![first],
+            _ => memory.read_bytes(address, len)?,
+        };
+        match std::str::from_utf8(&bytes)
+            .ok()
+            .and_then(|s| s.chars().next())
+        {
+            Some(char) => Ok((char, len)),
+            None => {
+                warning!("Invalid utf-8 sequence at 0x{address:X}, showing replacement character");
+                Ok((char::REPLACEMENT_CHARACTER, 1))
+            }
+        }
+    }
+
+    /// the length is capped at `max_string_len`, so a corrupt length can't cause huge reads
+    fn read_pascal_string(
+        memory: &Memory,
+        address: usize,
+        len_width: usize,
+    ) -> Result<String, MemoryError> {
+        let len = match len_width {
+            1 => memory.read::<u8>(address)? as usize,
+            2 => memory.read::<u16>(address)? as usize,
+            4 => memory.read::<u32>(address)? as usize,
+            _ => memory.read::<u64>(address)? as usize,
+        };
+        let len = len.min(memory.options().max_string_len);
+
+        let bytes = memory.read_bytes(address + len_width, len)?;
+        Ok(String::from_utf8_lossy(&bytes).into_owned())
+    }
+
+    /// at most `max_string_len` pointers, in case the null that ends the array is missing
+    fn read_pointer_array(memory: &Memory, address: usize) -> Result<Vec<usize>, MemoryError> {
+        let mut pointers = Vec::new();
+
+        for i in 0..memory.options().max_string_len {
+            let pointer: usize = memory.read(address + i * size_of::<usize>())?;
+            if pointer == 0 {
+                break;
+            }
+            if !memory.is_pointer_valid(pointer) {
+                return Err(MemoryError::InvalidPointer(pointer));
+            }
+            pointers.push(pointer);
+        }
+
+        Ok(pointers)
+    }
+}
+
+/// the name `parse_data_type` accepts for this type
+impl Display for DataType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            DataType::U8 => "u8",
+            DataType::U16 => "u16",
+            DataType::U32 => "u32",
+            DataType::U64 => "u64",
+
+            DataType::I8 => "i8",
+            DataType::I16 => "i16",
+            DataType::I32 => "i32",
+            DataType::I64 => "i64",
+
+            DataType::F16 => "f16",
+            DataType::F32 => "f32",
+            DataType::F64 => "f64",
+
+            DataType::Pointer => "pointer",
+            DataType::Pointer32 => "pointer32",
+            DataType::Pointer64 => "pointer64",
+            DataType::PointerArray => "ptrarray",
+
+            DataType::Vec2 => "vec2",
+            DataType::Vec3 => "vec3",
+            DataType::Vec4 => "vec4",
+            DataType::Mat4 => "mat4",
+
+            DataType::DVec2 => "dvec2",
+            DataType::DVec3 => "dvec3",
+            DataType::DVec4 => "dvec4",
+
+            DataType::Rgb => "rgb",
+            DataType::Rgba => "rgba",
+            DataType::Color32 => "color32",
+
+            DataType::Counter => "counter",
+            DataType::Utf8Char => "utf8char",
+
+            DataType::Flags { base, names } => {
+                let names = names
+                    .iter()
+                    .map(|(bit, name)| format!("{bit}={name}"))
+                    .collect::<Vec<_>>()
+                    .join(",");
+                return write!(f, "flags:{base}{{{names}}}");
+            }
+            DataType::FixedPoint {
+                int_bits,
+                frac_bits,
+                signed,
+            } => {
+                let prefix = if *signed { "fixed" } else { "ufixed" };
+                return write!(f, "{prefix}:{int_bits}.{frac_bits}");
+            }
+            DataType::CString { max_len: None } => "cstring",
+            DataType::CString {
+                max_len: Some(max_len),
+            } => return write!(f, "cstring:{max_len}"),
+            DataType::Array(element, len) => return write!(f, "{element}[{len}]"),
+            DataType::WString { max_units: None } => "wstring",
+            DataType::WString {
+                max_units: Some(max_units),
+            } => return write!(f, "wstring:{max_units}"),
+            DataType::PascalString { len_width } => {
+                return write!(f, "pstring:u{}", len_width * 8);
+            }
+        };
+
+        f.write_str(name)
+    }
+}
+
+/// saved by the name `parse_data_type` accepts, so files stay readable by hand
+impl Serialize for DataType {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for DataType {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let name = String::deserialize(deserializer)?;
+        parse_data_type(&name).map_err(serde::de::Error::custom)
+    }
+}
+
+/// decimal or `0x` hex, with an optional type suffix like `u32`
+fn parse_integer<T: TryFrom<i128>>(s: &str, suffix: &str) -> Result<T, String> {
+    let digits = s.strip_suffix(suffix).unwrap_or(s);
+    let value = match digits
+        .strip_prefix("0x")
+        .or_else(|| digits.strip_prefix("0X"))
+    {
+        Some(hex) => i128::from_str_radix(hex, 16),
+        None => digits.parse(),
+    }
+    .map_err(|e| format!("Invalid integer '{s}': {e}"))?;
+
+    T::try_from(value).map_err(|_| format!("Integer '{s}' is out of range"))
+}
+
+fn parse_float<T: FromStr<Err: Display>>(s: &str, suffix: &str) -> Result<T, String> {
+    let digits = s.strip_suffix(suffix).unwrap_or(s);
+    digits
+        .parse()
+        .map_err(|e| format!("Invalid float '{s}': {e}"))
+}
+
+pub(crate) fn unquote(s: &str) -> &str {
+    s.strip_prefix('"')
+        .and_then(|s| s.strip_suffix('"'))
+        .unwrap_or(s)
+}
+
+/// a string as displayed, `"a\tb"`. inside quotes the escapes rust prints are understood, an
+/// unquoted string is taken as is
+fn parse_string(s: &str) -> Result<String, String> {
+    let Some(quoted) = s.strip_prefix('"').and_then(|s| s.strip_suffix('"')) else {
+        return Ok(s.to_string());
+    };
+
+    let mut string = String::with_capacity(quoted.len());
+    let mut rest = quoted;
+    while let Some((literal, escaped)) = rest.split_once('\\') {
+        string.push_str(literal);
+        let len = match escaped.strip_prefix("u{") {
+            Some(hex) => hex.find('}').map_or(escaped.len(), |end| end + 3),
+            None => escaped.chars().next().map_or(0, char::len_utf8),
+        };
+        let char = unescape(&escaped[..len])
+            .ok_or_else(|| format!("Invalid escape '\\{}' in '{s}'", &escaped[..len]))?;
+        string.push(char);
+        rest = &escaped[len..];
+    }
+    string.push_str(rest);
+    Ok(string)
+}
+
+/// the char an escape like `n` or `u{e9}` stands for, without the backslash
+fn unescape(escape: &str) -> Option<char> {
+    match escape {
+        "n" => Some('\n'),
+        "r" => Some('\r'),
+        "t" => Some('\t'),
+        "0" => Some('\0'),
+        "\\" => Some('\\'),
+        "'" => Some('\''),
+        "\"" => Some('"'),
+        escape => escape
+            .strip_prefix("u{")
+            .and_then(|hex| hex.strip_suffix('}'))
+            .and_then(|hex| u32::from_str_radix(hex, 16).ok())
+            .and_then(char::from_u32),
+    }
+}
+
+/// a char as displayed, `'é'` with an optional ` (2 bytes)` suffix. quotes are optional and
+/// the escapes rust prints for chars are understood
+fn parse_char(s: &str) -> Result<char, String> {
+    let quoted = s.split_once(" (").map_or(s, |(char, _)| char);
+    let unquoted = quoted
+        .strip_prefix('\'')
+        .and_then(|s| s.strip_suffix('\''))
+        .unwrap_or(quoted);
+
+    let char = match unquoted.strip_prefix('\\') {
+        Some(escape) => unescape(escape),
+        None => {
+            let mut chars = unquoted.chars();
+            chars.next().filter(|_| chars.next().is_none())
+        }
+    };
+
+    char.ok_or_else(|| format!("Expected a single character, found '{s}'"))
+}
+
+/// the comma separated elements inside `[...]`, commas nested in brackets or quotes don't split
+fn split_elements(s: &str) -> Result<Vec<&str>, String> {
+    let Some(inner) = s
+        .strip_prefix('[')
+        .and_then(|inner| inner.strip_suffix(']'))
+    else {
+        return Err(format!("Expected an array like '[1, 2]', found '{s}'"));
+    };
+    if inner.trim().is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let mut elements = Vec::new();
+    let (mut depth, mut quoted, mut start) = (0usize, false, 0);
+    for (i, c) in inner.char_indices() {
+        match c {
+            '"' => quoted = !quoted,
+            '[' | '(' if !quoted => depth += 1,
+            ']' | ')' if !quoted => depth = depth.saturating_sub(1),
+            ',' if !quoted && depth == 0 => {
+                elements.push(inner[start..i].trim());
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    elements.push(inner[start..].trim());
+    Ok(elements)
+}
+
+/// a fixed number of floats, brackets and parentheses are ignored so nested matrices work too
+fn parse_floats<T: FromStr<Err: Display>, const N: usize>(s: &str) -> Result<[T; N], String> {
+    let floats = s
+        .split(|c: char| c == ',' || c.is_whitespace() || "[]()".contains(c))
+        .filter(|float| !float.is_empty())
+        .map(|float| parse_float(float, ""))
+        .collect::<Result<Vec<T>, _>>()?;
+
+    let len = floats.len();
+    floats
+        .try_into()
+        .map_err(|_| format!("Expected {N} floats in '{s}', found {len}"))
+}
+
+/// `#rrggbb` or `#rrggbbaa`
+fn parse_color<const N: usize>(s: &str) -> Result<[u8; N], String> {
+    let hex = s.strip_prefix('#').unwrap_or(s);
+    if hex.len() != N * 2 || !hex.is_ascii() {
+        return Err(format!("Expected {} hex digits in color '{s}'", N * 2));
+    }
+
+    let mut color = [0; N];
+    for (i, channel) in color.iter_mut().enumerate() {
+        *channel = u8::from_str_radix(&hex[i * 2..i * 2 + 2], 16)
+            .map_err(|e| format!("Invalid color '{s}': {e}"))?;
+    }
+    Ok(color)
 }
@@ -0,0 +1,153 @@
+use std::{
+    io::{IsTerminal as _, Write as _},
+    sync::atomic::{AtomicBool, Ordering},
+    time::{Duration, Instant},
+};
+
+static QUIET: AtomicBool = AtomicBool::new(false);
+static COUNT_MATCHES: AtomicBool = AtomicBool::new(false);
+
+/// silences non-fatal diagnostics, errors that end a command are still printed
+pub fn set_quiet(quiet: bool) {
+    QUIET.store(quiet, Ordering::Relaxed);
+}
+
+pub fn quiet() -> bool {
+    QUIET.load(Ordering::Relaxed)
+}
+
+pub fn set_count_matches(count_matches: bool) {
+    COUNT_MATCHES.store(count_matches, Ordering::Relaxed);
+}
+
+/// the `N matches found` summary of `--count-matches`, on stderr so piped results stay clean
+pub fn report_match_count(count: usize) {
+    if !COUNT_MATCHES.load(Ordering::Relaxed) {
+        return;
+    }
+
+    match count {
+        1 => eprintln!("1 match found"),
+        _ => eprintln!("{count} matches found"),
+    }
+}
+
+/// prints a non-fatal diagnostic to stderr unless `--quiet` was given
+macro_rules! warning {
+    ($($arg:tt)*) => {
+        if !$crate::diagnostics::quiet() {
+            eprintln!($($arg)*);
+        }
+    };
+}
+
+pub(crate) use warning;
+
+/// progress of a long operation over a known number of bytes, on stderr. a terminal gets a bar
+/// redrawn in place, anything else a line every few seconds. `--quiet` hides both
+pub struct Progress {
+    total: u64,
+    done: u64,
+    start: Instant,
+    last_report: Option<Instant>,
+    terminal: bool,
+}
+
+impl Progress {
+    const BAR_WIDTH: usize = 30;
+    const REDRAW_INTERVAL: Duration = Duration::from_millis(100);
+    const LINE_INTERVAL: Duration = Duration::from_secs(5);
+
+    pub fn new(total: u64) -> Self {
+        Self {
+            total,
+            done: 0,
+            start: Instant::now(),
+            last_report: None,
+            terminal: std::io::stderr().is_terminal(),
+        }
+    }
+
+    pub fn advance(&mut self, bytes: u64) {
+        self.done = (self.done + bytes).min(self.total);
+        let interval = if self.terminal {
+            Self::REDRAW_INTERVAL
+        } else {
+            Self::LINE_INTERVAL
+        };
+        if self
+            .last_report
+            .is_none_or(|last_report| last_report.elapsed() >= interval)
+        {
+            self.report();
+        }
+    }
+
+    /// reports the final state, and ends the bar's line on a terminal
+    pub fn finish(&mut self) {
+        self.report();
+        if self.terminal && !quiet() {
+            eprintln!();
+        }
+    }
+
+    fn report(&mut self) {
+        self.last_report = Some(Instant::now());
+        if quiet() {
+            return;
+        }
+
+        let fraction = if self.total == 0 {
+            1.0
+        } else {
+            self.done as f64 / self.total as f64
+        };
+        let elapsed = self.start.elapsed().as_secs_f64();
+        let rate = if elapsed > 0.0 {
+            self.done as f64 / elapsed
+        } else {
+            0.0
+        };
+        let eta = if rate > 0.0 {
+            format!("{:.0}s", (self.total - self.done) as f64 / rate)
+        } else {
+            "?".to_string()
+        };
+        let status = format!(
+            "{:>3.0}% {} of {}, {}/s, {eta} left",
+            fraction * 100.0,
+            format_bytes(self.done),
+            format_bytes(self.total),
+            format_bytes(rate as u64)
+        );
+
+        if self.terminal {
+            let filled = (fraction * Self::BAR_WIDTH as f64) as usize;
+            // \r and \x1b[K redraw the line in place
+            eprint!(
+                "\r\x1b[K[{}{}] {status}",
+                "#".repeat(filled),
+                "-".repeat(Self::BAR_WIDTH - filled)
+            );
+            let _ = std::io::stderr().flush();
+        } else {
+            eprintln!("scanned {status}");
+        }
+    }
+}
+
+/// a byte count in the largest binary unit that keeps it at or above 1, like `1.5 GiB`
+pub fn format_bytes(bytes: u64) -> String {
+    const UNITS: [&str; 5] = ["B", "KiB", "MiB", "GiB", "TiB"];
+
+    let mut value = bytes as f64;
+    let mut unit = 0;
+    while value >= 1024.0 && unit < UNITS.len() - 1 {
+        value /= 1024.0;
+        unit += 1;
+    }
+    match unit {
+        0 => format!("{bytes} B"),
+        _ => format!("{value:.1} {}", UNITS[unit]),
+    }
+}
@@ -0,0 +1,17 @@
+pub mod address;
+pub mod args;
+pub mod commands;
+pub mod data_type;
+pub mod diagnostics;
+pub mod memory;
+pub mod output;
+pub mod process;
+pub mod scan;
+#[cfg(test)]
+mod tests;
+pub mod value;
+
+pub use address::{AddressLocator, IdaSignature, Offset};
+pub use data_type::DataType;
+pub use memory::{FunctionLocation, Memory, MemoryError, MemoryRegion, Permissions};
+pub use value::Value;
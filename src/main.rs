@@ -1,38 +1,324 @@
+use std::process::ExitCode;
+
 use clap::Parser as _;
+use log::LevelFilter;
 
-use crate::{
-    args::{Args, Commands},
+use memutil::{
+    address::AddressLocator,
+    args::{Args, Commands, ScansCommand},
     commands::{
-        find::{find, find_function}, list::list, read::read_once, snap::snap, watch::watch
+        batch::batch,
+        disasm::disasm,
+        dump::dump,
+        export_sig::export_sig,
+        find::{FindOptions, find, find_function},
+        list::list,
+        monitor_sig::monitor_sig,
+        processes::processes,
+        read::{ReadOptions, read_hex, read_on_trigger, read_once, with_max_units},
+        replace::{Replacement, replace},
+        resolve::{ResolveOptions, resolve},
+        scan::{ScanOptions, next_scan, scan, scans_clear, scans_list},
+        self_test::self_test,
+        snap::snap,
+        strings::strings,
+        watch::{WatchOptions, watch},
+        write::{WriteOptions, write},
     },
+    diagnostics,
+    memory::{MemoryOptions, Permissions},
+    output,
 };
 
-mod address;
-mod args;
-mod commands;
-mod data_type;
-mod memory;
-#[cfg(test)]
-mod tests;
-mod value;
-
-fn main() {
+fn main() -> ExitCode {
     let args = Args::parse();
-    match args.command {
+    diagnostics::set_quiet(args.quiet);
+    diagnostics::set_count_matches(args.count_matches);
+    output::set_format(args.format);
+    output::set_color(
+        !args.no_color && std::env::var_os("NO_COLOR").is_none_or(|value| value.is_empty()),
+    );
+    init_logging(args.verbose);
+    let options = MemoryOptions {
+        max_string_len: args.max_string_len,
+        matrix_order: args.matrix_order,
+        max_matches: args.max_matches,
+        scan_data: args.scan_data,
+        esrch_retries: args.retry_on_esrch,
+        assume_static: args.assume_static,
+        gap_tolerance: args.gap_tolerance,
+        strict: args.strict,
+    };
+    let result = match args.command {
         Commands::Read {
-            pid,
+            pids,
             address,
             data_type,
-        } => read_once(pid, address, data_type),
+            hex,
+            stop,
+            stop_all,
+            watch_file,
+            rebase_to_symbol,
+            index,
+            stride,
+            show_bytes,
+            max_units,
+        } => with_max_units(data_type, max_units).and_then(|data_type| {
+            let read_options = ReadOptions {
+                element: index.zip(stride),
+                stop,
+                stop_all,
+                show_bytes,
+                rebase_to_symbol,
+            };
+            let read = || match (&data_type, hex) {
+                (_, Some(len)) => read_hex(&pids.0, &address, len, &read_options, &options),
+                (Some(data_type), None) => {
+                    read_once(&pids.0, &address, data_type, &read_options, &options)
+                }
+                (None, None) => unreachable!("clap requires a data type without --hex"),
+            };
+            match watch_file {
+                Some(trigger) => read_on_trigger(&trigger, read),
+                None => read(),
+            }
+        }),
         Commands::Watch {
             pid,
             address,
             data_type,
             interval,
-        } => watch(pid, address, data_type, interval),
-        Commands::Find { pid, address } => find(pid, address),
-        Commands::FindFunction { pid, function_name } => find_function(pid, function_name),
-        Commands::List { pid } => list(pid),
+            aggregate,
+            snapshot_on_exit,
+            refresh,
+            jitter,
+            show_bytes,
+            on_change,
+            epsilon,
+            output,
+            alert_above,
+            alert_below,
+            bell,
+            clock,
+        } => watch(
+            pid,
+            address,
+            data_type,
+            WatchOptions {
+                interval,
+                aggregate,
+                snapshot_on_exit,
+                refresh,
+                jitter,
+                show_bytes,
+                on_change: on_change.then_some(epsilon),
+                output,
+                alert_above,
+                alert_below,
+                bell,
+                clock,
+            },
+            options,
+        ),
+        Commands::Find {
+            pid,
+            address,
+            sig_file,
+            print_bytes,
+            context,
+            suggest,
+            all,
+            unsorted,
+            unique_only,
+        } => {
+            let address = address
+                .or(sig_file.map(AddressLocator::Pattern))
+                .expect("clap requires an address or --sig-file");
+            let find_options = FindOptions {
+                print_bytes,
+                context,
+                suggest,
+                all,
+                unsorted,
+                unique_only,
+            };
+            find(pid, address, &find_options, options)
+        }
+        Commands::Resolve {
+            pid,
+            address,
+            all,
+            symbol_relative,
+            rebase_to_symbol,
+            unsorted,
+            unique_only,
+        } => resolve(
+            pid,
+            address,
+            &ResolveOptions {
+                all,
+                symbol_relative,
+                rebase_to_symbol,
+                unsorted,
+                unique_only,
+            },
+            options,
+        ),
+        Commands::ExportSig { pid, address, len } => export_sig(pid, address, len),
+        Commands::MonitorSig {
+            pid,
+            signature,
+            interval,
+        } => monitor_sig(pid, signature, interval, options),
+        Commands::Batch { pid, file, compare } => batch(pid, &file, compare.as_deref(), options),
+        Commands::FindFunction {
+            pid,
+            function_name,
+            include_debuglink,
+            address_only,
+            raw,
+        } => find_function(pid, function_name, include_debuglink, address_only, raw),
+        Commands::List {
+            pids,
+            tree,
+            resident,
+            writable,
+            executable,
+            snapshot,
+            diff,
+        } => list(
+            &pids.0,
+            tree,
+            resident,
+            Permissions {
+                write: writable,
+                execute: executable,
+                ..Default::default()
+            },
+            snapshot.as_deref(),
+            diff.as_deref(),
+        ),
+        Commands::Strings {
+            pid,
+            target,
+            min_len,
+            unicode,
+        } => strings(pid, target, min_len, unicode),
         Commands::Snap { pid, lib } => snap(pid, lib),
+        Commands::SelfTest => self_test(),
+        Commands::Processes { filter } => processes(filter),
+        Commands::Scan {
+            pid,
+            data_type,
+            value,
+            region_filter,
+            progress_bar,
+            dirty_only,
+            no_context,
+            stride,
+            count,
+            name,
+        } => scan(
+            pid,
+            data_type,
+            &value,
+            &ScanOptions {
+                region_filter,
+                progress_bar,
+                dirty_only,
+                context: !no_context,
+                array: stride
+                    .zip(count)
+                    .map(|(stride, count)| (stride as usize, count as usize)),
+                name,
+            },
+            options,
+        ),
+        Commands::NextScan {
+            pid,
+            predicate,
+            name,
+        } => next_scan(pid, &predicate, &name, options),
+        Commands::Scans { command } => match command {
+            ScansCommand::List { pid } => scans_list(pid),
+            ScansCommand::Clear { name, pid } => scans_clear(&name, pid),
+        },
+        Commands::Write {
+            pid,
+            address,
+            data_type,
+            value,
+            allow_exec,
+            dry_run,
+        } => write(
+            pid,
+            address,
+            data_type,
+            &value,
+            &WriteOptions {
+                allow_exec,
+                dry_run,
+            },
+            options,
+        ),
+        Commands::Replace {
+            pid,
+            address,
+            bytes,
+            nop,
+            allow_exec,
+            dry_run,
+            backup,
+        } => {
+            let replacement = match (bytes, nop) {
+                (_, Some(count)) => Replacement::Nop(count as usize),
+                (bytes, None) => Replacement::Bytes(bytes.map(|bytes| bytes.0).unwrap_or_default()),
+            };
+            replace(
+                pid,
+                address,
+                &replacement,
+                &WriteOptions {
+                    allow_exec,
+                    dry_run,
+                },
+                backup.as_deref(),
+                options,
+            )
+        }
+        Commands::Dump {
+            pid,
+            target,
+            length,
+            output,
+        } => dump(pid, target, length, &output, options),
+        Commands::Disasm {
+            pid,
+            address,
+            count,
+        } => disasm(pid, address, count, options),
+    };
+
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(err) => {
+            output::print_error(&err.to_string(), err.kind());
+            ExitCode::from(err.exit_code())
+        }
     }
 }
+
+/// logging is off unless asked for with `-v`, `MEMUTIL_LOG` takes `env_logger` filters like
+/// `debug` or `memutil::memory=trace` and wins over the flag
+fn init_logging(verbose: u8) {
+    let level = match verbose {
+        0 => LevelFilter::Off,
+        1 => LevelFilter::Debug,
+        _ => LevelFilter::Trace,
+    };
+
+    env_logger::Builder::new()
+        .filter_level(level)
+        .parse_env("MEMUTIL_LOG")
+        .init();
+}
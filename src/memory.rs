@@ -1,10 +1,23 @@
-use std::{collections::BTreeMap, path::Path};
+use std::{
+    borrow::Cow,
+    cell::{OnceCell, RefCell},
+    collections::{BTreeMap, HashMap},
+    fs::File,
+    os::unix::fs::FileExt,
+    path::{Path, PathBuf},
+    rc::Rc,
+    time::Duration,
+};
 
 use elf::{ElfBytes, endian::AnyEndian, symbol::Symbol};
-use libc::{iovec, process_vm_readv};
+use libc::{iovec, process_vm_readv, process_vm_writev};
+use log::{debug, trace};
+use serde::Serialize;
 use thiserror::Error;
 
-use crate::address::IdaSignature;
+use crate::{
+    address::IdaSignature, diagnostics::warning, output::serialize_address, scan::signature_matches,
+};
 
 #[derive(Debug, Error)]
 pub enum MemoryError {
@@ -18,30 +31,232 @@ pub enum MemoryError {
     InvalidAddress(#[from] std::num::ParseIntError),
     #[error("Invalid Pointer 0x{0:X}")]
     InvalidPointer(usize),
-    #[error("Parial read: {0} out of {1} bytes")]
-    PartialRead(isize, usize),
+    #[error("Branch target 0x{0:X} is not executable")]
+    NotExecutable(usize),
+    #[error("Function '{0}' not found")]
+    FunctionNotFound(String),
+    #[error("No '{0}' region found")]
+    NamedRegionNotFound(String),
+    #[error("No function symbol contains 0x{0:X}")]
+    NoSymbol(usize),
+    #[error("Module '{0}' is not mapped")]
+    ModuleNotFound(String),
+    #[error("'{0}' is ambiguous, it names {1} separate regions")]
+    AmbiguousRegion(String, usize),
+    #[error("Process {0} still not found after {1} retries")]
+    ProcessGone(i32, u32),
+    #[error("Partial read at 0x{0:X}: {1} out of {2} bytes")]
+    PartialRead(usize, isize, usize),
+    #[error("Reading {1} bytes at 0x{0:X} runs past the end of its region")]
+    OutOfBounds(usize, usize),
+    #[error("Partial write at 0x{0:X}: {1} out of {2} bytes")]
+    PartialWrite(usize, isize, usize),
+    #[error("The kernel does not track soft-dirty pages")]
+    SoftDirtyUnsupported,
     #[error("I/O Error ({0})")]
     Io(#[from] std::io::Error),
 }
 
+impl MemoryError {
+    /// the variant name, the `kind` of json error output. these never change, so scripts can
+    /// rely on them
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Self::SignatureNotFound(_) => "SignatureNotFound",
+            Self::InvalidElf(_) => "InvalidElf",
+            Self::InvalidElfData(_) => "InvalidElfData",
+            Self::InvalidAddress(_) => "InvalidAddress",
+            Self::InvalidPointer(_) => "InvalidPointer",
+            Self::NotExecutable(_) => "NotExecutable",
+            Self::FunctionNotFound(_) => "FunctionNotFound",
+            Self::NamedRegionNotFound(_) => "NamedRegionNotFound",
+            Self::NoSymbol(_) => "NoSymbol",
+            Self::ModuleNotFound(_) => "ModuleNotFound",
+            Self::AmbiguousRegion(..) => "AmbiguousRegion",
+            Self::ProcessGone(..) => "ProcessGone",
+            Self::PartialRead(..) => "PartialRead",
+            Self::OutOfBounds(..) => "OutOfBounds",
+            Self::PartialWrite(..) => "PartialWrite",
+            Self::SoftDirtyUnsupported => "SoftDirtyUnsupported",
+            Self::Io(_) => "Io",
+        }
+    }
+}
+
+pub const DEFAULT_MAX_STRING_LEN: usize = 4096;
+
+#[derive(Debug, Clone)]
+pub struct MemoryOptions {
+    /// upper bound for string-like reads that search for a terminator, unless the type has its
+    /// own length
+    pub max_string_len: usize,
+    pub matrix_order: MatrixOrder,
+    /// stops collecting signature matches after this many, protects against loose patterns
+    pub max_matches: Option<usize>,
+    /// scan every region for signatures, not only mapped elf images
+    pub scan_data: bool,
+    /// how often a read is retried when the process can't be found, 0 disables retries
+    pub esrch_retries: u32,
+    /// signatures are assumed not to move, so each pattern is only scanned for once
+    pub assume_static: bool,
+    /// pointers into a gap between regions this small or smaller still count as valid
+    pub gap_tolerance: usize,
+    /// any short read fails with `PartialRead`, no retries and no `/proc/<pid>/mem` fallback
+    pub strict: bool,
+}
+
+impl Default for MemoryOptions {
+    fn default() -> Self {
+        Self {
+            max_string_len: DEFAULT_MAX_STRING_LEN,
+            matrix_order: MatrixOrder::default(),
+            max_matches: None,
+            scan_data: false,
+            esrch_retries: 0,
+            assume_static: false,
+            gap_tolerance: 0,
+            strict: false,
+        }
+    }
+}
+
+/// how matrices are laid out in the target, row-major matrices get transposed after reading
+#[derive(Debug, Clone, Copy, Default, PartialEq, clap::ValueEnum)]
+pub enum MatrixOrder {
+    Row,
+    #[default]
+    Col,
+}
+
 pub struct Memory {
     pid: i32,
     memory_regions: Vec<MemoryRegion>,
+    options: MemoryOptions,
+    stopped_threads: Vec<i32>,
+    /// first match of each pattern scanned for, only filled with `assume_static`
+    signature_cache: RefCell<HashMap<Vec<Option<u8>>, Option<usize>>>,
+    /// parsed symbols of each module file by canonical path, `None` for files that aren't elf
+    module_symbols: RefCell<HashMap<PathBuf, Option<Rc<ModuleSymbols>>>>,
+    /// `/proc/<pid>/mem`, opened the first time `process_vm_readv` is refused. `None` when
+    /// opening it failed too
+    mem_file: OnceCell<Option<File>>,
 }
 
 impl Memory {
     pub fn new(pid: i32) -> Result<Self, MemoryError> {
+        Self::with_options(pid, MemoryOptions::default())
+    }
+
+    pub fn with_options(pid: i32, options: MemoryOptions) -> Result<Self, MemoryError> {
         let memory_regions = Self::read_memory_regions(pid)?;
 
         Ok(Self {
             pid,
             memory_regions,
+            options,
+            stopped_threads: Vec::new(),
+            signature_cache: RefCell::default(),
+            module_symbols: RefCell::default(),
+            mem_file: OnceCell::new(),
         })
     }
 
+    pub fn options(&self) -> &MemoryOptions {
+        &self.options
+    }
+
+    /// stops the main thread of the target until `detach` is called. other threads keep
+    /// running, `attach_all` stops them too when a batch of reads must see one consistent state
+    pub fn attach(&mut self) -> Result<(), MemoryError> {
+        if !self.stopped_threads.is_empty() {
+            return Ok(());
+        }
+
+        Self::stop_thread(self.pid)?;
+        self.stopped_threads.push(self.pid);
+        Ok(())
+    }
+
+    /// stops every thread of the process, so reads of several values can't be torn by other
+    /// threads. threads that exit in the meantime are skipped, and the task list is rescanned
+    /// until no new threads show up
+    pub fn attach_all(&mut self) -> Result<(), MemoryError> {
+        loop {
+            let mut stopped_new_thread = false;
+            for tid in Self::thread_ids(self.pid)? {
+                if self.stopped_threads.contains(&tid) {
+                    continue;
+                }
+
+                match Self::stop_thread(tid) {
+                    Ok(()) => {
+                        self.stopped_threads.push(tid);
+                        stopped_new_thread = true;
+                    }
+                    Err(MemoryError::Io(err)) if err.raw_os_error() == Some(libc::ESRCH) => {}
+                    Err(err) => return Err(err),
+                }
+            }
+
+            if !stopped_new_thread {
+                debug!(
+                    "stopped {} threads of pid {}",
+                    self.stopped_threads.len(),
+                    self.pid
+                );
+                return Ok(());
+            }
+        }
+    }
+
+    fn thread_ids(pid: i32) -> Result<Vec<i32>, MemoryError> {
+        let mut tids = Vec::new();
+        for entry in std::fs::read_dir(format!("/proc/{pid}/task"))? {
+            if let Ok(tid) = entry?.file_name().to_string_lossy().parse() {
+                tids.push(tid);
+            }
+        }
+        Ok(tids)
+    }
+
+    /// resumes the target after `attach`, this also happens when `Memory` is dropped
+    pub fn detach(&mut self) -> Result<(), MemoryError> {
+        let mut result = Ok(());
+        for tid in self.stopped_threads.drain(..) {
+            let detached = unsafe { libc::ptrace(libc::PTRACE_DETACH, tid, 0, 0) };
+            if detached == -1 && result.is_ok() {
+                result = Err(MemoryError::Io(std::io::Error::last_os_error()));
+            }
+        }
+        result
+    }
+
+    fn stop_thread(tid: i32) -> Result<(), MemoryError> {
+        if unsafe { libc::ptrace(libc::PTRACE_SEIZE, tid, 0, 0) } == -1 {
+            return Err(MemoryError::Io(std::io::Error::last_os_error()));
+        }
+
+        if unsafe { libc::ptrace(libc::PTRACE_INTERRUPT, tid, 0, 0) } == -1 {
+            let err = std::io::Error::last_os_error();
+            unsafe { libc::ptrace(libc::PTRACE_DETACH, tid, 0, 0) };
+            return Err(MemoryError::Io(err));
+        }
+
+        let mut status = 0;
+        if unsafe { libc::waitpid(tid, &mut status, libc::__WALL) } == -1 {
+            let err = std::io::Error::last_os_error();
+            unsafe { libc::ptrace(libc::PTRACE_DETACH, tid, 0, 0) };
+            return Err(MemoryError::Io(err));
+        }
+
+        Ok(())
+    }
+
+    /// the remote address may have any alignment, the bytes land in a local `T` which is always
+    /// aligned for it, so over-aligned types like `Vec4` and `Mat4` are fine
     pub fn read<T: bytemuck::Pod>(&self, address: usize) -> Result<T, MemoryError> {
         let size = std::mem::size_of::<T>();
-        let mut value: T = unsafe { std::mem::zeroed() };
+        let mut value: T = bytemuck::Zeroable::zeroed();
         let bytes = bytemuck::bytes_of_mut(&mut value);
 
         let local_iov = iovec {
@@ -53,17 +268,143 @@ impl Memory {
             iov_len: size,
         };
 
-        let read = unsafe { process_vm_readv(self.pid, &local_iov, 1, &remote_iov, 1, 0) };
-        if read == -1 {
-            Err(MemoryError::Io(std::io::Error::last_os_error()))
-        } else if read as usize != size {
-            Err(MemoryError::PartialRead(read, size))
+        let read = self.read_vectored(&[local_iov], &[remote_iov])?;
+        if read != size {
+            Err(MemoryError::PartialRead(address, read as isize, size))
         } else {
             Ok(value)
         }
     }
 
+    /// reads a whole struct in one syscall. `T` has to be `#[repr(C)]` so its layout matches the
+    /// target's, and `Pod` so any bytes read are a valid value; padding must be spelled out as
+    /// explicit fields for the derive to accept it
+    ///
+    /// ```no_run
+    /// use bytemuck::{Pod, Zeroable};
+    /// use memutil::memory::Memory;
+    ///
+    /// #[repr(C)]
+    /// #[derive(Clone, Copy, Pod, Zeroable)]
+    /// struct Player {
+    ///     health: i32,
+    ///     armor: i32,
+    ///     position: [f32; 3],
+    ///     team: u32,
+    /// }
+    ///
+    /// let memory = Memory::new(1234)?;
+    /// let player: Player = memory.read_struct(0x7F00_0000_1000)?;
+    /// println!("{} hp", player.health);
+    /// # Ok::<(), memutil::memory::MemoryError>(())
+    /// ```
+    pub fn read_struct<T: bytemuck::Pod>(&self, address: usize) -> Result<T, MemoryError> {
+        self.read(address)
+    }
+
+    /// reads one value per address, batching up to `UIO_MAXIOV` reads into a single syscall
+    pub fn read_many<T: bytemuck::Pod>(&self, addresses: &[usize]) -> Result<Vec<T>, MemoryError> {
+        if let Some(&address) = addresses
+            .iter()
+            .find(|&&address| !self.is_pointer_valid(address))
+        {
+            return Err(MemoryError::InvalidPointer(address));
+        }
+
+        let size = std::mem::size_of::<T>();
+        let mut values: Vec<T> = vec![bytemuck::Zeroable::zeroed(); addresses.len()];
+        let max_iov = libc::UIO_MAXIOV as usize;
+
+        for (addresses, values) in addresses.chunks(max_iov).zip(values.chunks_mut(max_iov)) {
+            let bytes = bytemuck::cast_slice_mut::<T, u8>(values);
+            let count = bytes.len();
+
+            let local_iov = iovec {
+                iov_base: bytes.as_mut_ptr() as *mut libc::c_void,
+                iov_len: count,
+            };
+            let remote_iovs: Vec<iovec> = addresses
+                .iter()
+                .map(|&address| iovec {
+                    iov_base: address as *mut libc::c_void,
+                    iov_len: size,
+                })
+                .collect();
+
+            let read = self.read_vectored(&[local_iov], &remote_iovs)?;
+            if read != count {
+                let address = addresses[read / size.max(1)];
+                return Err(MemoryError::PartialRead(address, read as isize, count));
+            }
+        }
+
+        Ok(values)
+    }
+
+    /// reads several `(address, len)` ranges, batching up to `UIO_MAXIOV` ranges per syscall
+    pub fn read_bytes_at_multiple(
+        &self,
+        ranges: &[(usize, usize)],
+    ) -> Result<Vec<Vec<u8>>, MemoryError> {
+        let mut buffers: Vec<Vec<u8>> = ranges.iter().map(|&(_, len)| vec![0u8; len]).collect();
+        let max_iov = libc::UIO_MAXIOV as usize;
+
+        for (ranges, buffers) in ranges.chunks(max_iov).zip(buffers.chunks_mut(max_iov)) {
+            let local_iovs: Vec<iovec> = buffers
+                .iter_mut()
+                .map(|buffer| iovec {
+                    iov_base: buffer.as_mut_ptr() as *mut libc::c_void,
+                    iov_len: buffer.len(),
+                })
+                .collect();
+            let remote_iovs: Vec<iovec> = ranges
+                .iter()
+                .map(|&(address, len)| iovec {
+                    iov_base: address as *mut libc::c_void,
+                    iov_len: len,
+                })
+                .collect();
+
+            let read = match self.read_vectored(&local_iovs, &remote_iovs) {
+                Ok(read) => read,
+                // efault means the very first range could not be read
+                Err(MemoryError::Io(err)) if err.raw_os_error() == Some(libc::EFAULT) => {
+                    let (address, len) = ranges[0];
+                    return Err(MemoryError::PartialRead(address, 0, len));
+                }
+                Err(err) => return Err(err),
+            };
+
+            // a short read stops at the first range that could not be read completely
+            let mut remaining = read;
+            for &(address, len) in ranges {
+                if remaining < len {
+                    return Err(MemoryError::PartialRead(address, remaining as isize, len));
+                }
+                remaining -= len;
+            }
+        }
+
+        Ok(buffers)
+    }
+
     pub fn read_bytes(&self, address: usize, count: usize) -> Result<Vec<u8>, MemoryError> {
+        let buffer = self.read_bytes_partial(address, count)?;
+        if buffer.len() != count {
+            Err(MemoryError::PartialRead(
+                address,
+                buffer.len() as isize,
+                count,
+            ))
+        } else {
+            Ok(buffer)
+        }
+    }
+
+    /// like `read_bytes`, but a read that runs into an unreadable page returns the bytes before
+    /// it instead of failing. only fails when not even the first byte can be read, or with
+    /// `strict` on any short read
+    pub fn read_bytes_partial(&self, address: usize, count: usize) -> Result<Vec<u8>, MemoryError> {
         let mut buffer = vec![0u8; count];
 
         let local_iov = iovec {
@@ -75,52 +416,359 @@ impl Memory {
             iov_len: count,
         };
 
-        let read = unsafe { process_vm_readv(self.pid, &local_iov, 1, &remote_iov, 1, 0) };
-        if read == -1 {
-            Err(MemoryError::Io(std::io::Error::last_os_error()))
-        } else if read as usize != count {
-            Err(MemoryError::PartialRead(read, count))
+        let read = self.read_vectored(&[local_iov], &[remote_iov])?;
+        if self.options.strict && read != count {
+            return Err(MemoryError::PartialRead(address, read as isize, count));
+        }
+        buffer.truncate(read);
+        Ok(buffer)
+    }
+
+    pub fn write<T: bytemuck::Pod>(&self, address: usize, value: T) -> Result<(), MemoryError> {
+        self.write_bytes(address, bytemuck::bytes_of(&value))
+    }
+
+    /// both ends of the write have to lie in mapped regions, so a bad address fails before
+    /// anything in the target is touched
+    pub fn write_bytes(&self, address: usize, bytes: &[u8]) -> Result<(), MemoryError> {
+        let Some(last) = address.checked_add(bytes.len().saturating_sub(1)) else {
+            return Err(MemoryError::InvalidPointer(address));
+        };
+        if let Some(&invalid) = [address, last]
+            .iter()
+            .find(|&&pointer| !self.is_pointer_valid(pointer))
+        {
+            return Err(MemoryError::InvalidPointer(invalid));
+        }
+
+        let local_iov = iovec {
+            iov_base: bytes.as_ptr() as *mut libc::c_void,
+            iov_len: bytes.len(),
+        };
+        let remote_iov = iovec {
+            iov_base: address as *mut libc::c_void,
+            iov_len: bytes.len(),
+        };
+
+        let written = unsafe { process_vm_writev(self.pid, &local_iov, 1, &remote_iov, 1, 0) };
+        if written == -1 {
+            let err = std::io::Error::last_os_error();
+            trace!("process_vm_writev failed: {err}");
+            return Err(MemoryError::Io(err));
+        }
+        trace!("process_vm_writev at 0x{address:X} wrote {written} bytes");
+
+        if written as usize != bytes.len() {
+            Err(MemoryError::PartialWrite(address, written, bytes.len()))
         } else {
-            Ok(buffer)
+            Ok(())
         }
     }
 
-    pub fn scan_signature(&self, signature: &IdaSignature) -> Result<Option<usize>, MemoryError> {
-        for region in &self.memory_regions {
-            let path = Path::new(&region.pathname);
-            if !path.exists() || !path.is_file() {
-                continue;
+    /// like `write_bytes`, but through `/proc/<pid>/mem`, which writes past page protections the
+    /// way debuggers set breakpoints. for patching code in read-only executable mappings
+    pub fn patch_bytes(&self, address: usize, bytes: &[u8]) -> Result<(), MemoryError> {
+        let Some(last) = address.checked_add(bytes.len().saturating_sub(1)) else {
+            return Err(MemoryError::InvalidPointer(address));
+        };
+        if let Some(&invalid) = [address, last]
+            .iter()
+            .find(|&&pointer| !self.is_pointer_valid(pointer))
+        {
+            return Err(MemoryError::InvalidPointer(invalid));
+        }
+
+        let file = std::fs::OpenOptions::new()
+            .write(true)
+            .open(format!("/proc/{}/mem", self.pid))?;
+        file.write_all_at(bytes, address as u64)?;
+        trace!(
+            "/proc/{}/mem at 0x{address:X} wrote {} bytes",
+            self.pid,
+            bytes.len()
+        );
+        Ok(())
+    }
+
+    /// reads up to the first nul, at most `max_len` bytes (`max_string_len` without one) and never
+    /// past the end of the mapping. invalid utf-8 becomes the replacement character, a string
+    /// without a nul in range ends in `…`
+    pub fn read_cstring(
+        &self,
+        address: usize,
+        max_len: Option<usize>,
+    ) -> Result<String, MemoryError> {
+        let end = self
+            .mapped_end(address)
+            .ok_or(MemoryError::InvalidPointer(address))?;
+        let max_len = max_len.unwrap_or(self.options.max_string_len);
+        let len = max_len.min(end - address);
+
+        // the mapping can continue into a guard page, the string ends before it then
+        let bytes = self.read_bytes_partial(address, len)?;
+        match bytes.iter().position(|&byte| byte == 0) {
+            Some(end) => Ok(String::from_utf8_lossy(&bytes[..end]).into_owned()),
+            None => Ok(format!("{}…", String::from_utf8_lossy(&bytes))),
+        }
+    }
+
+    /// `read_cstring` for utf-16le, stopping at the first nul code unit after at most `max_units`.
+    /// unpaired surrogates become the replacement character
+    pub fn read_wstring(
+        &self,
+        address: usize,
+        max_units: Option<usize>,
+    ) -> Result<String, MemoryError> {
+        let end = self
+            .mapped_end(address)
+            .ok_or(MemoryError::InvalidPointer(address))?;
+        let max_units = max_units.unwrap_or(self.options.max_string_len);
+        let units = max_units.min((end - address) / 2);
+
+        let bytes = self.read_bytes_partial(address, units * 2)?;
+        let units: Vec<u16> = bytes
+            .chunks_exact(2)
+            .map(|unit| u16::from_le_bytes([unit[0], unit[1]]))
+            .collect();
+        match units.iter().position(|&unit| unit == 0) {
+            Some(end) => Ok(String::from_utf16_lossy(&units[..end])),
+            None => Ok(format!("{}…", String::from_utf16_lossy(&units))),
+        }
+    }
+
+    /// `process_vm_readv`, retrying up to `esrch_retries` times while the target reports ESRCH,
+    /// which can happen for a moment while one of its threads is torn down
+    fn read_vectored(
+        &self,
+        local_iovs: &[iovec],
+        remote_iovs: &[iovec],
+    ) -> Result<usize, MemoryError> {
+        const RETRY_DELAY: Duration = Duration::from_millis(1);
+
+        let mut retries = 0;
+        loop {
+            let read = unsafe {
+                process_vm_readv(
+                    self.pid,
+                    local_iovs.as_ptr(),
+                    local_iovs.len() as libc::c_ulong,
+                    remote_iovs.as_ptr(),
+                    remote_iovs.len() as libc::c_ulong,
+                    0,
+                )
+            };
+            if read != -1 {
+                trace!(
+                    "process_vm_readv of {} iovecs at 0x{:X} read {read} bytes",
+                    remote_iovs.len(),
+                    remote_iovs.first().map_or(0, |iov| iov.iov_base as usize)
+                );
+                return Ok(read as usize);
+            }
+
+            let err = std::io::Error::last_os_error();
+            trace!("process_vm_readv failed: {err}");
+            // not on EFAULT: /proc/<pid>/mem reads past page protections, so it would read
+            // guard pages and fault in reserved memory of the target
+            if err.raw_os_error() == Some(libc::EPERM) && !self.options.strict {
+                return match self.read_proc_mem(local_iovs, remote_iovs) {
+                    Some(read) => {
+                        debug!(
+                            "process_vm_readv failed ({err}), read {read} bytes from /proc/{}/mem",
+                            self.pid
+                        );
+                        Ok(read)
+                    }
+                    None => Err(MemoryError::Io(err)),
+                };
+            }
+            if err.raw_os_error() != Some(libc::ESRCH)
+                || self.options.esrch_retries == 0
+                || self.options.strict
+            {
+                return Err(MemoryError::Io(err));
+            }
+            if retries == self.options.esrch_retries {
+                return Err(MemoryError::ProcessGone(self.pid, retries));
+            }
+            retries += 1;
+            std::thread::sleep(RETRY_DELAY);
+        }
+    }
+
+    /// the same read through `pread` on `/proc/<pid>/mem`, which some hardened kernels allow
+    /// while refusing `process_vm_readv`. like it, stops at the first unreadable byte and returns
+    /// how many were read, `None` when not even the first one could be
+    fn read_proc_mem(&self, local_iovs: &[iovec], remote_iovs: &[iovec]) -> Option<usize> {
+        let file = self
+            .mem_file
+            .get_or_init(|| {
+                File::open(format!("/proc/{}/mem", self.pid))
+                    .inspect_err(|err| debug!("unable to open /proc/{}/mem: {err}", self.pid))
+                    .ok()
+            })
+            .as_ref()?;
+
+        let mut bytes = Vec::new();
+        for remote_iov in remote_iovs {
+            let start = bytes.len();
+            bytes.resize(start + remote_iov.iov_len, 0);
+            let read = pread_fully(file, &mut bytes[start..], remote_iov.iov_base as u64);
+            bytes.truncate(start + read);
+            if read < remote_iov.iov_len {
+                break;
+            }
+        }
+        if bytes.is_empty() && remote_iovs.iter().any(|iov| iov.iov_len > 0) {
+            return None;
+        }
+
+        let mut copied = 0;
+        for local_iov in local_iovs {
+            let len = local_iov.iov_len.min(bytes.len() - copied);
+            unsafe {
+                std::ptr::copy_nonoverlapping(
+                    bytes[copied..].as_ptr(),
+                    local_iov.iov_base as *mut u8,
+                    len,
+                );
             }
+            copied += len;
+        }
+
+        Some(bytes.len())
+    }
+
+    pub fn scan_signature(&self, signature: &IdaSignature) -> Result<Option<usize>, MemoryError> {
+        if !self.options.assume_static {
+            return self.scan_signature_uncached(signature);
+        }
+
+        if let Some(&address) = self.signature_cache.borrow().get(signature.pattern()) {
+            return Ok(address);
+        }
+        let address = self.scan_signature_uncached(signature)?;
+        self.signature_cache
+            .borrow_mut()
+            .insert(signature.pattern().to_vec(), address);
+        Ok(address)
+    }
+
+    /// forgets the cached signature matches of `assume_static`, the next scans run again
+    pub fn clear_signature_cache(&self) {
+        self.signature_cache.borrow_mut().clear();
+    }
 
-            let address = self.scan_signature_in_region(signature, region)?;
-            if let Some(address) = address {
-                return Ok(Some(address));
+    fn scan_signature_uncached(
+        &self,
+        signature: &IdaSignature,
+    ) -> Result<Option<usize>, MemoryError> {
+        for region in self.scan_targets().iter() {
+            let address = self.scan_region(signature, region, 1)?.first().copied();
+            if address.is_some() {
+                return Ok(address);
             }
         }
 
         Ok(None)
     }
 
-    fn scan_signature_in_region(
+    /// collects every match of the signature, including overlapping ones, up to `max_matches`
+    pub fn scan_signature_all(&self, signature: &IdaSignature) -> Result<Vec<usize>, MemoryError> {
+        let max_matches = self.options.max_matches.unwrap_or(usize::MAX);
+
+        let mut matches = Vec::new();
+        for region in self.scan_targets().iter() {
+            if matches.len() >= max_matches {
+                break;
+            }
+
+            matches.extend(self.scan_region(signature, region, max_matches - matches.len())?);
+        }
+
+        Ok(matches)
+    }
+
+    /// whether a scan returning `count` matches may have been cut short by `max_matches`
+    pub fn match_limit_reached(&self, count: usize) -> bool {
+        self.options.max_matches.is_some_and(|max| count >= max)
+    }
+
+    /// whole elf images, or every region with `scan_data`
+    fn scan_targets(&self) -> Cow<'_, [MemoryRegion]> {
+        if self.options.scan_data {
+            Cow::Borrowed(&self.memory_regions)
+        } else {
+            Cow::Owned(self.mapped_images())
+        }
+    }
+
+    /// by default only mapped elf images are scanned, `scan_data` extends this to every
+    /// region except the kernel-provided ones
+    fn scan_region(
         &self,
         signature: &IdaSignature,
         region: &MemoryRegion,
-    ) -> Result<Option<usize>, MemoryError> {
+        max_matches: usize,
+    ) -> Result<Vec<usize>, MemoryError> {
+        if self.options.scan_data {
+            if region.is_kernel_provided() {
+                debug!("skipping kernel-provided region {}", region.pathname);
+                return Ok(Vec::new());
+            }
+            return Ok(self.scan_region_windowed(signature, region, max_matches));
+        }
+
+        let path = Path::new(&region.pathname);
+        if !path.exists() || !path.is_file() {
+            trace!(
+                "skipping 0x{:X}-0x{:X} {}, not a mapped file",
+                region.start, region.end, region.pathname
+            );
+            return Ok(Vec::new());
+        }
+
         let data = self.dump_elf(region)?;
-        let pattern = signature.pattern();
-
-        'outer: for i in 0..=data.len() - pattern.len() {
-            for (j, &pat_byte) in pattern.iter().enumerate() {
-                if let Some(b) = pat_byte
-                    && data[i + j] != b
-                {
-                    continue 'outer;
-                }
+        let matches: Vec<usize> = signature_matches(&data, signature.pattern())
+            .map(|offset| region.start + offset)
+            .take(max_matches)
+            .collect();
+        debug!(
+            "scanned {} bytes of {}, {} matches",
+            data.len(),
+            region.pathname,
+            matches.len()
+        );
+        Ok(matches)
+    }
+
+    /// reads the region in windows that overlap by the pattern length, so matches across
+    /// window borders are still found. unreadable windows, like guard pages, are skipped
+    fn scan_region_windowed(
+        &self,
+        signature: &IdaSignature,
+        region: &MemoryRegion,
+        max_matches: usize,
+    ) -> Vec<usize> {
+        const WINDOW_SIZE: usize = 1024 * 1024;
+
+        let overlap = signature.pattern().len().saturating_sub(1);
+        let mut matches = Vec::new();
+        let mut window_start = region.start;
+        while window_start < region.end && matches.len() < max_matches {
+            let window_end = (window_start + WINDOW_SIZE + overlap).min(region.end);
+            match self.read_bytes(window_start, window_end - window_start) {
+                Ok(data) => matches.extend(
+                    signature_matches(&data, signature.pattern())
+                        .map(|offset| window_start + offset)
+                        .take(max_matches - matches.len()),
+                ),
+                Err(err) => trace!("skipping unreadable window at 0x{window_start:X}: {err}"),
             }
-            return Ok(Some(region.start + i));
+            window_start += WINDOW_SIZE;
         }
 
-        Ok(None)
+        matches
     }
 
     fn dump_elf(&self, region: &MemoryRegion) -> Result<Vec<u8>, MemoryError> {
@@ -136,124 +784,213 @@ impl Memory {
         let maps_file_name = format!("/proc/{pid}/maps");
         let maps_file = std::fs::read_to_string(maps_file_name)?;
 
-        let mut region_map = BTreeMap::new();
-        for line in maps_file.lines() {
-            let parts: Vec<&str> = line.splitn(6, ' ').collect();
-            if parts.len() < 2 {
-                continue;
-            }
+        let regions = parse_maps(&maps_file)?;
+        debug!(
+            "parsed {} maps lines of pid {pid} into {} regions",
+            maps_file.lines().count(),
+            regions.len()
+        );
+        Ok(regions)
+    }
 
-            // Parse address range (format: "start-end")
-            let address_range = parts[0];
-            let range_parts: Vec<&str> = address_range.split('-').collect();
-            if range_parts.len() != 2 {
-                continue;
-            }
+    /// the region with the permissions at `address`, which is only part of the module for
+    /// mapped files. `mapped_images` has the whole module
+    pub fn find_containing_region(&self, address: usize) -> Option<&MemoryRegion> {
+        self.memory_regions
+            .iter()
+            .find(|&region| address >= region.start && address < region.end)
+            .map(|v| v as _)
+    }
 
-            let start =
-                usize::from_str_radix(range_parts[0], 16).map_err(MemoryError::InvalidAddress)?;
-            let end =
-                usize::from_str_radix(range_parts[1], 16).map_err(MemoryError::InvalidAddress)?;
+    /// the first executable region overlapping `len` bytes at `address`
+    pub fn find_executable_region(&self, address: usize, len: usize) -> Option<&MemoryRegion> {
+        let end = address.saturating_add(len.max(1));
+        self.memory_regions
+            .iter()
+            .find(|region| region.permissions.execute && region.start < end && address < region.end)
+    }
 
-            // Get pathname (last field)
-            let pathname = if parts.len() >= 6 && !parts[5].is_empty() {
-                parts[5].trim().to_string()
-            } else {
-                "[anonymous]".to_string()
-            };
+    /// end of the mapping at `address`, following regions that touch it. regions are split by
+    /// permissions, so a value can lie in two of them while still being mapped in one piece
+    pub fn mapped_end(&self, address: usize) -> Option<usize> {
+        let index = self
+            .memory_regions
+            .iter()
+            .position(|region| address >= region.start && address < region.end)?;
 
-            region_map
-                .entry(pathname)
-                .or_insert_with(Vec::new)
-                .push((start, end));
+        let mut end = self.memory_regions[index].end;
+        for region in &self.memory_regions[index + 1..] {
+            if region.start != end {
+                break;
+            }
+            end = region.end;
         }
+        Some(end)
+    }
 
-        let mut regions = Vec::new();
-        for (pathname, mut ranges) in region_map {
-            ranges.sort_by_key(|&(start, _)| start);
+    /// regions are split by permissions, so guard regions like the `---p` gaps inside a module
+    /// no longer hide in a readable one and are rejected here
+    pub fn is_pointer_valid(&self, pointer: usize) -> bool {
+        for region in &self.memory_regions {
+            if region.permissions.read && pointer >= region.start && pointer <= region.end {
+                return true;
+            }
+        }
 
-            let mut merged_ranges = Vec::new();
-            let mut current_range = ranges[0];
+        // a pointer into a small gap between two regions, like a guard page between tightly
+        // packed mappings, is accepted if the whole gap is within `gap_tolerance`
+        let tolerance = self.options.gap_tolerance;
+        tolerance > 0
+            && self.memory_regions.windows(2).any(|regions| {
+                let (before, after) = (&regions[0], &regions[1]);
+                pointer > before.end
+                    && pointer < after.start
+                    && after.start - before.end <= tolerance
+            })
+    }
 
-            for &(start, end) in &ranges[1..] {
-                if start <= current_range.1 {
-                    current_range.1 = current_range.1.max(end);
-                } else {
-                    merged_ranges.push(current_range);
-                    current_range = (start, end);
-                }
-            }
-            merged_ranges.push(current_range);
+    /// resident bytes of every region, in the same order as `memory_regions`, from
+    /// `/proc/<pid>/pagemap`. pages that are swapped out or never touched don't count
+    pub fn resident_sizes(&self) -> Result<Vec<usize>, MemoryError> {
+        const PAGE_PRESENT: u64 = 1 << 63;
 
-            for (start, end) in merged_ranges {
-                regions.push(MemoryRegion {
-                    start,
-                    end,
-                    pathname: pathname.clone(),
-                });
-            }
-        }
+        let page_size = unsafe { libc::sysconf(libc::_SC_PAGESIZE) } as usize;
+        let pagemap = File::open(format!("/proc/{}/pagemap", self.pid))?;
 
-        // Sort regions by start address
-        regions.sort_by_key(|r| r.start);
+        let mut sizes = Vec::with_capacity(self.memory_regions.len());
+        for region in &self.memory_regions {
+            let resident_pages = read_pagemap(&pagemap, region.start, region.end, page_size)?
+                .iter()
+                .filter(|&&entry| entry & PAGE_PRESENT != 0)
+                .count();
+            sizes.push(resident_pages * page_size);
+        }
 
-        Ok(regions)
+        Ok(sizes)
     }
 
-    pub fn find_containing_region(&self, address: usize) -> Option<&MemoryRegion> {
-        self.memory_regions
+    /// clears the soft-dirty bit of every page, so `soft_dirty_ranges` only reports pages the
+    /// process writes to from now on. pages start out soft-dirty, so when none of them is the
+    /// kernel was built without tracking and clearing would silently do nothing
+    pub fn clear_soft_dirty(&self) -> Result<(), MemoryError> {
+        let mut tracked = false;
+        for region in self
+            .memory_regions
             .iter()
-            .find(|&region| address >= region.start && address <= region.end)
-            .map(|v| v as _)
+            .filter(|region| region.permissions.write)
+        {
+            if !self.soft_dirty_ranges(region.start, region.end)?.is_empty() {
+                tracked = true;
+                break;
+            }
+        }
+        if !tracked {
+            return Err(MemoryError::SoftDirtyUnsupported);
+        }
+
+        std::fs::write(format!("/proc/{}/clear_refs", self.pid), "4")?;
+        Ok(())
     }
 
-    pub fn is_pointer_valid(&self, pointer: usize) -> bool {
-        for region in &self.memory_regions {
-            if pointer >= region.start && pointer <= region.end {
-                return true;
+    /// page-aligned ranges of `start..end` written to since `clear_soft_dirty`, from the
+    /// soft-dirty bits in `/proc/<pid>/pagemap`. touching dirty pages form one range
+    pub fn soft_dirty_ranges(
+        &self,
+        start: usize,
+        end: usize,
+    ) -> Result<Vec<(usize, usize)>, MemoryError> {
+        const PAGE_SOFT_DIRTY: u64 = 1 << 55;
+
+        let page_size = unsafe { libc::sysconf(libc::_SC_PAGESIZE) } as usize;
+        let pagemap = File::open(format!("/proc/{}/pagemap", self.pid))?;
+        let first_page = start / page_size * page_size;
+
+        let mut ranges: Vec<(usize, usize)> = Vec::new();
+        for (index, entry) in read_pagemap(&pagemap, start, end, page_size)?
+            .into_iter()
+            .enumerate()
+        {
+            if entry & PAGE_SOFT_DIRTY == 0 {
+                continue;
+            }
+            let page = first_page + index * page_size;
+            let page_end = (page + page_size).min(end);
+            match ranges.last_mut() {
+                Some((_, range_end)) if *range_end == page.max(start) => *range_end = page_end,
+                _ => ranges.push((page.max(start), page_end)),
             }
         }
-        false
+        Ok(ranges)
     }
 
     pub fn memory_regions(&self) -> &[MemoryRegion] {
         &self.memory_regions
     }
 
-    pub fn find_function(&self, function_name: &str) -> Result<Vec<FunctionLocation>, MemoryError> {
-        let mut found_functions = Vec::new();
+    /// regions of a file are split by permissions, this joins touching regions of the same file
+    /// back into the whole image, with the permissions of any of its parts
+    pub fn mapped_images(&self) -> Vec<MemoryRegion> {
+        let mut images: Vec<MemoryRegion> = Vec::new();
+        for region in &self.memory_regions {
+            match images.last_mut() {
+                Some(image) if image.end == region.start && image.pathname == region.pathname => {
+                    image.end = region.end;
+                    image.permissions = image.permissions.union(region.permissions);
+                }
+                _ => images.push(region.clone()),
+            }
+        }
+        images
+    }
 
+    /// groups regions by pathname, ordered by the lowest address of each group
+    pub fn regions_by_module(&self) -> Vec<(&str, Vec<&MemoryRegion>)> {
+        let mut modules: Vec<(&str, Vec<&MemoryRegion>)> = Vec::new();
         for region in &self.memory_regions {
-            let file_name = &region.pathname;
-            let path = Path::new(file_name);
-            if !path.exists() || !path.is_file() {
-                continue;
+            match modules
+                .iter_mut()
+                .find(|(pathname, _)| *pathname == region.pathname)
+            {
+                Some((_, regions)) => regions.push(region),
+                None => modules.push((&region.pathname, vec![region])),
             }
-            let data = std::fs::read(file_name)?;
-            if !data.starts_with(&[0x7F, b'E', b'L', b'F']) {
+        }
+        modules
+    }
+
+    pub fn find_function(
+        &self,
+        function_name: &str,
+        include_debuglink: bool,
+    ) -> Result<Vec<FunctionLocation>, MemoryError> {
+        let mut found_functions = Vec::new();
+
+        for (file_name, _) in self.regions_by_module() {
+            let Some(symbols) = self.module_symbols(file_name)? else {
                 continue;
-            }
-            let elf = ElfBytes::<AnyEndian>::minimal_parse(&data)?;
-            let common_data = elf.find_common_data()?;
-            let (dynsyms, dynstr) = match (common_data.dynsyms, common_data.dynsyms_strs) {
-                (Some(dynsyms), Some(dynstr)) => (dynsyms, dynstr),
-                _ => {
-                    eprintln!("Could not find dynamic symbols for {}", region.pathname);
-                    continue;
-                }
             };
+            match &symbols.exported {
+                Some(exported) => found_functions.extend(
+                    exported
+                        .iter()
+                        .filter(|symbol| symbol.matches(function_name))
+                        .map(|symbol| FunctionLocation::new(file_name, symbol)),
+                ),
+                None => warning!("Could not find dynamic symbols for {file_name}"),
+            }
 
-            for sym in dynsyms {
-                if !self.is_exported_function(&sym) {
+            if !include_debuglink {
+                continue;
+            }
+            for symbol in symbols.debug() {
+                if !symbol.matches(function_name) {
                     continue;
                 }
-
-                let name = dynstr.get(sym.st_name as usize)?;
-                if name == function_name {
-                    found_functions.push(FunctionLocation {
-                        pathname: region.pathname.clone(),
-                        address: sym.st_value as usize,
-                    });
+                let is_duplicate = found_functions.iter().any(|function| {
+                    function.pathname == file_name && function.address == symbol.address
+                });
+                if !is_duplicate {
+                    found_functions.push(FunctionLocation::new(file_name, symbol));
                 }
             }
         }
@@ -261,27 +998,609 @@ impl Memory {
         Ok(found_functions)
     }
 
-    fn is_exported_function(&self, sym: &Symbol) -> bool {
-        let is_function = sym.st_symtype() == elf::abi::STT_FUNC;
+    /// runtime address of the first function with this name, the load bias of position
+    /// independent modules is applied
+    pub fn function_address(&self, function_name: &str) -> Result<usize, MemoryError> {
+        let functions = self.find_function(function_name, true)?;
+        let Some(function) = functions.first() else {
+            return Err(MemoryError::FunctionNotFound(function_name.to_string()));
+        };
+
+        let load_bias = self
+            .module_symbols(&function.pathname)?
+            .map_or(0, |symbols| self.load_bias(&function.pathname, &symbols));
+        Ok(function.address + load_bias)
+    }
+
+    /// locates the separate debug file named in `.gnu_debuglink`, using the same search paths as gdb
+    fn find_debuglink(path: &Path, elf: &ElfBytes<AnyEndian>) -> Option<PathBuf> {
+        let section = elf.section_header_by_name(".gnu_debuglink").ok()??;
+        let (data, _) = elf.section_data(&section).ok()?;
+        let name_len = data.iter().position(|&byte| byte == 0)?;
+        let name = std::str::from_utf8(&data[..name_len]).ok()?;
 
-        let is_global_or_weak = matches!(sym.st_bind(), elf::abi::STB_GLOBAL | elf::abi::STB_WEAK);
+        let dir = path.parent()?;
+        let global_dir = Path::new("/usr/lib/debug").join(dir.strip_prefix("/").ok()?);
+        [
+            dir.join(name),
+            dir.join(".debug").join(name),
+            global_dir.join(name),
+        ]
+        .into_iter()
+        .find(|candidate| candidate.is_file() && candidate != path)
+    }
 
-        let is_defined = sym.st_shndx != elf::abi::SHN_UNDEF;
+    /// finds the closest function symbol at or below `address` in the module containing it,
+    /// using the dynamic, static and debuglink symbol tables
+    pub fn symbol_for_address(
+        &self,
+        address: usize,
+    ) -> Result<Option<SymbolLocation>, MemoryError> {
+        let Some(region) = self.find_containing_region(address) else {
+            return Ok(None);
+        };
+        let Some(symbols) = self.module_symbols(&region.pathname)? else {
+            return Ok(None);
+        };
+        let load_bias = self.load_bias(&region.pathname, &symbols);
+        let Some(file_address) = address.checked_sub(load_bias) else {
+            return Ok(None);
+        };
 
-        let has_name = sym.st_name != 0;
+        // on a tie the symbol from the module itself wins over the debug file's
+        let closest = [symbols.functions(), symbols.debug()]
+            .into_iter()
+            .filter_map(|table| {
+                let index = table.partition_point(|symbol| symbol.address <= file_address);
+                index.checked_sub(1).map(|index| &table[index])
+            })
+            .reduce(|closest, symbol| {
+                if symbol.address > closest.address {
+                    symbol
+                } else {
+                    closest
+                }
+            });
 
-        is_function && is_global_or_weak && is_defined && has_name
+        Ok(closest.map(|symbol| SymbolLocation {
+            address: symbol.address + load_bias,
+            ..symbol.clone()
+        }))
+    }
+
+    /// symbols of the module file at `pathname`, parsed once per `Memory` even when mappings
+    /// name the same file by different paths. `None` when it isn't an elf image
+    pub fn module_symbols(&self, pathname: &str) -> Result<Option<Rc<ModuleSymbols>>, MemoryError> {
+        let path = Path::new(pathname);
+        if !path.is_file() {
+            return Ok(None);
+        }
+        let path = std::fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf());
+        if let Some(symbols) = self.module_symbols.borrow().get(&path) {
+            return Ok(symbols.clone());
+        }
+
+        let symbols = ModuleSymbols::parse(&path)?.map(Rc::new);
+        self.module_symbols
+            .borrow_mut()
+            .insert(path, symbols.clone());
+        Ok(symbols)
+    }
+
+    /// difference between runtime addresses and the virtual addresses in the module's elf file
+    fn load_bias(&self, pathname: &str, symbols: &ModuleSymbols) -> usize {
+        if !symbols.relocatable {
+            return 0;
+        }
+
+        let base = self
+            .memory_regions
+            .iter()
+            .filter(|region| region.pathname == pathname)
+            .map(|region| region.start)
+            .min()
+            .unwrap_or_default();
+        base.saturating_sub(symbols.first_vaddr & !0xFFF)
     }
 }
 
-#[derive(Debug)]
+impl Drop for Memory {
+    fn drop(&mut self) {
+        let _ = self.detach();
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
 pub struct MemoryRegion {
+    #[serde(serialize_with = "serialize_address")]
     pub start: usize,
+    #[serde(serialize_with = "serialize_address")]
     pub end: usize,
+    pub permissions: Permissions,
     pub pathname: String,
 }
 
+/// the permission field of a maps line, like `r-xp`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Permissions {
+    pub read: bool,
+    pub write: bool,
+    pub execute: bool,
+    /// shared with other processes, private copy-on-write otherwise
+    pub shared: bool,
+}
+
+impl Permissions {
+    pub fn parse(s: &str) -> Self {
+        let flag = |index: usize, set: u8| s.as_bytes().get(index) == Some(&set);
+        Self {
+            read: flag(0, b'r'),
+            write: flag(1, b'w'),
+            execute: flag(2, b'x'),
+            shared: flag(3, b's'),
+        }
+    }
+
+    /// permissions set in either
+    pub fn union(self, other: Permissions) -> Self {
+        Self {
+            read: self.read || other.read,
+            write: self.write || other.write,
+            execute: self.execute || other.execute,
+            shared: self.shared || other.shared,
+        }
+    }
+
+    /// whether every permission set in `required` is set here too
+    pub fn contains(&self, required: Permissions) -> bool {
+        (self.read || !required.read)
+            && (self.write || !required.write)
+            && (self.execute || !required.execute)
+            && (self.shared || !required.shared)
+    }
+}
+
+impl std::fmt::Display for Permissions {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let flag = |set: bool, c: char| if set { c } else { '-' };
+        write!(
+            f,
+            "{}{}{}{}",
+            flag(self.read, 'r'),
+            flag(self.write, 'w'),
+            flag(self.execute, 'x'),
+            if self.shared { 's' } else { 'p' }
+        )
+    }
+}
+
+impl Serialize for Permissions {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl MemoryRegion {
+    /// `[vvar]`, `[vdso]`, `[vsyscall]` and similar, which are not part of the program's data
+    pub fn is_kernel_provided(&self) -> bool {
+        self.pathname.starts_with('[')
+            && !matches!(self.pathname.as_str(), "[anonymous]" | "[heap]")
+            && !self.pathname.starts_with("[stack")
+    }
+
+    pub fn file_name(&self) -> Option<&str> {
+        self.pathname
+            .rsplit_once('/')
+            .map(|(_, file_name)| file_name)
+    }
+
+    /// matches the file name against `pattern`, as a glob if it contains `*` or `?`,
+    /// or as a substring otherwise
+    pub fn matches_module(&self, pattern: &str) -> bool {
+        let Some(file_name) = self.file_name() else {
+            return false;
+        };
+
+        if is_glob(pattern) {
+            glob_matches(pattern, file_name)
+        } else {
+            file_name.contains(pattern)
+        }
+    }
+}
+
+/// regions of a maps file, with touching ranges of the same file joined when their permissions
+/// match. a module is split into one region per segment with different permissions
+pub(crate) fn parse_maps(maps_file: &str) -> Result<Vec<MemoryRegion>, MemoryError> {
+    let mut region_map = BTreeMap::new();
+    for line in maps_file.lines() {
+        let parts: Vec<&str> = line.splitn(6, ' ').collect();
+        if parts.len() < 2 {
+            continue;
+        }
+
+        // Parse address range (format: "start-end")
+        let address_range = parts[0];
+        let range_parts: Vec<&str> = address_range.split('-').collect();
+        if range_parts.len() != 2 {
+            continue;
+        }
+
+        let start =
+            usize::from_str_radix(range_parts[0], 16).map_err(MemoryError::InvalidAddress)?;
+        let end = usize::from_str_radix(range_parts[1], 16).map_err(MemoryError::InvalidAddress)?;
+
+        let permissions = Permissions::parse(parts[1]);
+
+        // Get pathname (last field)
+        let pathname = if parts.len() >= 6 && !parts[5].is_empty() {
+            parts[5].trim().to_string()
+        } else {
+            "[anonymous]".to_string()
+        };
+
+        region_map
+            .entry(pathname)
+            .or_insert_with(Vec::new)
+            .push((start, end, permissions));
+    }
+
+    let mut regions = Vec::new();
+    for (pathname, mut ranges) in region_map {
+        ranges.sort_by_key(|&(start, ..)| start);
+
+        let mut merged_ranges = Vec::new();
+        let mut current_range = ranges[0];
+
+        // a module's segments touch, but only ones with the same permissions form a region
+        for &(start, end, permissions) in &ranges[1..] {
+            if start <= current_range.1 && permissions == current_range.2 {
+                current_range.1 = current_range.1.max(end);
+            } else {
+                merged_ranges.push(current_range);
+                current_range = (start, end, permissions);
+            }
+        }
+        merged_ranges.push(current_range);
+
+        for (start, end, permissions) in merged_ranges {
+            regions.push(MemoryRegion {
+                start,
+                end,
+                permissions,
+                pathname: pathname.clone(),
+            });
+        }
+    }
+
+    // Sort regions by start address
+    regions.sort_by_key(|r| r.start);
+    Ok(regions)
+}
+
+/// pagemap entries of the pages in `start..end`, read in chunks. pagemap ends with the user
+/// address space, so regions past it like `[vsyscall]` have no entries
+fn read_pagemap(
+    pagemap: &File,
+    start: usize,
+    end: usize,
+    page_size: usize,
+) -> Result<Vec<u64>, MemoryError> {
+    const CHUNK_PAGES: usize = 64 * 1024;
+
+    let first_page = start / page_size;
+    let page_count = end.div_ceil(page_size) - first_page;
+
+    let mut entries = Vec::with_capacity(page_count);
+    let mut chunk = vec![0u64; CHUNK_PAGES.min(page_count)];
+    for chunk_start in (0..page_count).step_by(CHUNK_PAGES) {
+        let chunk = &mut chunk[..CHUNK_PAGES.min(page_count - chunk_start)];
+        let offset = (first_page + chunk_start) * size_of::<u64>();
+        let read = pagemap.read_at(bytemuck::cast_slice_mut(chunk), offset as u64)?;
+        entries.extend_from_slice(&chunk[..read / size_of::<u64>()]);
+        if read < size_of_val(chunk) {
+            break;
+        }
+    }
+    Ok(entries)
+}
+
+/// `read_at` until the buffer is full or a read fails or hits the end, returns the bytes read
+fn pread_fully(file: &File, buffer: &mut [u8], offset: u64) -> usize {
+    let mut read = 0;
+    while read < buffer.len() {
+        match file.read_at(&mut buffer[read..], offset + read as u64) {
+            Ok(0) | Err(_) => break,
+            Ok(n) => read += n,
+        }
+    }
+    read
+}
+
+pub fn is_glob(pattern: &str) -> bool {
+    pattern.contains(['*', '?'])
+}
+
+/// `*` matches any run of characters, `?` matches a single character
+pub fn glob_matches(pattern: &str, text: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let text: Vec<char> = text.chars().collect();
+
+    let (mut p, mut t) = (0, 0);
+    // position of the last `*` and the text position it is currently matched up to
+    let mut backtrack = None;
+
+    while t < text.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                backtrack = Some((p, t));
+                p += 1;
+            }
+            Some(&c) if c == '?' || c == text[t] => {
+                p += 1;
+                t += 1;
+            }
+            _ => match backtrack {
+                Some((star, star_t)) => {
+                    p = star + 1;
+                    t = star_t + 1;
+                    backtrack = Some((star, star_t + 1));
+                }
+                None => return false,
+            },
+        }
+    }
+
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
+#[derive(Serialize)]
 pub struct FunctionLocation {
     pub pathname: String,
+    /// the symbol as stored in the module, mangled for c++ and rust
+    pub name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub demangled: Option<String>,
+    #[serde(serialize_with = "serialize_address")]
     pub address: usize,
 }
+
+impl FunctionLocation {
+    fn new(pathname: &str, symbol: &SymbolLocation) -> Self {
+        Self {
+            pathname: pathname.to_string(),
+            name: symbol.name.clone(),
+            demangled: symbol.demangled().map(str::to_string),
+            address: symbol.address,
+        }
+    }
+
+    /// the demangled name when there is one
+    pub fn display_name(&self) -> &str {
+        self.demangled.as_deref().unwrap_or(&self.name)
+    }
+}
+
+/// readable form of a mangled rust or itanium c++ symbol, `None` for plain c names
+pub fn demangle(name: &str) -> Option<String> {
+    // legacy rust symbols are valid itanium ones too, so rust goes first. `#` drops the hash
+    if let Ok(demangled) = rustc_demangle::try_demangle(name) {
+        return Some(format!("{demangled:#}"));
+    }
+    cpp_demangle::Symbol::new(name).ok()?.demangle().ok()
+}
+
+/// queries with characters only demangled names have, like `game::Entity::update`, are
+/// compared to the demangled symbol, with or without its parameter list. other queries have
+/// to match the symbol as stored
+pub fn symbol_matches(name: &str, query: &str) -> bool {
+    if !is_demangled_query(query) {
+        return name == query;
+    }
+    demangle(name).is_some_and(|demangled| demangled_matches(&demangled, query))
+}
+
+fn is_demangled_query(query: &str) -> bool {
+    query.contains([':', '(', '<', ' ', '~'])
+}
+
+fn demangled_matches(demangled: &str, query: &str) -> bool {
+    demangled == query || without_parameters(demangled) == Some(query)
+}
+
+/// the demangled name without its trailing parameter list and qualifiers like `const`. the
+/// list is found by matching parentheses from the end, names like `operator()` or
+/// `std::function<void (int)>` contain some of their own
+fn without_parameters(demangled: &str) -> Option<&str> {
+    let close = demangled.rfind(')')?;
+    let mut depth = 0;
+    for (index, c) in demangled[..=close].char_indices().rev() {
+        match c {
+            ')' => depth += 1,
+            '(' => {
+                depth -= 1;
+                if depth == 0 {
+                    return Some(&demangled[..index]);
+                }
+            }
+            _ => {}
+        }
+    }
+    None
+}
+
+/// function symbols of one module file at the virtual addresses in the file, which are off by
+/// the load bias from the runtime addresses of position independent modules
+#[derive(Debug)]
+pub struct ModuleSymbols {
+    /// global and weak functions from the dynamic symbol table, `None` without one
+    pub exported: Option<Vec<SymbolLocation>>,
+    /// every defined function from the dynamic and static symbol tables, sorted by address
+    functions: Vec<SymbolLocation>,
+    /// the separate debug file named in `.gnu_debuglink`
+    debug_path: Option<PathBuf>,
+    debug: OnceCell<Vec<SymbolLocation>>,
+    relocatable: bool,
+    /// lowest virtual address of a loadable segment
+    first_vaddr: usize,
+}
+
+impl ModuleSymbols {
+    /// reads the module file once, only the debug file is left until its symbols are needed
+    fn parse(path: &Path) -> Result<Option<Self>, MemoryError> {
+        let data = std::fs::read(path)?;
+        if !data.starts_with(&[0x7F, b'E', b'L', b'F']) {
+            return Ok(None);
+        }
+        let elf = ElfBytes::<AnyEndian>::minimal_parse(&data)?;
+
+        let common_data = elf.find_common_data()?;
+        let exported = match (common_data.dynsyms, common_data.dynsyms_strs) {
+            (Some(dynsyms), Some(dynstr)) => Some(
+                dynsyms
+                    .iter()
+                    .filter(is_exported_function)
+                    .map(|sym| {
+                        Ok(SymbolLocation::new(
+                            dynstr.get(sym.st_name as usize)?.to_string(),
+                            sym.st_value as usize,
+                        ))
+                    })
+                    .collect::<Result<_, MemoryError>>()?,
+            ),
+            _ => None,
+        };
+
+        let first_vaddr = elf
+            .segments()
+            .and_then(|segments| {
+                segments
+                    .iter()
+                    .filter(|segment| segment.p_type == elf::abi::PT_LOAD)
+                    .map(|segment| segment.p_vaddr as usize)
+                    .min()
+            })
+            .unwrap_or_default();
+
+        Ok(Some(Self {
+            exported,
+            functions: function_symbols(&elf, true),
+            debug_path: Memory::find_debuglink(path, &elf),
+            debug: OnceCell::new(),
+            relocatable: elf.ehdr.e_type == elf::abi::ET_DYN,
+            first_vaddr,
+        }))
+    }
+
+    /// every defined function from the dynamic and static symbol tables, sorted by address
+    pub fn functions(&self) -> &[SymbolLocation] {
+        &self.functions
+    }
+
+    /// defined functions from the `.gnu_debuglink` debug file, sorted by address
+    pub fn debug(&self) -> &[SymbolLocation] {
+        self.debug.get_or_init(|| {
+            let Some(debug_path) = &self.debug_path else {
+                return Vec::new();
+            };
+            let Ok(data) = std::fs::read(debug_path) else {
+                return Vec::new();
+            };
+            ElfBytes::<AnyEndian>::minimal_parse(&data)
+                .map(|elf| function_symbols(&elf, false))
+                .unwrap_or_default()
+        })
+    }
+}
+
+/// defined functions of an elf file, sorted by address, from its static symbol table and with
+/// `dynamic` its dynamic one as well. best-effort, unreadable tables yield no symbols
+fn function_symbols(elf: &ElfBytes<AnyEndian>, dynamic: bool) -> Vec<SymbolLocation> {
+    let mut tables = Vec::new();
+    if dynamic
+        && let Ok(common_data) = elf.find_common_data()
+        && let (Some(dynsyms), Some(dynstr)) = (common_data.dynsyms, common_data.dynsyms_strs)
+    {
+        tables.push((dynsyms, dynstr));
+    }
+    if let Ok(Some(table)) = elf.symbol_table() {
+        tables.push(table);
+    }
+
+    let mut symbols = Vec::new();
+    for (symtab, strtab) in &tables {
+        symbols.extend(symtab.iter().filter(is_defined_function).filter_map(|sym| {
+            Some(SymbolLocation::new(
+                strtab.get(sym.st_name as usize).ok()?.to_string(),
+                sym.st_value as usize,
+            ))
+        }));
+    }
+    sort_symbols(&mut symbols);
+    symbols
+}
+
+/// the sort is stable, so aliases keep the name from the earliest table, the dynamic one
+fn sort_symbols(symbols: &mut Vec<SymbolLocation>) {
+    symbols.sort_by_key(|symbol| symbol.address);
+    symbols.dedup_by_key(|symbol| symbol.address);
+}
+
+fn is_exported_function(sym: &Symbol) -> bool {
+    let is_global_or_weak = matches!(sym.st_bind(), elf::abi::STB_GLOBAL | elf::abi::STB_WEAK);
+
+    is_defined_function(sym) && is_global_or_weak
+}
+
+fn is_defined_function(sym: &Symbol) -> bool {
+    let is_function = sym.st_symtype() == elf::abi::STT_FUNC;
+
+    let is_defined = sym.st_shndx != elf::abi::SHN_UNDEF;
+
+    let has_name = sym.st_name != 0;
+
+    is_function && is_defined && has_name
+}
+
+#[derive(Debug, Clone)]
+pub struct SymbolLocation {
+    pub name: String,
+    pub address: usize,
+    /// demangled on first use, symbols stay cached on `Memory` across lookups
+    demangled: OnceCell<Option<String>>,
+}
+
+impl SymbolLocation {
+    pub fn new(name: String, address: usize) -> Self {
+        Self {
+            name,
+            address,
+            demangled: OnceCell::new(),
+        }
+    }
+
+    pub fn demangled(&self) -> Option<&str> {
+        self.demangled
+            .get_or_init(|| demangle(&self.name))
+            .as_deref()
+    }
+
+    /// the demangled name when there is one
+    pub fn display_name(&self) -> &str {
+        self.demangled().unwrap_or(&self.name)
+    }
+
+    /// `address` relative to the symbol, like `draw_frame+0x2C`, with the name demangled
+    pub fn relative(&self, address: usize) -> String {
+        format!("{}+0x{:X}", self.display_name(), address - self.address)
+    }
+
+    /// like `symbol_matches`, demangling the name at most once
+    pub fn matches(&self, query: &str) -> bool {
+        if !is_demangled_query(query) {
+            return self.name == query;
+        }
+        self.demangled()
+            .is_some_and(|demangled| demangled_matches(demangled, query))
+    }
+}
@@ -0,0 +1,82 @@
+use std::{
+    io::IsTerminal as _,
+    sync::atomic::{AtomicBool, Ordering},
+};
+
+use serde::{Serialize, Serializer};
+
+static JSON: AtomicBool = AtomicBool::new(false);
+static COLOR: AtomicBool = AtomicBool::new(true);
+
+/// how commands print their results
+#[derive(Debug, Clone, Copy, Default, PartialEq, clap::ValueEnum)]
+pub enum OutputFormat {
+    #[default]
+    Text,
+    /// one json object per line
+    Json,
+}
+
+pub fn set_format(format: OutputFormat) {
+    JSON.store(format == OutputFormat::Json, Ordering::Relaxed);
+}
+
+pub fn json() -> bool {
+    JSON.load(Ordering::Relaxed)
+}
+
+/// colors can still be turned off, but never on for output that isn't a terminal
+pub fn set_color(color: bool) {
+    COLOR.store(color, Ordering::Relaxed);
+}
+
+/// where colored text is printed to, only terminals get colors
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Stream {
+    Stdout,
+    Stderr,
+}
+
+impl Stream {
+    fn is_terminal(self) -> bool {
+        match self {
+            Self::Stdout => std::io::stdout().is_terminal(),
+            Self::Stderr => std::io::stderr().is_terminal(),
+        }
+    }
+}
+
+/// ansi style of alerts
+pub const BOLD_RED: &str = "1;31";
+
+/// `text` in an ansi `style` like `BOLD_RED`. every colored output goes through this, so
+/// `--no-color`, `NO_COLOR` and output that isn't a terminal all leave text plain
+pub fn paint(text: &str, style: &str, stream: Stream) -> String {
+    if COLOR.load(Ordering::Relaxed) && stream.is_terminal() {
+        format!("\x1b[{style}m{text}\x1b[0m")
+    } else {
+        text.to_string()
+    }
+}
+
+/// prints a record as a single line of json, so output can be streamed line by line
+pub fn print_json(record: &impl Serialize) {
+    match serde_json::to_string(record) {
+        Ok(line) => println!("{line}"),
+        Err(err) => eprintln!("Unable to serialize output: {err}"),
+    }
+}
+
+/// prints an error to stderr, as a `{"error", "kind"}` object with `--format json`
+pub fn print_error(message: &str, kind: &str) {
+    if json() {
+        eprintln!("{}", serde_json::json!({ "error": message, "kind": kind }));
+    } else {
+        eprintln!("{message}");
+    }
+}
+
+/// addresses as `0x`-prefixed hex strings, json numbers can't hold every 64 bit address exactly
+pub fn serialize_address<S: Serializer>(address: &usize, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.collect_str(&format_args!("0x{address:X}"))
+}
@@ -0,0 +1,78 @@
+use std::path::Path;
+
+/// a running process as `/proc/<pid>` describes it
+#[derive(Debug, Clone, PartialEq)]
+pub struct ProcessInfo {
+    pub pid: i32,
+    /// from `comm`, cut to 15 bytes by the kernel
+    pub name: String,
+    /// arguments from `cmdline`, empty for kernel threads
+    pub cmdline: Vec<String>,
+}
+
+impl ProcessInfo {
+    pub fn read(pid: i32) -> Option<Self> {
+        let dir = Path::new("/proc").join(pid.to_string());
+        let name = std::fs::read_to_string(dir.join("comm")).ok()?;
+        let cmdline = std::fs::read(dir.join("cmdline")).ok()?;
+
+        Some(Self {
+            pid,
+            name: name.trim_end_matches('\n').to_string(),
+            cmdline: cmdline
+                .split(|&byte| byte == 0)
+                .filter(|arg| !arg.is_empty())
+                .map(|arg| String::from_utf8_lossy(arg).into_owned())
+                .collect(),
+        })
+    }
+
+    /// file name of the first argument, usually the untruncated executable name
+    pub fn program(&self) -> Option<&str> {
+        let program = self.cmdline.first()?;
+        Some(program.rsplit('/').next().unwrap_or(program))
+    }
+}
+
+/// when the process started in clock ticks since boot, field 22 of `/proc/<pid>/stat`. tells a
+/// process apart from a later one that was given the same pid
+pub fn start_time(pid: i32) -> std::io::Result<u64> {
+    let stat = std::fs::read_to_string(format!("/proc/{pid}/stat"))?;
+    // the name in parentheses can contain spaces, the fields after it can't
+    stat.rsplit_once(')')
+        .and_then(|(_, fields)| fields.split_whitespace().nth(19)?.parse().ok())
+        .ok_or_else(|| std::io::Error::other("no start time in stat"))
+}
+
+/// every process in `/proc` sorted by pid, processes that exit or can't be read are skipped
+pub fn processes() -> Vec<ProcessInfo> {
+    let Ok(entries) = std::fs::read_dir("/proc") else {
+        return Vec::new();
+    };
+
+    let mut processes: Vec<ProcessInfo> = entries
+        .flatten()
+        .filter_map(|entry| entry.file_name().to_str()?.parse().ok())
+        .filter_map(ProcessInfo::read)
+        .collect();
+    processes.sort_by_key(|process| process.pid);
+    processes
+}
+
+/// processes whose `comm` is `name`, or when there are none, whose first argument is
+pub fn matching_pids(processes: &[ProcessInfo], name: &str) -> Vec<i32> {
+    let by_comm: Vec<i32> = processes
+        .iter()
+        .filter(|process| process.name == name)
+        .map(|process| process.pid)
+        .collect();
+    if !by_comm.is_empty() {
+        return by_comm;
+    }
+
+    processes
+        .iter()
+        .filter(|process| process.program() == Some(name))
+        .map(|process| process.pid)
+        .collect()
+}
@@ -0,0 +1,67 @@
+/// offsets of all positions in `data` where the pattern matches, `None` bytes match anything.
+/// boyer-moore-horspool on the longest run of concrete bytes in the pattern: the byte under the
+/// end of that run decides how far the window moves, the rest of the pattern is only compared
+/// once the run's last byte matched. patterns of only wildcards fall back to every offset
+pub fn signature_matches(data: &[u8], pattern: &[Option<u8>]) -> impl Iterator<Item = usize> {
+    let len = pattern.len().max(1);
+    let (anchor_start, anchor_len) = longest_concrete_run(pattern);
+    let anchor: Vec<u8> = pattern[anchor_start..anchor_start + anchor_len]
+        .iter()
+        .flatten()
+        .copied()
+        .collect();
+    let anchor_end = anchor_start + anchor_len.max(1) - 1;
+    let shifts = skip_table(&anchor);
+    let last = anchor.last().copied();
+
+    let mut offset = 0;
+    std::iter::from_fn(move || {
+        while offset + len <= data.len() {
+            let start = offset;
+            let tail = data[start + anchor_end];
+            offset += shifts[tail as usize];
+            // most windows already differ in the last byte of the run
+            match last {
+                None => return Some(start),
+                Some(b) if b == tail && window_matches(&data[start..start + len], pattern) => {
+                    return Some(start);
+                }
+                Some(_) => {}
+            }
+        }
+        None
+    })
+}
+
+fn window_matches(window: &[u8], pattern: &[Option<u8>]) -> bool {
+    window
+        .iter()
+        .zip(pattern)
+        .all(|(byte, pattern_byte)| pattern_byte.is_none_or(|b| b == *byte))
+}
+
+/// start and length of the longest run without wildcards, the last one of equally long runs.
+/// empty when the pattern is only wildcards
+fn longest_concrete_run(pattern: &[Option<u8>]) -> (usize, usize) {
+    let mut longest = (0, 0);
+    let mut run_start = 0;
+    for (i, pattern_byte) in pattern.iter().enumerate() {
+        if pattern_byte.is_none() {
+            run_start = i + 1;
+        } else if i + 1 - run_start >= longest.1 {
+            longest = (run_start, i + 1 - run_start);
+        }
+    }
+    longest
+}
+
+/// how far the window moves for each byte under the end of the run, from where that byte last
+/// occurs in the run before its final position
+fn skip_table(run: &[u8]) -> [usize; 256] {
+    let len = run.len().max(1);
+    let mut shifts = [len; 256];
+    for (i, byte) in run.iter().enumerate().take(len - 1) {
+        shifts[*byte as usize] = len - 1 - i;
+    }
+    shifts
+}
@@ -1,9 +1,45 @@
-use glam::{Mat4, Quat, vec2, vec3, vec4};
+use std::{
+    io::{BufRead as _, BufReader, Read as _},
+    os::unix::fs::MetadataExt as _,
+    process::{Child, Command, Stdio},
+    sync::atomic::{AtomicI32, AtomicUsize, Ordering as AtomicOrdering},
+    time::Duration,
+};
+
+use clap::CommandFactory as _;
+use glam::{Mat4, Quat, Vec4, dvec2, dvec3, dvec4, vec2, vec3, vec4};
 
 use crate::{
-    address::AddressLocator,
+    address::{AddressLocator, IdaSignature, Offset, ResolveCache},
+    args::{
+        Args, DumpTarget, parse_address_locator, parse_bytes, parse_data_type, parse_dump_target,
+        parse_pid, parse_scan_name, parse_signature_file,
+    },
+    commands::{
+        CommandError,
+        disasm::{Architecture, disassemble},
+        export_sig::wildcard_displacements,
+        find::longest_matching_prefix,
+        list::diff_regions,
+        read::with_max_units,
+        replace::{Replacement, plan_replace},
+        resolve::order_resolved,
+        scan::{
+            ScanPredicate, ScanRange, ScanSession, array_bases, saved_sessions, scan_ranges,
+            scan_value, scan_value_in, session_dir, total_bytes,
+        },
+        strings::find_strings,
+        watch::{Aggregate, Alerts, WatchOptions, XorShift, write_snapshot},
+        write::{WriteOptions, WritePlan, check_write},
+    },
     data_type::DataType,
-    memory::{Memory, MemoryError},
+    diagnostics::format_bytes,
+    memory::{
+        MatrixOrder, Memory, MemoryError, MemoryOptions, MemoryRegion, Permissions, demangle,
+        glob_matches, parse_maps, symbol_matches,
+    },
+    process::{ProcessInfo, matching_pids, processes, start_time},
+    scan::signature_matches,
     value::Value,
 };
 
@@ -27,6 +63,49 @@ fn assert_read(address: usize, data_type: DataType, expected: Value) -> Result<(
     Ok(())
 }
 
+#[test]
+fn test_args() {
+    Args::command().debug_assert();
+}
+
+#[test]
+fn test_pid_file() {
+    let path = std::env::temp_dir().join(format!("memutil-test-{}.pid", pid()));
+    let locator = format!("pidfile:{}", path.display());
+
+    std::fs::write(&path, format!("{}\n", pid())).unwrap();
+    assert_eq!(parse_pid(&locator), Ok(pid()));
+    for contents in ["self", "+1", &locator, "memutil*"] {
+        std::fs::write(&path, contents).unwrap();
+        assert!(parse_pid(&locator).is_err(), "{contents}");
+    }
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn test_pid_by_name() {
+    let comm = std::fs::read_to_string("/proc/self/comm").unwrap();
+    let comm = comm.trim_end();
+    assert_eq!(ProcessInfo::read(pid()).unwrap().name, comm);
+    // the helper process tests run the same binary, so the name may match more than this one
+    assert!(matching_pids(&processes(), comm).contains(&pid()));
+    assert!(parse_pid("no-process-is-called-this").is_err());
+
+    let process = |pid: i32, name: &str, program: &str| ProcessInfo {
+        pid,
+        name: name.to_string(),
+        cmdline: vec![program.to_string(), "--flag".to_string()],
+    };
+    let processes = [
+        process(10, "game", "/opt/game/game"),
+        process(20, "launcher-with-a", "/opt/game/launcher-with-a-long-name"),
+        process(30, "game", "./game"),
+    ];
+    assert_eq!(matching_pids(&processes, "game"), [10, 30]);
+    assert_eq!(matching_pids(&processes, "launcher-with-a-long-name"), [20]);
+    assert!(matching_pids(&processes, "--flag").is_empty());
+}
+
 #[test]
 fn test_simple_read() -> Result<(), MemoryError> {
     let buf = 0x12345678;
@@ -72,9 +151,11 @@ fn test_integer_reads() -> Result<(), MemoryError> {
 
 #[test]
 fn test_float_reads() -> Result<(), MemoryError> {
+    let f16_val = half::f16::from_f32(-0.75);
     let f32_val: f32 = 1.5;
     let f64_val: f64 = -3.25;
 
+    assert_read(address(&f16_val), DataType::F16, Value::F16(-0.75))?;
     assert_read(address(&f32_val), DataType::F32, Value::F32(f32_val))?;
     assert_read(address(&f64_val), DataType::F64, Value::F64(f64_val))?;
 
@@ -117,6 +198,158 @@ fn test_vector_reads() -> Result<(), MemoryError> {
     assert_read(address(&v3), DataType::Vec3, Value::Vec3(v3))?;
     assert_read(address(&v4), DataType::Vec4, Value::Vec4(v4))?;
 
+    let dv2 = dvec2(1e-300, 2.5);
+    let dv3 = dvec3(0.1, -4.0, 1e300);
+    let dv4 = dvec4(1.0, 2.0, 3.0, f64::MAX);
+
+    assert_read(address(&dv2), DataType::DVec2, Value::DVec2(dv2))?;
+    assert_read(address(&dv3), DataType::DVec3, Value::DVec3(dv3))?;
+    assert_read(address(&dv4), DataType::DVec4, Value::DVec4(dv4))?;
+
+    Ok(())
+}
+
+#[test]
+fn test_array_reads() -> Result<(), MemoryError> {
+    let bytes: [u8; 4] = [1, 2, 3, 4];
+    let floats: [f32; 3] = [0.5, -1.0, 8.0];
+
+    assert_read(
+        address(&bytes),
+        parse_data_type("u8[4]").unwrap(),
+        Value::Array(bytes.map(Value::U8).into()),
+    )?;
+    assert_read(
+        address(&floats),
+        parse_data_type("f32[3]").unwrap(),
+        Value::Array(floats.map(Value::F32).into()),
+    )?;
+    assert_eq!(
+        Value::Array(bytes.map(Value::U8).into()).to_string(),
+        "[1u8, 2u8, 3u8, 4u8]"
+    );
+    assert!(parse_data_type("cstring:8[2]").is_err());
+    assert!(parse_data_type("u8[0]").is_err());
+    assert!(parse_data_type("u8[0][5]").is_err());
+    assert!(parse_data_type(&format!("u64[{}]", usize::MAX / 4)).is_err());
+
+    Ok(())
+}
+
+#[test]
+fn test_data_type_sizes() {
+    let sizes = [
+        (DataType::U16, size_of::<u16>()),
+        (DataType::F64, size_of::<f64>()),
+        (DataType::Pointer, size_of::<usize>()),
+        (DataType::Vec3, size_of::<glam::Vec3>()),
+        (DataType::DVec4, size_of::<glam::DVec4>()),
+        (DataType::Mat4, size_of::<Mat4>()),
+        (DataType::Rgb, size_of::<[u8; 3]>()),
+        (DataType::Color32, size_of::<[f32; 4]>()),
+        (parse_data_type("u32[16]").unwrap(), size_of::<[u32; 16]>()),
+        (parse_data_type("fixed:16.16").unwrap(), size_of::<i32>()),
+        (parse_data_type("pstring:u16").unwrap(), size_of::<u16>()),
+    ];
+
+    for (data_type, size) in sizes {
+        assert_eq!(data_type.size(), size, "{data_type}");
+    }
+}
+
+#[test]
+fn test_out_of_bounds_read() -> Result<(), MemoryError> {
+    let memory = Memory::new(pid())?;
+    let regions = memory.memory_regions();
+    let (last, _) = regions
+        .windows(2)
+        .map(|regions| (&regions[0], &regions[1]))
+        .find(|(region, next)| region.permissions.read && region.end != next.start)
+        .unwrap();
+
+    let address = last.end - 4;
+    assert!(DataType::U32.read(&memory, address).is_ok());
+    assert!(matches!(
+        DataType::U64.read(&memory, address),
+        Err(MemoryError::OutOfBounds(a, 8)) if a == address
+    ));
+    Ok(())
+}
+
+#[test]
+fn test_strict_partial_reads() -> Result<(), MemoryError> {
+    let page_size = unsafe { libc::sysconf(libc::_SC_PAGESIZE) } as usize;
+    let pages = unsafe {
+        libc::mmap(
+            std::ptr::null_mut(),
+            page_size * 2,
+            libc::PROT_READ | libc::PROT_WRITE,
+            libc::MAP_PRIVATE | libc::MAP_ANONYMOUS,
+            -1,
+            0,
+        )
+    };
+    assert_ne!(pages, libc::MAP_FAILED);
+    let guard = pages as usize + page_size;
+    assert_eq!(
+        unsafe { libc::mprotect(guard as *mut libc::c_void, page_size, libc::PROT_NONE) },
+        0
+    );
+
+    let address = guard - 8;
+    assert_eq!(
+        Memory::new(pid())?.read_bytes_partial(address, 16)?.len(),
+        8
+    );
+    let options = MemoryOptions {
+        strict: true,
+        ..MemoryOptions::default()
+    };
+    let result = Memory::with_options(pid(), options)?.read_bytes_partial(address, 16);
+    unsafe { libc::munmap(pages, page_size * 2) };
+    assert!(matches!(result, Err(MemoryError::PartialRead(a, 8, 16)) if a == address));
+    Ok(())
+}
+
+#[test]
+fn test_string_across_regions() -> Result<(), MemoryError> {
+    let page_size = unsafe { libc::sysconf(libc::_SC_PAGESIZE) } as usize;
+    let pages = unsafe {
+        libc::mmap(
+            std::ptr::null_mut(),
+            page_size * 4,
+            libc::PROT_READ | libc::PROT_WRITE,
+            libc::MAP_PRIVATE | libc::MAP_ANONYMOUS,
+            -1,
+            0,
+        )
+    };
+    assert_ne!(pages, libc::MAP_FAILED);
+    let second = pages as usize + page_size;
+    let third = second + page_size;
+    let guard = third + page_size;
+
+    let utf16: Vec<u8> = "wide\0".encode_utf16().flat_map(u16::to_le_bytes).collect();
+    unsafe {
+        std::ptr::copy_nonoverlapping(c"name".as_ptr().cast(), (second - 2) as *mut u8, 5);
+        std::ptr::copy_nonoverlapping(utf16.as_ptr(), (third - 4) as *mut u8, utf16.len());
+        std::ptr::copy_nonoverlapping(b"tail".as_ptr(), (guard - 4) as *mut u8, 4);
+        // one mapping, split into rw-p, r--p, rw-p and ---p regions
+        libc::mprotect(second as *mut libc::c_void, page_size, libc::PROT_READ);
+        libc::mprotect(guard as *mut libc::c_void, page_size, libc::PROT_NONE);
+    }
+
+    let memory = Memory::new(pid())?;
+    let results = (
+        memory.read_cstring(second - 2, None),
+        memory.read_wstring(third - 4, None),
+        memory.read_cstring(guard - 4, None),
+    );
+    unsafe { libc::munmap(pages, page_size * 4) };
+
+    assert_eq!(results.0?, "name");
+    assert_eq!(results.1?, "wide");
+    assert_eq!(results.2?, "tail…");
     Ok(())
 }
 
@@ -147,3 +380,1879 @@ fn test_read_bytes() -> Result<(), MemoryError> {
 
     Ok(())
 }
+
+#[test]
+fn test_read_many() -> Result<(), MemoryError> {
+    let memory = Memory::new(pid())?;
+    let values: [u32; 4] = [0x11, 0x2222, 0x33_3333, 0x4444_4444];
+    let addresses: Vec<usize> = values.iter().rev().map(address).collect();
+    let read: Vec<u32> = memory.read_many(&addresses)?;
+
+    assert!(read.iter().eq(values.iter().rev()));
+
+    Ok(())
+}
+
+#[test]
+fn test_read_struct() -> Result<(), MemoryError> {
+    #[repr(C)]
+    #[derive(Clone, Copy, Debug, PartialEq, bytemuck::Pod, bytemuck::Zeroable)]
+    struct Entity {
+        id: u64,
+        health: i32,
+        flags: u16,
+        team: u8,
+        alive: u8,
+        position: [f32; 3],
+        _padding: u32,
+    }
+
+    let memory = Memory::new(pid())?;
+    let entity = Entity {
+        id: 0xDEAD_BEEF_CAFE,
+        health: -25,
+        flags: 0b1010,
+        team: 3,
+        alive: 1,
+        position: [1.5, -2.0, 300.25],
+        _padding: 0,
+    };
+
+    assert_eq!(memory.read_struct::<Entity>(address(&entity))?, entity);
+
+    Ok(())
+}
+
+#[test]
+fn test_read_bytes_at_multiple() -> Result<(), MemoryError> {
+    let memory = Memory::new(pid())?;
+    let first: [u8; 3] = [1, 2, 3];
+    let second: [u8; 5] = [4, 5, 6, 7, 8];
+    let buffers = memory.read_bytes_at_multiple(&[
+        (address(&second), second.len()),
+        (address(&first), first.len()),
+    ])?;
+
+    assert!(buffers == [second.to_vec(), first.to_vec()]);
+
+    let unmapped = memory.read_bytes_at_multiple(&[(address(&first), first.len()), (0x10, 4)]);
+    assert!(matches!(
+        unmapped,
+        Err(MemoryError::PartialRead(0x10, 0, 4))
+    ));
+
+    Ok(())
+}
+
+#[test]
+fn test_pointer_array_read() -> Result<(), MemoryError> {
+    let first: u32 = 1;
+    let second: u32 = 2;
+    let pointers: [usize; 3] = [address(&first), address(&second), 0];
+
+    assert_read(
+        address(&pointers),
+        DataType::PointerArray,
+        Value::PointerArray(pointers[..2].to_vec()),
+    )
+}
+
+#[test]
+fn test_resolve_all_overlapping() -> Result<(), MemoryError> {
+    static MARKER: [u8; 6] = [0x9B, 0x9B, 0x9B, 0x5A, 0xC3, 0x7E];
+
+    let memory = Memory::new(pid())?;
+    let marker = address(&MARKER);
+
+    let signature = IdaSignature::new(
+        vec![
+            Some(0x9B),
+            Some(0x9B),
+            Some(0x9B),
+            None,
+            Some(0xC3),
+            Some(0x7E),
+        ],
+        None,
+    );
+    let resolved = AddressLocator::Pattern(signature).resolve_all(&memory)?;
+    assert!(
+        resolved
+            .iter()
+            .any(|(match_address, address)| *match_address == marker
+                && address.as_ref().is_ok_and(|&address| address == marker))
+    );
+
+    let overlapping = IdaSignature::new(vec![Some(0x9B), Some(0x9B)], None);
+    let matches = memory.scan_signature_all(&overlapping)?;
+    assert!(matches.contains(&marker));
+    assert!(matches.contains(&(marker + 1)));
+
+    Ok(())
+}
+
+#[test]
+fn test_mask_signatures() {
+    let pattern = |s| match parse_address_locator(s) {
+        Ok(AddressLocator::Pattern(signature)) => signature.pattern().to_vec(),
+        locator => panic!("{s} parsed as {locator:?}"),
+    };
+    let expected = [Some(0x48), Some(0x8B), None, Some(0x05), None];
+
+    assert_eq!(pattern("48 8B ? 05 ??"), expected);
+    assert_eq!(pattern(r#""\x48\x8B\x00\x05\x00" "xx?x?""#), expected);
+    assert_eq!(pattern(r"code:\x48\x8B\x00\x05\x00 xx?x?"), expected);
+    assert_eq!(
+        parse_address_locator(r"code:\x48\x8B\x00\x05\x00 xx?x?")
+            .unwrap()
+            .to_string(),
+        "48 8B ?? 05 ??"
+    );
+
+    assert!(parse_address_locator(r"code:\x48\x8B xxx").is_err());
+    assert!(parse_address_locator(r"code:\x48\x8B x!").is_err());
+}
+
+#[test]
+fn test_signature_file() {
+    let path = std::env::temp_dir().join(format!("memutil-test-{}.sig", pid()));
+    let path_str = path.to_str().unwrap();
+
+    std::fs::write(
+        &path,
+        "# player base\n48 8B 05 ? ? ? ?\n  48 85 C0 @3/7\n\n",
+    )
+    .unwrap();
+    let signature = parse_signature_file(path_str).unwrap();
+    assert_eq!(signature.to_string(), "48 8B 05 ?? ?? ?? ?? 48 85 C0 @3/7");
+
+    let locator = parse_address_locator(&format!("sigfile:{path_str} -> 0x10")).unwrap();
+    assert!(matches!(locator, AddressLocator::PointerChain(_, pointers) if pointers == [0x10]));
+
+    std::fs::write(&path, "# nothing but comments\n\n").unwrap();
+    let empty = parse_signature_file(path_str);
+    std::fs::remove_file(&path).unwrap();
+    assert!(empty.unwrap_err().contains("holds no pattern"));
+}
+
+#[test]
+fn test_order_resolved() {
+    let resolved = || {
+        vec![
+            (0x100, Ok(0x3000)),
+            (0x200, Err(MemoryError::InvalidPointer(0))),
+            (0x300, Ok(0x1000)),
+            (0x400, Ok(0x3000)),
+        ]
+    };
+    let match_addresses = |sorted, unique_only| {
+        order_resolved(resolved(), sorted, unique_only)
+            .into_iter()
+            .map(|(match_address, _)| match_address)
+            .collect::<Vec<_>>()
+    };
+
+    assert_eq!(match_addresses(false, false), [0x100, 0x200, 0x300, 0x400]);
+    assert_eq!(match_addresses(true, false), [0x300, 0x100, 0x400, 0x200]);
+    assert_eq!(match_addresses(false, true), [0x100, 0x200, 0x300]);
+    assert_eq!(match_addresses(true, true), [0x300, 0x100, 0x200]);
+}
+
+#[test]
+fn test_signature_matches() {
+    let mut state: u32 = 0x9E37_79B9;
+    let mut data: Vec<u8> = (0..64 * 1024)
+        .map(|_| {
+            state ^= state << 13;
+            state ^= state >> 17;
+            state ^= state << 5;
+            // few distinct bytes, so partial matches are common
+            (state % 4) as u8
+        })
+        .collect();
+    data.extend([7, 7, 7, 7, 7]);
+
+    let naive = |pattern: &[Option<u8>]| -> Vec<usize> {
+        data.windows(pattern.len().max(1))
+            .enumerate()
+            .filter(|(_, window)| {
+                window
+                    .iter()
+                    .zip(pattern)
+                    .all(|(byte, pattern_byte)| pattern_byte.is_none_or(|b| b == *byte))
+            })
+            .map(|(offset, _)| offset)
+            .collect()
+    };
+
+    let patterns: [&[Option<u8>]; 7] = [
+        &[Some(1), Some(2), Some(3), Some(0)],
+        &[Some(1), None, Some(3), Some(0), Some(2)],
+        &[None, None, Some(2), Some(2)],
+        &[Some(3), Some(1), None],
+        &[Some(7), Some(7)],
+        &[None],
+        &[],
+    ];
+    for pattern in patterns {
+        let matches: Vec<usize> = signature_matches(&data, pattern).collect();
+        assert_eq!(matches, naive(pattern), "{pattern:?}");
+    }
+}
+
+#[test]
+fn test_wildcard_displacements() {
+    // mov rax, [rip+0x1234]; call rel32; test eax, eax
+    let bytes = [
+        0x48, 0x8B, 0x05, 0x34, 0x12, 0x00, 0x00, 0xE8, 0x10, 0x20, 0x30, 0x40, 0x85, 0xC0,
+    ];
+    let pattern = wildcard_displacements(&bytes);
+
+    let expected = [
+        Some(0x48),
+        Some(0x8B),
+        Some(0x05),
+        None,
+        None,
+        None,
+        None,
+        Some(0xE8),
+        None,
+        None,
+        None,
+        None,
+        Some(0x85),
+        Some(0xC0),
+    ];
+    assert!(pattern == expected);
+}
+
+#[test]
+fn test_flags_read() -> Result<(), MemoryError> {
+    let flags: u16 = 0b1000_0101;
+    let data_type = DataType::Flags {
+        base: Box::new(DataType::U16),
+        names: vec![(0, "alive".to_string()), (2, "flying".to_string())],
+    };
+    let expected = Value::Flags(
+        flags as u64,
+        vec![
+            "alive".to_string(),
+            "flying".to_string(),
+            "bit7".to_string(),
+        ],
+    );
+
+    assert_read(address(&flags), data_type, expected)
+}
+
+#[test]
+fn test_glob_matches() {
+    assert!(glob_matches("libQt*.so*", "libQt6Core.so.6.2.1"));
+    assert!(glob_matches("libc.so.?", "libc.so.6"));
+    assert!(glob_matches("*", ""));
+    assert!(glob_matches("*core*", "libcore.so"));
+    assert!(!glob_matches("libQt*.so*", "libGL.so.1"));
+    assert!(!glob_matches("libc.so.?", "libc.so.10"));
+}
+
+#[test]
+fn test_pascal_string_read() -> Result<(), MemoryError> {
+    #[repr(C)]
+    struct PascalString {
+        len: u32,
+        bytes: [u8; 8],
+    }
+    let string = PascalString {
+        len: 5,
+        bytes: *b"hello!!!",
+    };
+    let data_type = DataType::PascalString { len_width: 4 };
+
+    assert_read(
+        address(&string),
+        data_type,
+        Value::String("hello".to_string()),
+    )
+}
+
+#[test]
+fn test_cstring_read() -> Result<(), MemoryError> {
+    let memory = Memory::new(pid())?;
+    let name = *b"player\xFF1\0garbage";
+
+    assert_eq!(
+        memory.read_cstring(address(&name), Some(256))?,
+        "player\u{FFFD}1"
+    );
+    assert_eq!(memory.read_cstring(address(&name), Some(4))?, "play…");
+    assert_read(
+        address(&name),
+        DataType::CString { max_len: Some(3) },
+        Value::CString("pla…".to_string()),
+    )?;
+
+    // without its own length a string is only capped by --max-string-len
+    let mut long = vec![b'a'; 1000];
+    long.push(0);
+    let expected = "a".repeat(1000);
+    assert_read(
+        address(&long[0]),
+        parse_data_type("cstring").unwrap(),
+        Value::CString(expected.clone()),
+    )?;
+    let wide: Vec<u8> = format!("{expected}\0")
+        .encode_utf16()
+        .flat_map(u16::to_le_bytes)
+        .collect();
+    assert_read(
+        address(&wide[0]),
+        parse_data_type("wstring").unwrap(),
+        Value::WString(expected),
+    )?;
+    let options = MemoryOptions {
+        max_string_len: 10,
+        ..MemoryOptions::default()
+    };
+    let capped = Memory::with_options(pid(), options)?;
+    assert_eq!(
+        capped.read_cstring(address(&long[0]), None)?,
+        format!("{}…", "a".repeat(10))
+    );
+    let pointers = [address(&long[0]); 16];
+    assert_eq!(
+        DataType::PointerArray.read(&capped, address(&pointers[0]))?,
+        Value::PointerArray(vec![address(&long[0]); 10])
+    );
+    Ok(())
+}
+
+#[test]
+fn test_wstring_read() -> Result<(), MemoryError> {
+    let memory = Memory::new(pid())?;
+    let utf16 = |s: &str| -> Vec<u8> { s.encode_utf16().flat_map(u16::to_le_bytes).collect() };
+
+    let bmp = utf16("Grüße\0rest");
+    assert_eq!(memory.read_wstring(address(&bmp[0]), Some(64))?, "Grüße");
+
+    let surrogate_pair = utf16("a😀b\0");
+    assert_eq!(
+        memory.read_wstring(address(&surrogate_pair[0]), Some(64))?,
+        "a😀b"
+    );
+
+    let unterminated = utf16("abcdef");
+    assert_read(
+        address(&unterminated[0]),
+        DataType::WString { max_units: Some(3) },
+        Value::WString("abc…".to_string()),
+    )
+}
+
+#[test]
+fn test_max_units() {
+    assert!(matches!(
+        with_max_units(Some(DataType::WString { max_units: None }), Some(8)),
+        Ok(Some(DataType::WString { max_units: Some(8) }))
+    ));
+    assert!(matches!(
+        with_max_units(Some(DataType::U32), None),
+        Ok(Some(DataType::U32))
+    ));
+    assert!(matches!(
+        with_max_units(Some(DataType::CString { max_len: Some(256) }), Some(8)),
+        Err(CommandError::InvalidArguments(_))
+    ));
+    assert!(
+        Args::command()
+            .try_get_matches_from([
+                "memutil",
+                "read",
+                "1",
+                "0x1000",
+                "--hex",
+                "16",
+                "--max-units",
+                "8"
+            ])
+            .is_err()
+    );
+}
+
+#[test]
+fn test_utf8_char_read() -> Result<(), MemoryError> {
+    let bytes = "é€!".as_bytes();
+    assert_read(address(&bytes[0]), DataType::Utf8Char, Value::Char('é', 2))?;
+    assert_read(address(&bytes[2]), DataType::Utf8Char, Value::Char('€', 3))?;
+    assert_read(address(&bytes[5]), DataType::Utf8Char, Value::Char('!', 1))?;
+
+    let invalid = [0xFFu8, 0x41];
+    assert_read(
+        address(&invalid),
+        DataType::Utf8Char,
+        Value::Char(char::REPLACEMENT_CHARACTER, 1),
+    )
+}
+
+#[test]
+fn test_counter_delta() {
+    let delta =
+        |current, previous| Value::Counter(current).counter_delta(&Value::Counter(previous));
+
+    assert_eq!(delta(1500, 1000), Some(500));
+    assert_eq!(delta(5, u64::MAX - 4), Some(10));
+    assert_eq!(Value::U64(5).counter_delta(&Value::U64(1)), None);
+}
+
+#[test]
+fn test_fixed_point_read() -> Result<(), MemoryError> {
+    let signed: i32 = -0x18000;
+    let unsigned: u16 = 0x0180;
+
+    assert_read(
+        address(&signed),
+        DataType::FixedPoint {
+            int_bits: 16,
+            frac_bits: 16,
+            signed: true,
+        },
+        Value::Fixed(-1.5),
+    )?;
+    assert_read(
+        address(&unsigned),
+        DataType::FixedPoint {
+            int_bits: 8,
+            frac_bits: 8,
+            signed: false,
+        },
+        Value::Fixed(1.5),
+    )
+}
+
+#[test]
+fn test_parse_value_round_trip() {
+    let values = [
+        (DataType::U32, Value::U32(0xDEAD)),
+        (DataType::I8, Value::I8(-5)),
+        (DataType::F32, Value::F32(0.1)),
+        (DataType::Pointer, Value::Pointer(0x7FFF1234)),
+        (DataType::Vec3, Value::Vec3(vec3(1.5, -2.0, 0.25))),
+        (DataType::Rgba, Value::Rgba([0x12, 0x34, 0x56, 0x78])),
+        (
+            DataType::PointerArray,
+            Value::PointerArray(vec![0x1000, 0x2000]),
+        ),
+        (DataType::Utf8Char, Value::Char('€', 3)),
+        (DataType::Utf8Char, Value::Char('\n', 1)),
+        (
+            DataType::Array(Box::new(DataType::Vec2), 2),
+            Value::Array(vec![
+                Value::Vec2(vec2(1.0, 2.0)),
+                Value::Vec2(vec2(3.0, 4.0)),
+            ]),
+        ),
+    ];
+
+    for (data_type, value) in values {
+        let parsed = data_type.parse_value(&value.to_string());
+        assert!(parsed == Ok(value));
+    }
+
+    // strings are printed debug-quoted, with escapes
+    let text = "tab\there \"quoted\" back\\slash \u{1} é";
+    let strings = [
+        ("cstring:64", Value::CString(text.to_string())),
+        ("wstring:64", Value::WString(text.to_string())),
+        ("pstring:u8", Value::String(text.to_string())),
+    ];
+    for (name, value) in strings {
+        let data_type = parse_data_type(name).unwrap();
+        assert!(data_type.parse_value(&value.to_string()) == Ok(value));
+    }
+
+    let cstring = parse_data_type("cstring:64").unwrap();
+    assert!(cstring.parse_value(r"C:\dir") == Ok(Value::CString(r"C:\dir".to_string())));
+    assert!(cstring.parse_value(r#""bad \q""#).is_err());
+    assert!(cstring.parse_value(r#""trailing \""#).is_err());
+}
+
+#[test]
+fn test_write_read_back() -> Result<(), MemoryError> {
+    let memory = Memory::new(pid())?;
+    let buf = std::hint::black_box([0u8; 80]);
+    let base = address(&buf);
+
+    memory.write(base, 0xDEAD_BEEFu32)?;
+    assert_eq!(memory.read::<u32>(base)?, 0xDEAD_BEEF);
+
+    let values = [
+        (DataType::I16, "-1234"),
+        (DataType::F64, "-2.5"),
+        (DataType::Vec3, "[1.5, -2, 0.25]"),
+        (
+            DataType::Mat4,
+            "[1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15, 16]",
+        ),
+        (DataType::Utf8Char, "'€'"),
+        (DataType::PascalString { len_width: 2 }, "\"hello\""),
+        (
+            DataType::FixedPoint {
+                int_bits: 16,
+                frac_bits: 16,
+                signed: true,
+            },
+            "-1.75",
+        ),
+    ];
+    for (data_type, value) in values {
+        let value = data_type.parse_value(value).unwrap();
+        let bytes = data_type.encode(&value, memory.options().matrix_order);
+        memory.write_bytes(base + 1, &bytes)?;
+        assert_eq!(data_type.read(&memory, base + 1)?, value);
+    }
+
+    assert!(matches!(
+        memory.write(0x10, 1u8),
+        Err(MemoryError::InvalidPointer(0x10))
+    ));
+
+    Ok(())
+}
+
+#[test]
+fn test_write_guard() -> Result<(), MemoryError> {
+    let memory = Memory::new(pid())?;
+    let data = std::hint::black_box([0u8; 8]);
+    let code = libc::getpid as *const () as usize;
+
+    let guarded = WriteOptions::default();
+    assert!(check_write(&memory, address(&data), 8, &guarded).is_ok());
+    assert!(matches!(
+        check_write(&memory, code, 1, &guarded),
+        Err(CommandError::ExecutableMemory(address, _)) if address == code
+    ));
+    // a write that only runs into code is refused as well
+    let region = memory.find_containing_region(code).unwrap();
+    assert!(check_write(&memory, region.start - 4, 8, &guarded).is_err());
+
+    let allowed = WriteOptions {
+        allow_exec: true,
+        ..WriteOptions::default()
+    };
+    assert!(check_write(&memory, code, 1, &allowed).is_ok());
+    Ok(())
+}
+
+#[test]
+fn test_write_dry_run() -> Result<(), MemoryError> {
+    let memory = Memory::new(pid())?;
+    let data = std::hint::black_box([1u8, 2, 3, 4]);
+
+    let plan = WritePlan::new(
+        &memory,
+        address(&data),
+        vec![1, 9, 3, 9],
+        &WriteOptions::default(),
+    )
+    .unwrap();
+    assert_eq!(plan.old, [1, 2, 3, 4]);
+    assert_eq!(plan.changed(), 2);
+    assert_eq!(std::hint::black_box(data), [1, 2, 3, 4]);
+
+    // a dry run is refused wherever the write would be
+    let code = libc::getpid as *const () as usize;
+    assert!(WritePlan::new(&memory, code, vec![0x90], &WriteOptions::default()).is_err());
+    Ok(())
+}
+
+#[test]
+fn test_replace() -> Result<(), MemoryError> {
+    assert_eq!(parse_bytes("90 90").unwrap().0, [0x90, 0x90]);
+    assert_eq!(parse_bytes("0xEB 0x05").unwrap().0, [0xEB, 0x05]);
+    assert_eq!(parse_bytes("c3").unwrap().0, [0xC3]);
+    assert!(parse_bytes("9").is_err());
+    assert!(parse_bytes("zz").is_err());
+    assert!(parse_bytes("").is_err());
+
+    let memory = Memory::new(pid())?;
+    let data = std::hint::black_box([0u8; 4]);
+    let code = libc::getpid as *const () as usize;
+    let allowed = WriteOptions {
+        allow_exec: true,
+        ..WriteOptions::default()
+    };
+
+    // nops only go into code, and only with --allow-exec
+    assert!(plan_replace(&memory, address(&data), &Replacement::Nop(2), &allowed).is_err());
+    assert!(
+        plan_replace(
+            &memory,
+            code,
+            &Replacement::Nop(2),
+            &WriteOptions::default()
+        )
+        .is_err()
+    );
+    let plan = plan_replace(&memory, code, &Replacement::Nop(2), &allowed).unwrap();
+    assert_eq!(plan.new, [0x90, 0x90]);
+    let bytes = Replacement::Bytes(vec![1, 2]);
+    assert!(plan_replace(&memory, address(&data), &bytes, &WriteOptions::default()).is_ok());
+
+    // patches go past page protections
+    let page_size = unsafe { libc::sysconf(libc::_SC_PAGESIZE) } as usize;
+    let page = unsafe {
+        libc::mmap(
+            std::ptr::null_mut(),
+            page_size,
+            libc::PROT_READ,
+            libc::MAP_PRIVATE | libc::MAP_ANONYMOUS,
+            -1,
+            0,
+        )
+    };
+    assert_ne!(page, libc::MAP_FAILED);
+    let memory = Memory::new(pid())?;
+    let result = memory.patch_bytes(page as usize, &[0xAB, 0xCD]);
+    let patched = unsafe { std::slice::from_raw_parts(page as *const u8, 2).to_vec() };
+    unsafe { libc::munmap(page, page_size) };
+    result?;
+    assert_eq!(patched, [0xAB, 0xCD]);
+    Ok(())
+}
+
+#[test]
+fn test_scan_value() -> Result<(), MemoryError> {
+    let memory = Memory::new(pid())?;
+    // odd offsets, so only an unaligned scan finds them
+    let mut buf = Box::new([0u8; 32]);
+    buf[3..11].copy_from_slice(&0x5CA7_7E12_D0E5_F00Du64.to_ne_bytes());
+    buf[17..21].copy_from_slice(&1234.5678f32.to_ne_bytes());
+    let base = address(&*buf);
+    let region = memory
+        .find_containing_region(base)
+        .unwrap()
+        .pathname
+        .clone();
+
+    let matches = scan_value(
+        &memory,
+        &DataType::U64,
+        &Value::U64(0x5CA7_7E12_D0E5_F00D),
+        Some(&region),
+    );
+    assert!(matches.contains(&(base + 3)));
+
+    let matches = scan_value(&memory, &DataType::F32, &Value::F32(1234.57), Some(&region));
+    assert!(matches.contains(&(base + 17)));
+    let matches = scan_value(&memory, &DataType::F32, &Value::F32(1235.0), Some(&region));
+    assert!(!matches.contains(&(base + 17)));
+
+    // the progress reported adds up to the total known before the scan
+    let ranges = scan_ranges(&memory, Some(&region));
+    let mut scanned = 0;
+    scan_value_in(
+        &memory,
+        &DataType::U64,
+        &Value::U64(0x5CA7_7E12_D0E5_F00D),
+        &ranges,
+        |bytes| scanned += bytes,
+    );
+    assert_eq!(scanned, total_bytes(&ranges));
+
+    // unreadable reservations are neither scanned nor counted
+    let page_size = unsafe { libc::sysconf(libc::_SC_PAGESIZE) } as usize;
+    let reserved = unsafe {
+        libc::mmap(
+            std::ptr::null_mut(),
+            page_size * 4,
+            libc::PROT_NONE,
+            libc::MAP_PRIVATE | libc::MAP_ANONYMOUS,
+            -1,
+            0,
+        )
+    };
+    assert_ne!(reserved, libc::MAP_FAILED);
+    let ranges = scan_ranges(&Memory::new(pid())?, None);
+    unsafe { libc::munmap(reserved, page_size * 4) };
+    assert!(
+        ranges
+            .iter()
+            .all(|range| !(range.start..range.end).contains(&(reserved as usize)))
+    );
+
+    // a value starting in a range is found even when it reaches past its end, one starting
+    // after it is not
+    let limit = memory.find_containing_region(base).unwrap().end;
+    let range = |start, end| ScanRange { start, end, limit };
+    let matches = scan_value_in(
+        &memory,
+        &DataType::U64,
+        &Value::U64(0x5CA7_7E12_D0E5_F00D),
+        &[range(base, base + 4)],
+        |_| {},
+    );
+    assert_eq!(matches, vec![base + 3]);
+    let matches = scan_value_in(
+        &memory,
+        &DataType::U64,
+        &Value::U64(0x5CA7_7E12_D0E5_F00D),
+        &[range(base, base + 3)],
+        |_| {},
+    );
+    assert!(matches.is_empty());
+
+    Ok(())
+}
+
+#[test]
+fn test_resident_sizes() -> Result<(), MemoryError> {
+    let page_size = unsafe { libc::sysconf(libc::_SC_PAGESIZE) } as usize;
+    // shared anonymous mappings never merge with their neighbours, so the region is exactly these
+    // pages
+    let pages = unsafe {
+        libc::mmap(
+            std::ptr::null_mut(),
+            page_size * 8,
+            libc::PROT_READ | libc::PROT_WRITE,
+            libc::MAP_SHARED | libc::MAP_ANONYMOUS,
+            -1,
+            0,
+        )
+    };
+    assert_ne!(pages, libc::MAP_FAILED);
+    for page in [0, 2, 5] {
+        unsafe { (pages as *mut u8).add(page * page_size).write_volatile(1) };
+    }
+
+    let memory = Memory::new(pid())?;
+    let sizes = memory.resident_sizes();
+    unsafe { libc::munmap(pages, page_size * 8) };
+    let index = memory
+        .memory_regions()
+        .iter()
+        .position(|region| region.start == pages as usize)
+        .unwrap();
+    assert_eq!(
+        memory.memory_regions()[index].end - pages as usize,
+        page_size * 8
+    );
+    assert_eq!(sizes?[index], page_size * 3);
+    Ok(())
+}
+
+#[test]
+fn test_array_bases() -> Result<(), MemoryError> {
+    const STRIDE: usize = 12;
+    let memory = Memory::new(pid())?;
+    // the value is a field at offset 4 of elements 0 to 3 and of element 5
+    let mut buf = vec![0u8; STRIDE * 6];
+    for element in [0, 1, 2, 3, 5] {
+        let field = element * STRIDE + 4;
+        buf[field..field + 4].copy_from_slice(&0x600D_F00Du32.to_ne_bytes());
+    }
+    let buf = std::hint::black_box(buf);
+    let field = |element: usize| address(&buf[element * STRIDE + 4]);
+    let matches: Vec<_> = [0, 1, 2, 3, 5].into_iter().map(field).collect();
+
+    let bases = |count| {
+        array_bases(
+            &memory,
+            &DataType::U32,
+            &Value::U32(0x600D_F00D),
+            &matches,
+            STRIDE,
+            count,
+        )
+    };
+    assert_eq!(bases(3), [field(0)]);
+    assert_eq!(bases(4), [field(0)]);
+    assert!(bases(5).is_empty());
+    Ok(())
+}
+
+#[test]
+fn test_soft_dirty() -> Result<(), MemoryError> {
+    let memory = Memory::new(pid())?;
+    if memory.clear_soft_dirty().is_err() {
+        return Ok(());
+    }
+
+    let page_size = unsafe { libc::sysconf(libc::_SC_PAGESIZE) } as usize;
+    let mut buf = vec![0u8; page_size * 4];
+    let base = address(&buf[0]);
+    let start = base.next_multiple_of(page_size);
+    memory.clear_soft_dirty()?;
+    let dirty = start + page_size;
+    std::hint::black_box(&mut buf)[dirty - base] = 1;
+
+    let ranges = memory.soft_dirty_ranges(start, start + page_size * 3)?;
+    assert!(
+        ranges
+            .iter()
+            .any(|&(range_start, range_end)| range_start <= dirty && dirty < range_end)
+    );
+    Ok(())
+}
+
+#[test]
+fn test_strings() -> Result<(), MemoryError> {
+    static MARKER: &str = "memutil strings test marker";
+
+    let memory = Memory::new(pid())?;
+    let marker = MARKER.as_ptr() as usize;
+    let region = memory.find_containing_region(marker).unwrap();
+    let mut found = None;
+    find_strings(
+        &memory,
+        region.start..region.end,
+        8,
+        false,
+        |address, text| {
+            // the run can start earlier when other printable bytes come right before the marker
+            if let Some(offset) = text.find(MARKER)
+                && address + offset == marker
+            {
+                found = Some(address);
+            }
+        },
+    );
+    assert!(found.is_some());
+
+    let wide: Vec<u16> = "wide marker".encode_utf16().collect();
+    let start = address(&wide[0]);
+    let mut strings = Vec::new();
+    find_strings(
+        &memory,
+        start..start + wide.len() * 2,
+        4,
+        true,
+        |address, text| {
+            strings.push((address, text.to_string()));
+        },
+    );
+    assert_eq!(strings, [(start, "wide marker".to_string())]);
+    Ok(())
+}
+
+#[test]
+fn test_error_kinds() {
+    let signature = IdaSignature::new(vec![Some(0x90)], None);
+    assert_eq!(
+        CommandError::Resolve(MemoryError::SignatureNotFound(signature)).kind(),
+        "SignatureNotFound"
+    );
+    assert_eq!(
+        CommandError::Read(MemoryError::InvalidPointer(0x10)).kind(),
+        "InvalidPointer"
+    );
+    assert_eq!(
+        CommandError::NoScanSession("default".to_string(), Some(1)).kind(),
+        "NoScanSession"
+    );
+    assert_eq!(CommandError::Interrupted.kind(), "Interrupted");
+}
+
+#[test]
+fn test_format_bytes() {
+    assert_eq!(format_bytes(512), "512 B");
+    assert_eq!(format_bytes(1536), "1.5 KiB");
+    assert_eq!(format_bytes(3 << 30), "3.0 GiB");
+}
+
+#[test]
+fn test_scan_predicates() {
+    let holds = |predicate: &str, data_type: DataType, previous: Value, current: Value| {
+        ScanPredicate::parse(predicate, &data_type)
+            .unwrap()
+            .holds(&data_type, &previous, &current)
+    };
+
+    assert!(holds(
+        "increased",
+        DataType::I32,
+        Value::I32(-5),
+        Value::I32(3)
+    ));
+    assert!(!holds(
+        "increased",
+        DataType::I32,
+        Value::I32(3),
+        Value::I32(3)
+    ));
+    assert!(holds(
+        "decreased",
+        DataType::F32,
+        Value::F32(1.5),
+        Value::F32(1.25)
+    ));
+    assert!(holds("unchanged", DataType::U8, Value::U8(7), Value::U8(7)));
+    assert!(holds("changed", DataType::U8, Value::U8(7), Value::U8(8)));
+    assert!(holds("=42", DataType::U16, Value::U16(0), Value::U16(42)));
+    assert!(!holds("=42", DataType::U16, Value::U16(42), Value::U16(41)));
+    assert!(holds(
+        "=99.5",
+        DataType::F64,
+        Value::F64(0.0),
+        Value::F64(99.500001)
+    ));
+
+    let vec = || Value::Vec2(vec2(1.0, 2.0));
+    assert!(!holds("increased", DataType::Vec2, vec(), vec()));
+    assert!(holds("unchanged", DataType::Vec2, vec(), vec()));
+
+    assert!(ScanPredicate::parse("bigger", &DataType::U32).is_err());
+    assert!(ScanPredicate::parse("=-1", &DataType::U32).is_err());
+}
+
+#[test]
+fn test_scan_session_refine() {
+    let mut session = ScanSession {
+        pid: 1234,
+        start_time: 5678,
+        data_type: DataType::U32,
+        matches: vec![
+            (0x1000, 10u32.to_ne_bytes().to_vec()),
+            (0x2000, 10u32.to_ne_bytes().to_vec()),
+            (0x3000, 10u32.to_ne_bytes().to_vec()),
+        ],
+    };
+    let loaded = ScanSession::from_json(&session.to_json().to_string()).unwrap();
+    assert_eq!(loaded, session);
+
+    // 0x2000 grew, 0x3000 can't be read anymore
+    let current = |address: usize, _| match address {
+        0x1000 => Some(10u32.to_ne_bytes().to_vec()),
+        0x2000 => Some(11u32.to_ne_bytes().to_vec()),
+        _ => None,
+    };
+    session.refine(&ScanPredicate::Increased, MatrixOrder::Col, current);
+    assert_eq!(
+        session.matches,
+        vec![(0x2000, 11u32.to_ne_bytes().to_vec())]
+    );
+    assert!(
+        ScanSession::from_json(
+            r#"{"pid":1,"start_time":2,"data_type":"u32","matches":[[4096,[1]]]}"#
+        )
+        .is_err()
+    );
+}
+
+#[test]
+fn test_scan_session_storage() {
+    let dir = session_dir().unwrap();
+    let metadata = std::fs::symlink_metadata(&dir).unwrap();
+    assert!(metadata.is_dir());
+    assert_eq!(metadata.mode() & 0o777, 0o700);
+    assert_eq!(metadata.uid(), unsafe { libc::geteuid() });
+
+    let session = ScanSession {
+        pid: pid(),
+        start_time: start_time(pid()).unwrap(),
+        data_type: DataType::U16,
+        matches: vec![(0x1000, vec![1, 2])],
+    };
+    session.save("test-a").unwrap();
+    assert_eq!(ScanSession::load(pid(), "test-a").unwrap(), session);
+
+    // named sessions of the same process don't replace each other
+    let other = ScanSession {
+        data_type: DataType::U8,
+        matches: vec![(0x2000, vec![3])],
+        ..session.clone()
+    };
+    other.save("test-b").unwrap();
+    assert_eq!(ScanSession::load(pid(), "test-a").unwrap(), session);
+    assert_eq!(ScanSession::load(pid(), "test-b").unwrap(), other);
+    assert_eq!(
+        saved_sessions(Some(pid())).unwrap(),
+        [(pid(), "test-a".to_string()), (pid(), "test-b".to_string())]
+    );
+    assert!(matches!(
+        ScanSession::load(pid(), "test-c"),
+        Err(CommandError::NoScanSession(..))
+    ));
+
+    // a session left behind by an earlier process with the same pid is not picked up
+    ScanSession {
+        start_time: session.start_time + 1,
+        ..session
+    }
+    .save("test-a")
+    .unwrap();
+    assert!(matches!(
+        ScanSession::load(pid(), "test-a"),
+        Err(CommandError::InvalidScanSession(..))
+    ));
+
+    ScanSession::remove(pid(), "test-a").unwrap();
+    ScanSession::remove(pid(), "test-b").unwrap();
+    assert!(saved_sessions(Some(pid())).unwrap().is_empty());
+    assert!(!dir.join(pid().to_string()).exists());
+
+    assert!(parse_scan_name("health_2").is_ok());
+    assert!(parse_scan_name("../health").is_err());
+    assert!(parse_scan_name("").is_err());
+}
+
+#[test]
+fn test_data_type_display_round_trip() {
+    for name in [
+        "u32",
+        "dvec3",
+        "ptrarray",
+        "fixed:16.16",
+        "ufixed:8.8",
+        "cstring:64",
+        "wstring:32",
+        "pstring:u16",
+        "flags:u8{0=alive,3=burning}",
+        "u32[16]",
+        "vec3[2]",
+    ] {
+        assert_eq!(parse_data_type(name).unwrap().to_string(), name);
+    }
+}
+
+#[test]
+fn test_watch_aggregate() {
+    let mut aggregate = Aggregate::default();
+    for value in [3.0, -1.0, 4.0] {
+        aggregate.add(value);
+    }
+    assert_eq!(
+        (aggregate.count, aggregate.min, aggregate.max),
+        (3, -1.0, 4.0)
+    );
+    assert_eq!(aggregate.mean(), 2.0);
+    // an aggregating watch ended by ctrl-c prints its summary, then exits like one
+    assert_eq!(CommandError::Interrupted.exit_code(), 130);
+}
+
+#[test]
+fn test_watch_jitter() {
+    let interval = Duration::from_millis(100);
+    let jitter = Duration::from_millis(20);
+    let mut rng = XorShift::new(0x1234_5678);
+    let samples: Vec<_> = (0..10_000).map(|_| rng.jitter(interval, jitter)).collect();
+
+    assert!(
+        samples
+            .iter()
+            .all(|&sample| interval - jitter <= sample && sample <= interval + jitter)
+    );
+    // spread over the whole range, centered on the interval
+    let min = samples.iter().min().unwrap();
+    let max = samples.iter().max().unwrap();
+    assert!(*min < Duration::from_millis(81) && *max > Duration::from_millis(119));
+    let mean = samples.iter().sum::<Duration>() / samples.len() as u32;
+    assert!(
+        mean.abs_diff(interval) < Duration::from_millis(1),
+        "{mean:?}"
+    );
+
+    // intervals never go below zero, and no jitter leaves them alone
+    let short = Duration::from_millis(5);
+    assert!((0..1000).all(|_| rng.jitter(short, jitter) <= short + jitter));
+    assert_eq!(rng.jitter(interval, Duration::ZERO), interval);
+}
+
+#[test]
+fn test_watch_snapshot() {
+    let path = std::env::temp_dir().join(format!("memutil-test-{}.snapshot", pid()));
+    let read = || -> serde_json::Value {
+        serde_json::from_str(&std::fs::read_to_string(&path).unwrap()).unwrap()
+    };
+
+    write_snapshot(&path, 1, &DataType::F32, Some((0x1000, Value::F32(1.5)))).unwrap();
+    let snapshot = read();
+    assert_eq!(snapshot["address"], "0x1000");
+    assert_eq!(snapshot["type"], "f32");
+    assert_eq!(snapshot["value"], 1.5);
+
+    write_snapshot(&path, 1, &DataType::F32, None).unwrap();
+    assert!(read().is_null());
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn test_watch_alerts() {
+    let watch_options = WatchOptions {
+        alert_above: Some("10".to_string()),
+        alert_below: Some("-5".to_string()),
+        ..WatchOptions::default()
+    };
+    let mut alerts = Alerts::new(&DataType::I32, &watch_options).unwrap();
+
+    // each bound alerts once when crossed, and again only after samples came back
+    let raised: Vec<usize> = [0, 11, 12, 10, 15, -6, -7, 3, -5, -9]
+        .into_iter()
+        .map(|value| alerts.check(0x1000, &Value::I32(value)).len())
+        .collect();
+    assert_eq!(raised, [0, 1, 0, 0, 1, 1, 0, 0, 0, 1]);
+    assert_eq!(
+        alerts.check(0x1000, &Value::I32(20)),
+        ["0x1000 = 20i32 is above 10i32"]
+    );
+
+    let unordered = WatchOptions {
+        alert_above: Some("[1, 2]".to_string()),
+        ..WatchOptions::default()
+    };
+    assert!(Alerts::new(&DataType::Vec2, &unordered).is_err());
+    assert!(Alerts::new(&DataType::I32, &unordered).is_err());
+}
+
+#[test]
+fn test_value_changed_from() {
+    assert!(!Value::F32(1.0).changed_from(&Value::F32(1.0 + 1e-7), 1e-6));
+    assert!(Value::F32(1.0).changed_from(&Value::F32(1.1), 1e-6));
+    assert!(Value::F32(f32::NAN).changed_from(&Value::F32(1.0), 1e-6));
+    assert!(!Value::F32(f32::NAN).changed_from(&Value::F32(f32::NAN), 1e-6));
+    assert!(Value::Vec3(vec3(1.0, 2.0, 3.0)).changed_from(&Value::Vec3(vec3(1.0, 2.5, 3.0)), 0.1));
+    assert!(
+        !Value::Vec3(vec3(1.0, 2.0, 3.0)).changed_from(&Value::Vec3(vec3(1.0, 2.05, 3.0)), 0.1)
+    );
+    assert!(Value::U32(1).changed_from(&Value::U32(2), 10.0));
+    assert!(!Value::CString("hp".into()).changed_from(&Value::CString("hp".into()), 0.0));
+}
+
+#[test]
+fn test_value_ordering() {
+    use std::cmp::Ordering;
+
+    assert!(Value::U32(2) > Value::U32(1));
+    assert!(Value::I8(-1) < Value::I8(1));
+    assert!(Value::Pointer(0x2000) > Value::Pointer(0x1000));
+
+    // the same magnitude in different variants is neither equal nor ordered
+    assert_ne!(Value::U8(5), Value::I8(5));
+    assert_eq!(Value::U8(5).partial_cmp(&Value::I8(5)), None);
+    assert_eq!(Value::U64(u64::MAX).partial_cmp(&Value::I64(-1)), None);
+
+    assert_eq!(Value::F32(f32::NAN).partial_cmp(&Value::F32(1.0)), None);
+    assert_ne!(Value::F64(f64::NAN), Value::F64(f64::NAN));
+    assert_eq!(Value::F32(1.0).partial_cmp(&Value::F32(f32::NAN)), None);
+
+    let vec = || Value::Vec3(vec3(1.0, 2.0, 3.0));
+    assert_eq!(vec().partial_cmp(&vec()), Some(Ordering::Equal));
+    assert_eq!(vec().partial_cmp(&Value::Rgb([1, 2, 3])), None);
+}
+
+#[test]
+fn test_json_output() {
+    let json = |value: Value| serde_json::to_string(&value).unwrap();
+    assert_eq!(json(Value::I32(-5)), "-5");
+    assert_eq!(json(Value::F64(1.5)), "1.5");
+    assert_eq!(json(Value::Pointer(0xdead)), r#""0xdead""#);
+    assert_eq!(json(Value::Vec3(vec3(1.0, 2.0, 3.0))), "[1.0,2.0,3.0]");
+    assert_eq!(json(Value::Rgb([255, 0, 16])), r##""#ff0010""##);
+    assert_eq!(
+        json(Value::Flags(9, vec!["alive".into(), "burning".into()])),
+        r#"{"raw":9,"names":["alive","burning"]}"#
+    );
+    assert_eq!(json(Value::CString("hi".into())), r#""hi""#);
+
+    let region = MemoryRegion {
+        start: 0x1000,
+        end: 0x2000,
+        permissions: Permissions::parse("r-xp"),
+        pathname: "/usr/lib/libc.so.6".to_string(),
+    };
+    assert_eq!(
+        serde_json::to_string(&region).unwrap(),
+        r#"{"start":"0x1000","end":"0x2000","permissions":"r-xp","pathname":"/usr/lib/libc.so.6"}"#
+    );
+}
+
+#[test]
+fn test_permissions() {
+    for field in ["r-xp", "rw-p", "r--s", "---p"] {
+        assert_eq!(Permissions::parse(field).to_string(), field);
+    }
+
+    let writable = Permissions {
+        write: true,
+        ..Default::default()
+    };
+    assert!(Permissions::parse("rw-p").contains(writable));
+    assert!(!Permissions::parse("r-xp").contains(writable));
+    assert!(Permissions::parse("r-xp").contains(Permissions::default()));
+}
+
+#[test]
+fn test_unaligned_reads() -> Result<(), MemoryError> {
+    let mat = Mat4::from_cols_array(&std::array::from_fn(|i| i as f32 * 1.5));
+    let vec = vec4(1.0, -2.0, 3.5, 4.25);
+
+    // odd offsets into a byte buffer, so the remote values are never aligned
+    let mut buf = [0u8; 1 + size_of::<Mat4>() + 3 + size_of::<Vec4>()];
+    let vec_offset = 1 + size_of::<Mat4>() + 3;
+    buf[1..vec_offset - 3].copy_from_slice(bytemuck::bytes_of(&mat));
+    buf[vec_offset..].copy_from_slice(bytemuck::bytes_of(&vec));
+    let base = address(&buf);
+
+    assert_read(base + 1, DataType::Mat4, Value::Mat4(mat))?;
+    assert_read(base + vec_offset, DataType::Vec4, Value::Vec4(vec))
+}
+
+#[test]
+fn test_row_major_matrix_read() -> Result<(), MemoryError> {
+    let matrix = Mat4::from_cols_array(&std::array::from_fn(|i| i as f32));
+    let options = MemoryOptions {
+        matrix_order: MatrixOrder::Row,
+        ..MemoryOptions::default()
+    };
+    let memory = Memory::with_options(pid(), options)?;
+
+    let value = DataType::Mat4.read(&memory, address(&matrix))?;
+    assert!(value == Value::Mat4(matrix.transpose()));
+    Ok(())
+}
+
+#[test]
+fn test_scan_data() -> Result<(), MemoryError> {
+    // built at runtime, so the bytes only exist on the heap and not in the mapped binary
+    let marker: Vec<u8> = (0..16u8).map(|i| i.wrapping_mul(37) ^ 0xA5).collect();
+    let signature = IdaSignature::new(marker.iter().copied().map(Some).collect(), None);
+    let marker = address(&marker[0]);
+
+    let memory = Memory::new(pid())?;
+    assert!(!memory.scan_signature_all(&signature)?.contains(&marker));
+
+    let options = MemoryOptions {
+        scan_data: true,
+        ..MemoryOptions::default()
+    };
+    let memory = Memory::with_options(pid(), options)?;
+    assert!(memory.scan_signature_all(&signature)?.contains(&marker));
+    Ok(())
+}
+
+#[test]
+fn test_assume_static_caches_matches() -> Result<(), MemoryError> {
+    let mut marker: Vec<u8> = (0..16u8).map(|i| i.wrapping_mul(53) ^ 0x3C).collect();
+    let signature = IdaSignature::new(marker.iter().copied().map(Some).collect(), None);
+    let marker_address = address(&marker[0]);
+
+    let options = MemoryOptions {
+        scan_data: true,
+        assume_static: true,
+        ..MemoryOptions::default()
+    };
+    let memory = Memory::with_options(pid(), options)?;
+    assert_eq!(memory.scan_signature(&signature)?, Some(marker_address));
+
+    // the cached match is returned even though the bytes are gone
+    marker.fill(0);
+    std::hint::black_box(&marker);
+    assert_eq!(memory.scan_signature(&signature)?, Some(marker_address));
+
+    memory.clear_signature_cache();
+    assert_eq!(memory.scan_signature(&signature)?, None);
+    Ok(())
+}
+
+#[test]
+fn test_longest_matching_prefix() -> Result<(), MemoryError> {
+    let marker: Vec<u8> = (0..16u8).map(|i| i.wrapping_mul(89) ^ 0xC3).collect();
+    let options = MemoryOptions {
+        scan_data: true,
+        ..MemoryOptions::default()
+    };
+    let memory = Memory::with_options(pid(), options)?;
+
+    // the first 10 bytes are the marker, the 11th differs
+    let mut pattern: Vec<_> = marker.iter().copied().map(Some).collect();
+    pattern[10] = Some(!marker[10]);
+    let signature = IdaSignature::new(pattern, None);
+    assert_eq!(longest_matching_prefix(&memory, &signature)?, Some(10));
+    std::hint::black_box(&marker);
+
+    // a pattern this short has no prefix worth reporting
+    let short = IdaSignature::new(marker[..3].iter().copied().map(Some).collect(), None);
+    assert_eq!(longest_matching_prefix(&memory, &short)?, None);
+    Ok(())
+}
+
+#[test]
+fn test_resolve_cached() -> Result<(), MemoryError> {
+    let mut marker: Vec<u8> = (0..16u8).map(|i| i.wrapping_mul(71) ^ 0x5A).collect();
+    let locator = AddressLocator::Pattern(IdaSignature::new(
+        marker.iter().copied().map(Some).collect(),
+        None,
+    ));
+    let marker_address = address(&marker[0]);
+
+    let options = MemoryOptions {
+        scan_data: true,
+        ..MemoryOptions::default()
+    };
+    let memory = Memory::with_options(pid(), options)?;
+    let mut cache = ResolveCache::default();
+    assert_eq!(locator.resolve_cached(&memory, &mut cache)?, marker_address);
+
+    // with the bytes gone only a scan would fail, so the second resolve didn't scan
+    marker.fill(0);
+    std::hint::black_box(&marker);
+    assert_eq!(locator.resolve_cached(&memory, &mut cache)?, marker_address);
+    assert!(matches!(
+        locator.resolve(&memory),
+        Err(MemoryError::SignatureNotFound(_))
+    ));
+
+    cache.clear();
+    assert!(matches!(
+        locator.resolve_cached(&memory, &mut cache),
+        Err(MemoryError::SignatureNotFound(_))
+    ));
+    Ok(())
+}
+
+#[test]
+fn test_function_locator() -> Result<(), MemoryError> {
+    let memory = Memory::new(pid())?;
+    let getpid = libc::getpid as *const () as usize;
+
+    let locator = parse_address_locator("func:getpid+0x4").unwrap();
+    assert_eq!(locator.resolve(&memory)?, getpid + 4);
+
+    for (s, function, offset) in [
+        ("func:operator+", "operator+", 0),
+        (
+            "func:Vec::operator+(Vec const&)+0x10",
+            "Vec::operator+(Vec const&)",
+            0x10,
+        ),
+    ] {
+        let locator = parse_address_locator(s).unwrap();
+        assert!(
+            matches!(&locator, AddressLocator::Function(name, o, _) if name == function && *o == offset),
+            "{locator:?}"
+        );
+    }
+    Ok(())
+}
+
+#[test]
+fn test_symbol_for_address() -> Result<(), MemoryError> {
+    let memory = Memory::new(pid())?;
+    let getpid = libc::getpid as *const () as usize;
+
+    for _ in 0..2 {
+        let symbol = memory.symbol_for_address(getpid + 2)?.unwrap();
+        assert_eq!(symbol.address, getpid);
+        assert!(symbol.name.contains("getpid"), "{}", symbol.name);
+        assert!(symbol.relative(getpid + 2).ends_with("+0x2"));
+    }
+    assert!(memory.symbol_for_address(0x10)?.is_none());
+
+    // the module locator leads back to the same address
+    let locator = AddressLocator::relative_to_region(&memory, getpid).unwrap();
+    assert!(
+        matches!(locator, AddressLocator::ModuleOffset(..)),
+        "{locator}"
+    );
+    assert_eq!(locator.resolve(&memory)?, getpid);
+    assert!(AddressLocator::relative_to_region(&memory, 0x10).is_none());
+
+    assert_eq!(
+        AddressLocator::rebase_to_symbol(&memory, getpid + 2)?,
+        getpid
+    );
+    assert!(matches!(
+        AddressLocator::rebase_to_symbol(&memory, 0x10),
+        Err(MemoryError::NoSymbol(0x10))
+    ));
+    Ok(())
+}
+
+#[test]
+fn test_module_symbols_parsed_once() -> Result<(), MemoryError> {
+    let memory = Memory::new(pid())?;
+    let getpid = libc::getpid as *const () as usize;
+    let libc_path = memory
+        .find_containing_region(getpid)
+        .unwrap()
+        .pathname
+        .clone();
+
+    let link = std::env::temp_dir().join(format!("memutil-test-{}.so", pid()));
+    std::os::unix::fs::symlink(&libc_path, &link)?;
+    let linked = memory.module_symbols(link.to_str().unwrap());
+    std::fs::remove_file(&link)?;
+
+    let symbols = memory.module_symbols(&libc_path)?.unwrap();
+    assert!(std::rc::Rc::ptr_eq(&symbols, &linked?.unwrap()));
+    assert!(
+        symbols
+            .exported
+            .as_ref()
+            .unwrap()
+            .iter()
+            .any(|symbol| symbol.name == "getpid")
+    );
+    Ok(())
+}
+
+#[test]
+fn test_demangle() {
+    assert_eq!(
+        demangle("_ZN4game6EntityC1Ev").as_deref(),
+        Some("game::Entity::Entity()")
+    );
+    assert_eq!(
+        demangle("_ZN4core3fmt5write17h0123456789abcdefE").as_deref(),
+        Some("core::fmt::write")
+    );
+    assert_eq!(demangle("getpid"), None);
+
+    assert!(symbol_matches(
+        "_ZN4game6EntityC1Ev",
+        "game::Entity::Entity"
+    ));
+    assert!(symbol_matches(
+        "_ZN4game6EntityC1Ev",
+        "game::Entity::Entity()"
+    ));
+    assert!(symbol_matches("_ZN4game6EntityC1Ev", "_ZN4game6EntityC1Ev"));
+    assert!(!symbol_matches("_ZN4game6EntityC1Ev", "Entity"));
+    assert!(symbol_matches("getpid", "getpid"));
+
+    // parentheses in the name itself don't end it
+    assert!(symbol_matches(
+        "_ZNKSt8functionIFviEEclEi",
+        "std::function<void (int)>::operator()"
+    ));
+    assert!(!symbol_matches(
+        "_ZNKSt8functionIFviEEclEi",
+        "std::function<void "
+    ));
+    assert!(symbol_matches(
+        "_ZNK4game6Entity6healthEv",
+        "game::Entity::health"
+    ));
+}
+
+#[test]
+fn test_module_offset_locator() -> Result<(), MemoryError> {
+    static VALUE: u32 = 0x5EED;
+    static POINTER: &u32 = &VALUE;
+
+    let memory = Memory::new(pid())?;
+    let exe = std::env::current_exe().unwrap();
+    let exe_name = exe.file_name().unwrap().to_str().unwrap();
+    let base = memory
+        .memory_regions()
+        .iter()
+        .find(|region| region.pathname.ends_with(exe_name))
+        .unwrap()
+        .start;
+
+    let offset = address(&POINTER) - base;
+    let locator = parse_address_locator(&format!("{exe_name}+0x{offset:X}")).unwrap();
+    assert_eq!(locator.resolve(&memory)?, address(&POINTER));
+
+    let chain = parse_address_locator(&format!("{exe_name}+0x{offset:X} -> 0x0 -> 0x0")).unwrap();
+    assert_eq!(chain.resolve(&memory)?, address(&VALUE));
+
+    let missing = parse_address_locator("libmissing.so+0x10").unwrap();
+    assert!(matches!(
+        missing.resolve(&memory),
+        Err(MemoryError::ModuleNotFound(_))
+    ));
+    Ok(())
+}
+
+#[test]
+fn test_parse_maps_keeps_permissions_apart() -> Result<(), MemoryError> {
+    let maps = "\
+555555554000-555555556000 r--p 00000000 08:01 1234    /usr/bin/game
+555555556000-55555555a000 r-xp 00002000 08:01 1234    /usr/bin/game
+55555555a000-55555555c000 r--p 00006000 08:01 1234    /usr/bin/game
+55555555c000-55555555d000 rw-p 00007000 08:01 1234    /usr/bin/game
+55555555d000-55555557e000 rw-p 00000000 00:00 0       [heap]
+55555557e000-55555557f000 rw-p 00000000 00:00 0       [heap]
+";
+    let regions = parse_maps(maps)?;
+
+    let game: Vec<_> = regions
+        .iter()
+        .filter(|region| region.pathname == "/usr/bin/game")
+        .map(|region| (region.start, region.end, region.permissions.to_string()))
+        .collect();
+    assert_eq!(
+        game,
+        [
+            (0x555555554000, 0x555555556000, "r--p".to_string()),
+            (0x555555556000, 0x55555555a000, "r-xp".to_string()),
+            (0x55555555a000, 0x55555555c000, "r--p".to_string()),
+            (0x55555555c000, 0x55555555d000, "rw-p".to_string()),
+        ]
+    );
+
+    // touching ranges with the same permissions are still merged
+    let heap: Vec<_> = regions
+        .iter()
+        .filter(|region| region.pathname == "[heap]")
+        .collect();
+    assert_eq!(heap.len(), 1);
+    assert_eq!(
+        (heap[0].start, heap[0].end),
+        (0x55555555d000, 0x55555557f000)
+    );
+    Ok(())
+}
+
+#[test]
+fn test_parse_dump_target() {
+    assert!(matches!(
+        parse_dump_target("#3"),
+        Ok(DumpTarget::RegionIndex(3))
+    ));
+    assert!(matches!(
+        parse_dump_target("libclient.so"),
+        Ok(DumpTarget::Address(AddressLocator::ModuleOffset(name, 0, _))) if name == "libclient.so"
+    ));
+    assert!(matches!(
+        parse_dump_target("[heap]+0x10"),
+        Ok(DumpTarget::Address(AddressLocator::Region(_, 0x10)))
+    ));
+    assert!(parse_dump_target("#x").is_err());
+}
+
+#[test]
+fn test_disassemble() {
+    let bytes = [
+        0x48, 0x8B, 0x05, 0x10, 0x00, 0x00, 0x00, // mov rax, [rip+0x10]
+        0xE8, 0x00, 0x01, 0x00, 0x00, // call rel32
+        0xC3, // ret
+        0x48, 0x8B, // cut off
+    ];
+    let instructions = disassemble(Architecture::X86_64, &bytes, 0x1000, 8);
+
+    let listing = instructions
+        .iter()
+        .map(|instruction| format!("0x{:X} {}", instruction.address, instruction.text))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        listing,
+        [
+            "0x1000 mov rax, [0x1017]",
+            "0x1007 call 0x110C",
+            "0x100C ret"
+        ]
+    );
+    assert_eq!(instructions[0].target, Some(0x1017));
+    assert_eq!(instructions[1].target, Some(0x110C));
+    assert_eq!(instructions[2].target, None);
+    assert_eq!(instructions[1].bytes, bytes[7..12]);
+
+    assert_eq!(
+        Architecture::from_machine(elf::abi::EM_X86_64).unwrap(),
+        Architecture::X86_64
+    );
+    assert!(Architecture::from_machine(elf::abi::EM_AARCH64).is_err());
+}
+
+#[test]
+fn test_diff_regions() {
+    let region = |start, end, pathname: &str| MemoryRegion {
+        start,
+        end,
+        permissions: Permissions::default(),
+        pathname: pathname.to_string(),
+    };
+    let old = [
+        region(0x1000, 0x2000, "/usr/bin/game"),
+        region(0x5000, 0x6000, "[heap]"),
+        region(0x9000, 0xA000, "/usr/lib/libold.so"),
+    ];
+    let new = [
+        region(0x1000, 0x2000, "/usr/bin/game"),
+        region(0x5000, 0x8000, "[heap]"),
+        region(0xB000, 0xC000, "/usr/lib/libnew.so"),
+    ];
+
+    let diff = diff_regions(&old, &new);
+    assert_eq!(diff.added.len(), 1);
+    assert_eq!(diff.added[0].pathname, "/usr/lib/libnew.so");
+    assert_eq!(diff.removed.len(), 1);
+    assert_eq!(diff.removed[0].pathname, "/usr/lib/libold.so");
+    assert_eq!(diff.changed.len(), 1);
+    assert_eq!(
+        (diff.changed[0].0.end, diff.changed[0].1.end),
+        (0x6000, 0x8000)
+    );
+}
+
+#[test]
+fn test_gap_tolerance() -> Result<(), MemoryError> {
+    let memory = Memory::new(pid())?;
+    let (before, after) = memory
+        .memory_regions()
+        .windows(2)
+        .map(|regions| (regions[0].end, regions[1].start))
+        .find(|(end, start)| start > end)
+        .expect("no gap between regions");
+    let gap = after - before;
+    let pointer = before + gap / 2;
+
+    let with_tolerance = |gap_tolerance| {
+        let options = MemoryOptions {
+            gap_tolerance,
+            ..MemoryOptions::default()
+        };
+        Memory::with_options(pid(), options).map(|memory| memory.is_pointer_valid(pointer))
+    };
+    assert!(!with_tolerance(0)?);
+    assert!(!with_tolerance(gap - 1)?);
+    assert!(with_tolerance(gap)?);
+    Ok(())
+}
+
+/// a marker followed by `call rel32`, the displacement is filled in by the test
+#[repr(C)]
+struct CallSite {
+    marker: [u8; 11],
+    call: u8,
+    displacement: AtomicI32,
+}
+
+static CALL_SITE: CallSite = CallSite {
+    marker: [
+        0x43, 0x41, 0x4C, 0x4C, 0x9E, 0x17, 0xB4, 0x62, 0x0D, 0xF3, 0x58,
+    ],
+    call: 0xE8,
+    displacement: AtomicI32::new(0),
+};
+
+#[test]
+fn test_call_target() -> Result<(), MemoryError> {
+    let call_end = address(&CALL_SITE.displacement) + 4;
+    let point_at = |target: usize| {
+        let displacement = target as isize - call_end as isize;
+        CALL_SITE
+            .displacement
+            .store(displacement as i32, AtomicOrdering::SeqCst);
+    };
+
+    let marker = CALL_SITE.marker.map(|byte| format!("{byte:02X}")).join(" ");
+    let locator = parse_address_locator(&format!("{marker} E8 call@12")).unwrap();
+    assert_eq!(locator.to_string(), format!("{marker} E8 call@12"));
+
+    let memory = Memory::new(pid())?;
+    let function = pid as fn() -> i32 as usize;
+    point_at(function);
+    assert_eq!(locator.resolve(&memory)?, function);
+
+    point_at(address(&HELPER_STATIC));
+    assert!(matches!(
+        locator.resolve(&memory),
+        Err(MemoryError::NotExecutable(target)) if target == address(&HELPER_STATIC)
+    ));
+    Ok(())
+}
+
+/// set for the copy of the test binary that runs `helper_process`
+const HELPER_ENV: &str = "MEMUTIL_TEST_HELPER";
+const HELPER_MARKER: [u8; 13] = [
+    0x4D, 0x55, 0x54, 0x49, 0x4C, 0xE7, 0x91, 0x0B, 0x5F, 0xA2, 0x3C, 0xD4, 0x68,
+];
+const HELPER_VALUE: u64 = 0xC0FF_EED0_0D15;
+
+/// what the helper is found by: the marker, then `lea rax, [rip+displacement]` pointing at
+/// `HELPER_STATIC`, then a pointer to a heap allocation
+#[repr(C)]
+struct HelperAnchor {
+    marker: [u8; 13],
+    lea: [u8; 3],
+    displacement: AtomicI32,
+    pointer: AtomicUsize,
+}
+
+static HELPER_ANCHOR: HelperAnchor = HelperAnchor {
+    marker: HELPER_MARKER,
+    lea: [0x48, 0x8D, 0x05],
+    displacement: AtomicI32::new(0),
+    pointer: AtomicUsize::new(0),
+};
+static HELPER_STATIC: u64 = HELPER_VALUE;
+
+#[repr(C)]
+struct HelperHeap {
+    health: i32,
+    speed: f32,
+}
+
+/// a second copy of the test binary running `helper_process`, so reads cross a process
+/// boundary. it is killed when dropped
+struct HelperProcess {
+    child: Child,
+    speed_address: usize,
+}
+
+impl HelperProcess {
+    fn spawn() -> Self {
+        let mut child = Command::new(std::env::current_exe().unwrap())
+            .args([
+                "--exact",
+                "tests::helper_process",
+                "--ignored",
+                "--nocapture",
+            ])
+            .env(HELPER_ENV, "1")
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .spawn()
+            .unwrap();
+
+        // libtest prints its own output around the helper's, even on the same line
+        let stdout = BufReader::new(child.stdout.take().unwrap());
+        let speed_address = stdout
+            .lines()
+            .map_while(Result::ok)
+            .find_map(|line| {
+                let (_, address) = line.split_once("helper ready 0x")?;
+                usize::from_str_radix(address, 16).ok()
+            })
+            .expect("helper process exited before it was ready");
+
+        Self {
+            child,
+            speed_address,
+        }
+    }
+
+    fn pid(&self) -> i32 {
+        self.child.id().cast_signed()
+    }
+
+    /// the pattern of `HELPER_ANCHOR`, with `@16/20` resolving the lea to `HELPER_STATIC`
+    fn signature(rip_relative: bool) -> IdaSignature {
+        let mut pattern: Vec<_> = HELPER_MARKER.iter().copied().map(Some).collect();
+        pattern.extend([Some(0x48), Some(0x8D), Some(0x05)]);
+        let offset = rip_relative.then_some(Offset {
+            offset: 16,
+            instruction_size: 20,
+            branch: false,
+        });
+        IdaSignature::new(pattern, offset)
+    }
+}
+
+impl Drop for HelperProcess {
+    fn drop(&mut self) {
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
+
+#[test]
+#[ignore = "only runs as the helper process of the end-to-end tests"]
+fn helper_process() {
+    if std::env::var_os(HELPER_ENV).is_none() {
+        return;
+    }
+
+    let heap = Box::new(HelperHeap {
+        health: 1337,
+        speed: 2.5,
+    });
+    HELPER_ANCHOR
+        .pointer
+        .store(address(&*heap), AtomicOrdering::SeqCst);
+    let instruction_end = address(&HELPER_ANCHOR) + 20;
+    let displacement = address(&HELPER_STATIC) as isize - instruction_end as isize;
+    HELPER_ANCHOR
+        .displacement
+        .store(displacement as i32, AtomicOrdering::SeqCst);
+
+    println!("helper ready 0x{:x}", address(&heap.speed));
+    // the parent closes stdin or kills the helper when it is done reading
+    let _ = std::io::stdin().read_to_end(&mut Vec::new());
+    assert_eq!(heap.health, 1337);
+}
+
+#[test]
+fn test_helper_pointer_chain() -> Result<(), MemoryError> {
+    let helper = HelperProcess::spawn();
+    let memory = Memory::new(helper.pid())?;
+
+    // marker -> pointer at 0x18 -> speed at 0x4
+    let chain = AddressLocator::PointerChain(HelperProcess::signature(false), vec![0x18, 0x4]);
+    let address = chain.resolve(&memory)?;
+    assert_eq!(address, helper.speed_address);
+    assert!(DataType::F32.read(&memory, address)? == Value::F32(2.5));
+    assert!(DataType::I32.read(&memory, address - 4)? == Value::I32(1337));
+
+    Ok(())
+}
+
+/// whether each thread of the process is in a ptrace stop, by thread id
+fn traced_threads(pid: i32) -> Vec<(i32, bool)> {
+    let mut threads: Vec<_> = std::fs::read_dir(format!("/proc/{pid}/task"))
+        .unwrap()
+        .map(|entry| {
+            let tid = entry
+                .unwrap()
+                .file_name()
+                .to_str()
+                .unwrap()
+                .parse()
+                .unwrap();
+            let stat = std::fs::read_to_string(format!("/proc/{pid}/task/{tid}/stat")).unwrap();
+            let (_, fields) = stat.rsplit_once(") ").unwrap();
+            (tid, fields.starts_with('t'))
+        })
+        .collect();
+    threads.sort();
+    threads
+}
+
+#[test]
+fn test_helper_attach() -> Result<(), MemoryError> {
+    let helper = HelperProcess::spawn();
+    let mut memory = Memory::new(helper.pid())?;
+    // libtest runs the helper in a thread of its own, next to the main thread
+    assert!(traced_threads(helper.pid()).len() > 1);
+
+    memory.attach()?;
+    for (tid, traced) in traced_threads(helper.pid()) {
+        assert_eq!(traced, tid == helper.pid(), "thread {tid}");
+    }
+    memory.detach()?;
+    assert!(
+        traced_threads(helper.pid())
+            .iter()
+            .all(|(_, traced)| !traced)
+    );
+
+    memory.attach_all()?;
+    assert!(
+        traced_threads(helper.pid())
+            .iter()
+            .all(|(_, traced)| *traced)
+    );
+    drop(memory);
+    assert!(
+        traced_threads(helper.pid())
+            .iter()
+            .all(|(_, traced)| !traced)
+    );
+    Ok(())
+}
+
+#[test]
+fn test_helper_rip_relative_signature() -> Result<(), MemoryError> {
+    let helper = HelperProcess::spawn();
+    let memory = Memory::new(helper.pid())?;
+
+    let signature = HelperProcess::signature(true);
+    assert_eq!(memory.scan_signature_all(&signature)?.len(), 1);
+
+    let address = AddressLocator::Pattern(signature).resolve(&memory)?;
+    assert!(DataType::U64.read(&memory, address)? == Value::U64(HELPER_VALUE));
+
+    Ok(())
+}
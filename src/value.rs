@@ -1,7 +1,11 @@
-use std::fmt::Display;
+use std::{cmp::Ordering, fmt::Display};
 
-use glam::{Mat4, Vec2, Vec3, Vec4};
+use glam::{DVec2, DVec3, DVec4, Mat4, Vec2, Vec3, Vec4};
+use serde::{Serialize, Serializer, ser::SerializeStruct as _};
 
+/// values only equal values of the same variant, so `U8(1)` and `I8(1)` are never equal.
+/// ordering is defined for integers, floats, fixed point numbers, counters and pointers, any
+/// other pair of values is unordered unless it is equal
 #[derive(Debug, PartialEq)]
 pub enum Value {
     U8(u8),
@@ -14,21 +18,165 @@ pub enum Value {
     I32(i32),
     I64(i64),
 
+    F16(f32),
     F32(f32),
     F64(f64),
 
     Pointer(usize),
     Pointer32(u32),
     Pointer64(u64),
+    PointerArray(Vec<usize>),
 
     Vec2(Vec2),
     Vec3(Vec3),
     Vec4(Vec4),
     Mat4(Mat4),
 
+    DVec2(DVec2),
+    DVec3(DVec3),
+    DVec4(DVec4),
+
     Rgb([u8; 3]),
     Rgba([u8; 4]),
     Color32([f32; 4]),
+
+    Flags(u64, Vec<String>),
+
+    Fixed(f64),
+    Counter(u64),
+
+    /// decoded char and the number of bytes it took
+    Char(char, usize),
+    String(String),
+    CString(String),
+    WString(String),
+
+    Array(Vec<Value>),
+}
+
+impl Value {
+    /// numeric value as a float, `None` for pointers, vectors, colors and other non-scalars
+    pub fn as_f64(&self) -> Option<f64> {
+        let value = match self {
+            Value::U8(v) => *v as f64,
+            Value::U16(v) => *v as f64,
+            Value::U32(v) => *v as f64,
+            Value::U64(v) => *v as f64,
+
+            Value::I8(v) => *v as f64,
+            Value::I16(v) => *v as f64,
+            Value::I32(v) => *v as f64,
+            Value::I64(v) => *v as f64,
+
+            Value::F16(v) => *v as f64,
+            Value::F32(v) => *v as f64,
+            Value::F64(v) => *v,
+
+            Value::Fixed(v) => *v,
+            Value::Counter(v) => *v as f64,
+
+            _ => return None,
+        };
+        Some(value)
+    }
+
+    /// whether values of this variant have an order beyond equality
+    pub fn is_ordered(&self) -> bool {
+        matches!(
+            self,
+            Value::U8(_)
+                | Value::U16(_)
+                | Value::U32(_)
+                | Value::U64(_)
+                | Value::I8(_)
+                | Value::I16(_)
+                | Value::I32(_)
+                | Value::I64(_)
+                | Value::F16(_)
+                | Value::F32(_)
+                | Value::F64(_)
+                | Value::Pointer(_)
+                | Value::Pointer32(_)
+                | Value::Pointer64(_)
+                | Value::Fixed(_)
+                | Value::Counter(_)
+        )
+    }
+
+    /// whether the value differs from `previous`. floats, vectors and matrices only count as
+    /// changed once a component moved by more than `epsilon`
+    pub fn changed_from(&self, previous: &Value, epsilon: f64) -> bool {
+        match (self.float_components(), previous.float_components()) {
+            (Some(current), Some(previous)) if current.len() == previous.len() => {
+                current.iter().zip(&previous).any(|(current, previous)| {
+                    current.is_nan() != previous.is_nan() || (current - previous).abs() > epsilon
+                })
+            }
+            _ => self != previous,
+        }
+    }
+
+    fn float_components(&self) -> Option<Vec<f64>> {
+        let widen = |components: &[f32]| components.iter().map(|&v| v as f64).collect();
+        let components = match self {
+            Value::F16(v) | Value::F32(v) => vec![*v as f64],
+            Value::F64(v) | Value::Fixed(v) => vec![*v],
+
+            Value::Vec2(v) => widen(&v.to_array()),
+            Value::Vec3(v) => widen(&v.to_array()),
+            Value::Vec4(v) => widen(&v.to_array()),
+            Value::Mat4(v) => widen(&v.to_cols_array()),
+            Value::Color32(v) => widen(v),
+
+            Value::DVec2(v) => v.to_array().to_vec(),
+            Value::DVec3(v) => v.to_array().to_vec(),
+            Value::DVec4(v) => v.to_array().to_vec(),
+
+            _ => return None,
+        };
+        Some(components)
+    }
+
+    /// how far a counter advanced since `previous`, a counter that wrapped around still yields
+    /// the distance it moved
+    pub fn counter_delta(&self, previous: &Value) -> Option<u64> {
+        match (self, previous) {
+            (Value::Counter(current), Value::Counter(previous)) => {
+                Some(current.wrapping_sub(*previous))
+            }
+            _ => None,
+        }
+    }
+}
+
+impl PartialOrd for Value {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        match (self, other) {
+            (Value::U8(a), Value::U8(b)) => a.partial_cmp(b),
+            (Value::U16(a), Value::U16(b)) => a.partial_cmp(b),
+            (Value::U32(a), Value::U32(b)) => a.partial_cmp(b),
+            (Value::U64(a), Value::U64(b)) => a.partial_cmp(b),
+
+            (Value::I8(a), Value::I8(b)) => a.partial_cmp(b),
+            (Value::I16(a), Value::I16(b)) => a.partial_cmp(b),
+            (Value::I32(a), Value::I32(b)) => a.partial_cmp(b),
+            (Value::I64(a), Value::I64(b)) => a.partial_cmp(b),
+
+            (Value::F16(a), Value::F16(b)) => a.partial_cmp(b),
+            (Value::F32(a), Value::F32(b)) => a.partial_cmp(b),
+            (Value::F64(a), Value::F64(b)) => a.partial_cmp(b),
+
+            (Value::Pointer(a), Value::Pointer(b)) => a.partial_cmp(b),
+            (Value::Pointer32(a), Value::Pointer32(b)) => a.partial_cmp(b),
+            (Value::Pointer64(a), Value::Pointer64(b)) => a.partial_cmp(b),
+
+            (Value::Fixed(a), Value::Fixed(b)) => a.partial_cmp(b),
+            (Value::Counter(a), Value::Counter(b)) => a.partial_cmp(b),
+
+            // keeps `partial_cmp` consistent with `==` for values without an order
+            _ => (self == other).then_some(Ordering::Equal),
+        }
+    }
 }
 
 impl Display for Value {
@@ -44,21 +192,105 @@ impl Display for Value {
             Value::I32(v) => write!(f, "{}i32", v),
             Value::I64(v) => write!(f, "{}i64", v),
 
+            Value::F16(v) => write!(f, "{:?}f16", v),
             Value::F32(v) => write!(f, "{:?}f32", v),
             Value::F64(v) => write!(f, "{:?}f64", v),
 
             Value::Pointer(v) => write!(f, "0x{:x}", v),
             Value::Pointer32(v) => write!(f, "0x{:x}", v),
             Value::Pointer64(v) => write!(f, "0x{:x}", v),
+            Value::PointerArray(v) => {
+                let pointers = v
+                    .iter()
+                    .map(|pointer| format!("0x{pointer:x}"))
+                    .collect::<Vec<_>>()
+                    .join(", ");
+                write!(f, "[{pointers}]")
+            }
 
             Value::Vec2(v) => write!(f, "{}", v),
             Value::Vec3(v) => write!(f, "{}", v),
             Value::Vec4(v) => write!(f, "{}", v),
             Value::Mat4(v) => write!(f, "{}", v),
 
+            Value::DVec2(v) => write!(f, "{}", v),
+            Value::DVec3(v) => write!(f, "{}", v),
+            Value::DVec4(v) => write!(f, "{}", v),
+
             Value::Rgb(v) => write!(f, "#{:02x}{:02x}{:02x}", v[0], v[1], v[2]),
             Value::Rgba(v) => write!(f, "#{:02x}{:02x}{:02x}{:02x}", v[0], v[1], v[2], v[3]),
             Value::Color32(v) => write!(f, "({:?}, {:?}, {:?}, {:?})", v[0], v[1], v[2], v[3]),
+
+            Value::Flags(raw, names) => write!(f, "0x{raw:x} [{}]", names.join(", ")),
+
+            Value::Fixed(v) => write!(f, "{v:?}fixed"),
+            Value::Counter(v) => write!(f, "{v}"),
+
+            Value::Char(v, 1) => write!(f, "{v:?}"),
+            Value::Char(v, len) => write!(f, "{v:?} ({len} bytes)"),
+            Value::String(v) | Value::CString(v) | Value::WString(v) => write!(f, "{v:?}"),
+
+            Value::Array(values) => {
+                let values = values
+                    .iter()
+                    .map(Value::to_string)
+                    .collect::<Vec<_>>()
+                    .join(", ");
+                write!(f, "[{values}]")
+            }
+        }
+    }
+}
+
+/// numbers stay numbers, pointers and colors are strings in the same format as the text output,
+/// vectors are arrays and matrices arrays of columns
+impl Serialize for Value {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self {
+            Value::U8(v) => serializer.serialize_u8(*v),
+            Value::U16(v) => serializer.serialize_u16(*v),
+            Value::U32(v) => serializer.serialize_u32(*v),
+            Value::U64(v) | Value::Counter(v) => serializer.serialize_u64(*v),
+
+            Value::I8(v) => serializer.serialize_i8(*v),
+            Value::I16(v) => serializer.serialize_i16(*v),
+            Value::I32(v) => serializer.serialize_i32(*v),
+            Value::I64(v) => serializer.serialize_i64(*v),
+
+            Value::F16(v) | Value::F32(v) => serializer.serialize_f32(*v),
+            Value::F64(v) | Value::Fixed(v) => serializer.serialize_f64(*v),
+
+            Value::Pointer(_)
+            | Value::Pointer32(_)
+            | Value::Pointer64(_)
+            | Value::Rgb(_)
+            | Value::Rgba(_) => serializer.collect_str(self),
+            Value::PointerArray(v) => {
+                serializer.collect_seq(v.iter().map(|pointer| format!("0x{pointer:x}")))
+            }
+
+            Value::Vec2(v) => v.to_array().serialize(serializer),
+            Value::Vec3(v) => v.to_array().serialize(serializer),
+            Value::Vec4(v) => v.to_array().serialize(serializer),
+            Value::Mat4(v) => v.to_cols_array_2d().serialize(serializer),
+
+            Value::DVec2(v) => v.to_array().serialize(serializer),
+            Value::DVec3(v) => v.to_array().serialize(serializer),
+            Value::DVec4(v) => v.to_array().serialize(serializer),
+
+            Value::Color32(v) => v.serialize(serializer),
+
+            Value::Flags(raw, names) => {
+                let mut flags = serializer.serialize_struct("Flags", 2)?;
+                flags.serialize_field("raw", raw)?;
+                flags.serialize_field("names", names)?;
+                flags.end()
+            }
+
+            Value::Char(v, _) => serializer.serialize_char(*v),
+            Value::String(v) | Value::CString(v) | Value::WString(v) => serializer.serialize_str(v),
+
+            Value::Array(values) => serializer.collect_seq(values),
         }
     }
 }